@@ -1,4 +1,14 @@
 use crate::utils::error;
+use crate::model::{
+    grid,
+    universe::Universe,
+    session::Session,
+    universe_set::{
+        RcUniverseSet,
+        UniverseSet,
+    },
+    library::PatternLibrary,
+};
 use crate::view::{
     renderer::{
         RendererManager
@@ -7,88 +17,516 @@ use crate::view::{
         self,
         ThemeManager,
     },
+    text::{
+        self,
+        TextRenderer,
+    },
+    locale::{
+        self,
+        LocaleManager,
+    },
+    keymap::{
+        self,
+        Action,
+        Keymap,
+        Scope,
+    },
+    window_state::WindowState,
     widget::{
         layout,
+        layout_spec::LayoutSpec,
         camera,
         widget_base,
+        event::WidgetEvent,
     },
 };
 
 use sdl2::{
     event::{
-        Event
+        Event,
+        WindowEvent,
     },
+    video::FullscreenType,
 };
 
 use std::{
     rc::Rc,
     cell::RefCell,
+    time::{
+        Duration,
+        Instant,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 
+static GRID_UPPER_SCALE: i32 = 1000;
+static STEP_INTERVAL: Duration = Duration::from_millis(100);
+/// 每帧用于推进控制台`step`命令的时间
+static PENDING_STEP_BUDGET: Duration = Duration::from_millis(8);
+static PATTERN_DIR_ARG: &str = "--pattern-dir";
+static KEYMAP_ARG: &str = "--keymap";
+static DEFAULT_KEYMAP_PATH: &str = "keymap.toml";
+static THEME_ARG: &str = "--theme";
+static DEFAULT_THEME_PATH: &str = "theme.toml";
+static THEME_POLL_INTERVAL: Duration = Duration::from_millis(500);
+static LAYOUT_ARG: &str = "--layout";
+static DEFAULT_LAYOUT_PATH: &str = "layout.toml";
+static WINDOW_ARG: &str = "--window";
+static DEFAULT_WINDOW_PATH: &str = "window.toml";
+static LOCALE_ARG: &str = "--locale";
+static LOCALE_DIR_ARG: &str = "--locale-dir";
+static DEFAULT_LOCALE_DIR: &str = "locales";
+static SESSION_ARG: &str = "--session";
+static TOPOLOGY_ARG: &str = "--topology";
+static DEFAULT_SESSION_PATH: &str = "session.toml";
+
 
 pub struct GameContext {
     sdl_context: sdl2::Sdl,
     theme_manager: theme::RcThemeManager,
+    text_renderer: text::RcTextRenderer,
+    locale: locale::RcLocaleManager,
+    keymap: keymap::RcKeymap,
     renderer_manager: RendererManager,
     layout_manager: layout::LayoutManager,
+    universes: RcUniverseSet,
+    /// 没有窗口状态文件且未指定`--window`时为None, 退出时不写文件
+    window_state_path: Option<PathBuf>,
+    session_path: PathBuf,
+    /// 启动时读入的会话, 读取失败时为None, 此时退出时不写回, 以免覆盖原来的文件
+    session: Option<Session>,
 }
 
 impl GameContext {
     pub fn init() -> Result<Self, error::DynError> {
         let mut sdl_context = sdl2::init()?;
-        let theme_manager = ThemeManager::default_init()?;
-        let theme_manager = Rc::new(RefCell::new(theme_manager));
-        let renderer_manager = RendererManager::new(theme_manager.clone(), &mut sdl_context)?;
-        let layout_manager = layout::LayoutManager::new(theme_manager.clone(), renderer_manager.get_shared_canvas());
+        let theme_manager = Rc::new(RefCell::new(Self::load_theme()?));
+        let text_renderer = Rc::new(RefCell::new(TextRenderer::new()?));
+        let locale = Rc::new(RefCell::new(Self::load_locale()));
+        let keymap = Rc::new(RefCell::new(Self::load_keymap()));
+        let (window_state_path, window_state) = Self::load_window_state();
+        let renderer_manager = RendererManager::new(theme_manager.clone(), text_renderer.clone(),
+            locale.clone(), keymap.clone(), &window_state, &mut sdl_context)?;
+        let grid = grid::CellGrid::new(GRID_UPPER_SCALE).with_topology(Self::topology());
+        let universe = Universe::new(grid);
+        let universes = Rc::new(RefCell::new(UniverseSet::new(Rc::new(RefCell::new(universe)))));
+        let library = Rc::new(RefCell::new(Self::load_library()));
+        let layout_manager = layout::LayoutManager::new(theme_manager.clone(),
+            text_renderer.clone(), locale.clone(), keymap.clone(), renderer_manager.get_shared_canvas(),
+            universes.clone(), library);
 
         let game_context = GameContext{
             sdl_context,
             theme_manager,
+            text_renderer,
+            locale,
+            keymap,
             renderer_manager,
             layout_manager,
+            universes,
+            window_state_path,
+            session_path: PathBuf::from(Self::arg_values(SESSION_ARG)
+                .pop()
+                .unwrap_or_else(|| DEFAULT_SESSION_PATH.to_string())),
+            session: Some(Session::default()),
+        };
+        let dpi_scale = game_context.dpi_scale();
+        let custom_font = {
+            let theme_manager = game_context.theme_manager.borrow();
+            theme_manager.font_path().is_some() || !theme_manager.fallback_fonts().is_empty()
+                || theme_manager.font_size() != text::FONT_SIZE
         };
+        if (custom_font || dpi_scale != 1.)
+            && let Err(e) = game_context.apply_font(dpi_scale) {
+            eprintln!("{}", e);
+        }
         Ok(game_context)
     }
+
+    /// 命令行中所有 `<name> <value>` 形式参数的值
+    fn arg_values(name: &str) -> Vec<String> {
+        let mut values = Vec::new();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == name
+                && let Some(value) = args.next() {
+                values.push(value);
+            }
+        }
+        values
+    }
+
+    /// `--topology <bounded|torus>`指定网格边界的处理方式, 默认为bounded
+    /// 布局中的其他宇宙与main使用相同的拓扑
+    fn topology() -> grid::Topology {
+        match Self::arg_values(TOPOLOGY_ARG).pop() {
+            Some(name) => grid::Topology::from_name(&name).unwrap_or_else(|| {
+                eprintln!("unknown topology `{}`, expected bounded or torus", name);
+                grid::Topology::Bounded
+            }),
+            None => grid::Topology::Bounded,
+        }
+    }
+
+    /// 内置图案加上命令行中以`--pattern-dir <DIR>`指定的目录, 无法读取的目录被忽略
+    fn load_library() -> PatternLibrary {
+        let mut library = PatternLibrary::builtin();
+        for dir in Self::arg_values(PATTERN_DIR_ARG) {
+            if let Err(e) = library.add_directory(&PathBuf::from(&dir)) {
+                eprintln!("failed to load pattern directory `{}`: {}", dir, e);
+            }
+        }
+        library
+    }
+
+    /// 读取`--theme <FILE>`指定的文件, 未指定时读取工作目录下的theme.toml(如果存在)
+    /// 文件无法解析时使用默认主题, 运行期间文件的修改会被重新读取
+    fn load_theme() -> error::Result<ThemeManager> {
+        let path = match Self::arg_values(THEME_ARG).pop() {
+            Some(path) => PathBuf::from(path),
+            None if Path::new(DEFAULT_THEME_PATH).exists() => PathBuf::from(DEFAULT_THEME_PATH),
+            None => return ThemeManager::default_init(),
+        };
+        ThemeManager::from_file(path.clone()).or_else(|e| {
+            eprintln!("failed to load theme `{}`: {}, using defaults", path.display(), e);
+            ThemeManager::default_init()
+        })
+    }
+
+    /// 内置语言加上`--locale-dir <DIR>`(默认为工作目录下的locales)中的语言文件
+    /// 使用`--locale <ID>`指定的语言, 未指定时跟随系统语言
+    fn load_locale() -> LocaleManager {
+        let mut locale = LocaleManager::bundled();
+        let dir = match Self::arg_values(LOCALE_DIR_ARG).pop() {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None if Path::new(DEFAULT_LOCALE_DIR).is_dir() => Some(PathBuf::from(DEFAULT_LOCALE_DIR)),
+            None => None,
+        };
+        if let Some(dir) = dir {
+            match locale.load_dir(&dir) {
+                Ok(problems) => problems
+                    .iter()
+                    .for_each(|problem| eprintln!("locale: {}", problem)),
+                Err(e) => eprintln!("failed to load locale directory `{}`: {}", dir.display(), e),
+            }
+        }
+        match Self::arg_values(LOCALE_ARG).pop() {
+            Some(id) => if let Err(e) = locale.select(&id) {
+                eprintln!("{}", e);
+            },
+            None => locale.select_system(),
+        }
+        locale
+    }
+
+    /// 读取`--layout <FILE>`指定的文件, 未指定时读取工作目录下的layout.toml(如果存在)
+    /// 文件无效时使用默认布局
+    fn load_layout(&self) -> error::Result<layout::Layout> {
+        let path = match Self::arg_values(LAYOUT_ARG).pop() {
+            Some(path) => PathBuf::from(path),
+            None if Path::new(DEFAULT_LAYOUT_PATH).exists() => PathBuf::from(DEFAULT_LAYOUT_PATH),
+            None => return self.layout_manager.get_default_layout(),
+        };
+        LayoutSpec::load(&path)
+            .and_then(|spec| self.layout_manager.build_layout(&spec))
+            .or_else(|e| {
+                eprintln!("failed to load layout `{}`: {}, using the default layout", path.display(), e);
+                self.layout_manager.get_default_layout()
+            })
+    }
+
+    /// 读取`--window <FILE>`指定的文件, 未指定时使用工作目录下的window.toml
+    /// 文件不存在或无效时使用默认设置, 退出时写回同一个文件
+    /// 只有文件已经存在或者指定了`--window`时才会写回, 不会在工作目录下留下新文件
+    /// 文件无法解析时也不写回, 以免覆盖手动编辑的内容
+    fn load_window_state() -> (Option<PathBuf>, WindowState) {
+        let path = match Self::arg_values(WINDOW_ARG).pop() {
+            Some(path) => PathBuf::from(path),
+            None if Path::new(DEFAULT_WINDOW_PATH).exists() => PathBuf::from(DEFAULT_WINDOW_PATH),
+            None => return (None, WindowState::default()),
+        };
+        if !path.exists() {
+            return (Some(path), WindowState::default());
+        }
+        match WindowState::load(&path) {
+            Ok(state) => (Some(path), state),
+            Err(e) => {
+                eprintln!("failed to load window state: {}, using defaults, it will not be saved on exit", e);
+                (None, WindowState::default())
+            },
+        }
+    }
+
+    fn save_window_state(&self) {
+        let Some(path) = &self.window_state_path else {
+            return;
+        };
+        let state = self.renderer_manager.window_state();
+        if let Err(e) = state.save(path) {
+            eprintln!("failed to save window state `{}`: {}", path.display(), e);
+        }
+    }
+
+    /// 读取`--session <FILE>`指定的文件, 未指定时使用工作目录下的session.toml
+    /// 布局中的宇宙创建之后才能恢复, 退出时写回同一个文件
+    fn restore_session(&mut self) {
+        if !self.session_path.exists() {
+            return;
+        }
+        match Session::load(&self.session_path) {
+            Ok(session) => {
+                session
+                    .restore(&self.universes.borrow())
+                    .iter()
+                    .for_each(|name| eprintln!("session: universe `{}` is not in the layout", name));
+                self.session = Some(session);
+            },
+            Err(e) => {
+                eprintln!("failed to load session: {}, it will not be saved on exit", e);
+                self.session = None;
+            },
+        }
+    }
+
+    /// 没有标注且文件不存在时不创建文件
+    fn save_session(&self) {
+        let Some(previous) = &self.session else {
+            return;
+        };
+        let session = Session::capture(&self.universes.borrow(), previous);
+        if session.is_empty() && !self.session_path.exists() {
+            return;
+        }
+        if let Err(e) = session.save(&self.session_path) {
+            eprintln!("failed to save session `{}`: {}", self.session_path.display(), e);
+        }
+    }
+
+    /// 高DPI下按绘制区域与窗口尺寸之比放大字号, 使文字的显示大小不变
+    fn dpi_scale(&self) -> f64 {
+        self.renderer_manager.pixel_scale().1
+    }
+
+    fn apply_font(&self, dpi_scale: f64) -> error::Result<()> {
+        let theme_manager = self.theme_manager.borrow();
+        let size = (theme_manager.font_size() as f64 * dpi_scale).round().max(1.) as u16;
+        self.text_renderer
+            .borrow_mut()
+            .set_font(theme_manager.font_path(), theme_manager.fallback_fonts(), size)
+    }
+
+    /// 读取`--keymap <FILE>`指定的文件, 未指定时读取工作目录下的keymap.toml(如果存在)
+    /// 文件无法解析时使用默认绑定
+    fn load_keymap() -> Keymap {
+        let path = match Self::arg_values(KEYMAP_ARG).pop() {
+            Some(path) => PathBuf::from(path),
+            None if Path::new(DEFAULT_KEYMAP_PATH).exists() => PathBuf::from(DEFAULT_KEYMAP_PATH),
+            None => return Keymap::defaults(),
+        };
+        match Keymap::load(&path) {
+            Ok(keymap) => {
+                keymap.problems()
+                    .iter()
+                    .for_each(|problem| eprintln!("keymap: {}", problem));
+                keymap
+            },
+            Err(e) => {
+                eprintln!("failed to load keymap `{}`: {}, using defaults", path.display(), e);
+                Keymap::defaults()
+            },
+        }
+    }
 }
 
 pub struct GameManager {
     game_context: GameContext,
     event_pump: sdl2::EventPump,
+    running: bool,
+    last_step: Instant,
+    last_theme_poll: Instant,
+    theme_revision: u64,
+    locale_revision: u64,
+    /// 当前字号对应的DPI比例
+    dpi_scale: f64,
 }
 
 impl GameManager {
     pub fn new(game_context: GameContext) -> Result<Self, error::DynError> {
         let event_pump = game_context.sdl_context.event_pump()?;
+        let theme_revision = game_context.theme_manager.borrow().revision();
+        let locale_revision = game_context.locale.borrow().revision();
+        let dpi_scale = game_context.dpi_scale();
         let controller_manager = GameManager{
             game_context,
-            event_pump
+            event_pump,
+            running: false,
+            last_step: Instant::now(),
+            last_theme_poll: Instant::now(),
+            theme_revision,
+            locale_revision,
+            dpi_scale,
         };
         Ok(controller_manager)
     }
 
+    /// 运行状态下按固定间隔推进一代, 编辑在运行与暂停时都可以进行
+    /// 控制台`step`命令的代数分散到各帧中推进
+    fn update_simulation(&mut self) {
+        self.game_context.universes.borrow_mut().run_pending(PENDING_STEP_BUDGET);
+        if self.running && self.last_step.elapsed() >= STEP_INTERVAL {
+            self.game_context.universes.borrow().step();
+            self.last_step = Instant::now();
+        }
+    }
+
+    /// 定期检查主题文件, 主题改变(文件修改或控制台切换)后重绘所有widget
+    fn update_theme(&mut self, layout: &mut layout::Layout) -> error::Result<()> {
+        if self.last_theme_poll.elapsed() >= THEME_POLL_INTERVAL {
+            self.last_theme_poll = Instant::now();
+            let reloaded = self.game_context.theme_manager.borrow_mut().reload_if_changed();
+            if let Some(Err(e)) = reloaded {
+                eprintln!("failed to reload theme: {}", e);
+            }
+        }
+
+        let revision = self.game_context.theme_manager.borrow().revision();
+        if revision == self.theme_revision {
+            return Ok(());
+        }
+        self.theme_revision = revision;
+        if let Err(e) = self.game_context.apply_font(self.dpi_scale) {
+            eprintln!("{}", e);
+        }
+        self.game_context.renderer_manager.apply_window_theme()?;
+        layout.invalidate();
+        Ok(())
+    }
+
+    /// 切换语言(快捷键或控制台)后更新窗口标题并重绘所有widget
+    fn update_locale(&mut self, layout: &mut layout::Layout) -> error::Result<()> {
+        let revision = self.game_context.locale.borrow().revision();
+        if revision == self.locale_revision {
+            return Ok(());
+        }
+        self.locale_revision = revision;
+        self.game_context.renderer_manager.apply_window_theme()?;
+        layout.invalidate();
+        Ok(())
+    }
+
     pub fn main_loop(&mut self) -> Result<(), error::DynError>{
-        let mut layout = self.game_context.layout_manager.get_default_layout()?;
+        let mut layout = self.game_context.load_layout()?;
+        self.game_context.restore_session();
         loop {
-            if self.handle_event()? {
+            let frame_start = Instant::now();
+            if self.handle_event(&mut layout)? {
                 break;
             }
+            self.update_simulation();
+            self.update_theme(&mut layout)?;
+            self.update_locale(&mut layout)?;
             self.game_context.renderer_manager.views_iterator(&mut layout)?;
+            if let Some(interval) = self.game_context.renderer_manager.frame_interval() {
+                std::thread::sleep(interval.saturating_sub(frame_start.elapsed()));
+            }
         };
-        
+        self.game_context.save_window_state();
+        self.game_context.save_session();
+
         Ok(())
     }
     
-    fn handle_event(&mut self) -> Result<bool, error::DynError> {
-        for event in self.event_pump.poll_iter() {
+    fn handle_event(&mut self, layout: &mut layout::Layout) -> Result<bool, error::DynError> {
+        let events: Vec<_> = self.event_pump.poll_iter().collect();
+        let pixel_scale = self.game_context.renderer_manager.pixel_scale();
+        for event in events {
+            let widget_event = WidgetEvent::from_sdl(&event).map(|event| event.scaled(pixel_scale));
+            if let Some(widget_event) = &widget_event {
+                self.game_context.renderer_manager.track_pointer(widget_event);
+            }
+            if let Some(widget_event) = &widget_event
+                && layout.dispatch_event(widget_event).is_handled() {
+                continue;
+            }
+
             match event {
                 Event::Quit {..} => {
                     return Ok(true);
                 },
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..) | WindowEvent::DisplayChanged(..), ..
+                } => {
+                    // 布局按窗口尺寸重新计算, 旧的纹理缓存全部作废
+                    // 移动到另一个显示器时DPI可能改变, 字号随之调整
+                    self.update_dpi_scale();
+                    layout.invalidate();
+                },
                 _ => {}
             }
+
+            let action = widget_event.and_then(|widget_event| self.game_context.keymap
+                .borrow()
+                .event_action(&widget_event, Scope::Global));
+            if let Some(action) = action
+                && self.handle_action(action) {
+                return Ok(true);
+            }
         }
 
         Ok(false)
     }
+
+    /// 窗口移动到DPI不同的显示器后重新设置字号
+    fn update_dpi_scale(&mut self) {
+        let dpi_scale = self.game_context.dpi_scale();
+        if dpi_scale == self.dpi_scale {
+            return;
+        }
+        self.dpi_scale = dpi_scale;
+        if let Err(e) = self.game_context.apply_font(dpi_scale) {
+            eprintln!("{}", e);
+        }
+    }
+
+    /// 返回true时退出主循环
+    fn handle_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::Quit => return true,
+            Action::ToggleFullscreen | Action::ToggleExclusiveFullscreen => {
+                let fullscreen = if action == Action::ToggleFullscreen {
+                    FullscreenType::Desktop
+                } else {
+                    FullscreenType::True
+                };
+                // 窗口尺寸的变化会以SizeChanged事件到达, 届时重新布局
+                if let Err(e) = self.game_context.renderer_manager.toggle_fullscreen(fullscreen) {
+                    eprintln!("failed to toggle fullscreen: {}", e);
+                }
+            },
+            Action::CycleLanguage => self.game_context.locale.borrow_mut().cycle(),
+            Action::ToggleRun => self.running = !self.running,
+            Action::Step if !self.running => self.game_context.universes.borrow().step(),
+            Action::Undo | Action::Redo => {
+                // 撤销/重做时暂停, 否则演化会立即覆盖撤销的结果
+                self.running = false;
+                let result = {
+                    let mut universes = self.game_context.universes.borrow_mut();
+                    universes.cancel_pending();
+                    if action == Action::Undo { universes.undo() } else { universes.redo() }
+                };
+                // 演化记录不同步时不执行, 在控制台中说明原因
+                if let Err(name) = result {
+                    let message = self.game_context.locale.borrow().format("console.out_of_step", &[&name]);
+                    eprintln!("{}", message);
+                    self.game_context.layout_manager.notify(message);
+                }
+            },
+            _ => {},
+        }
+        false
+    }
 }
 
@@ -0,0 +1,90 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+
+/// 死亡的cell留下尾迹的代数
+pub static TRAIL_LENGTH: u32 = 16;
+
+/// 每个cell最近的活动: 活细胞已存活的代数, 上一代的出生与死亡, 以及近期死亡的位置
+pub struct CellActivity {
+    ages: HashMap<(i32, i32), u32>,
+    births: HashSet<(i32, i32)>,
+    deaths: HashSet<(i32, i32)>,
+    /// 死亡后经过的代数, 超过`TRAIL_LENGTH`后移除
+    trails: HashMap<(i32, i32), u32>,
+}
+
+impl CellActivity {
+    pub fn new(alive_cells: &HashSet<(i32, i32)>) -> Self {
+        CellActivity {
+            ages: alive_cells.iter().map(|addr| (*addr, 0)).collect(),
+            births: HashSet::new(),
+            deaths: HashSet::new(),
+            trails: HashMap::new(),
+        }
+    }
+
+    /// 活细胞已存活的代数, 出生的这一代为0
+    pub fn age(&self, addr: &(i32, i32)) -> Option<u32> {
+        self.ages.get(addr).copied()
+    }
+
+    pub fn births(&self) -> &HashSet<(i32, i32)> {
+        &self.births
+    }
+
+    pub fn deaths(&self) -> &HashSet<(i32, i32)> {
+        &self.deaths
+    }
+
+    pub fn trails(&self) -> &HashMap<(i32, i32), u32> {
+        &self.trails
+    }
+
+    /// 演化一代后更新, `flipped`为状态发生变化的cell, `alive_cells`为新一代的活细胞
+    pub fn record_step(&mut self, flipped: &HashSet<(i32, i32)>, alive_cells: &HashSet<(i32, i32)>) {
+        let (births, deaths): (HashSet<_>, HashSet<_>) = flipped
+            .iter()
+            .partition(|addr| alive_cells.contains(addr));
+
+        self.ages.values_mut().for_each(|age| *age = age.saturating_add(1));
+        deaths.iter().for_each(|addr| { self.ages.remove(addr); });
+        self.ages.extend(births.iter().map(|addr| (*addr, 0)));
+
+        self.trails.values_mut().for_each(|trail| *trail += 1);
+        self.trails.retain(|addr, trail| *trail < TRAIL_LENGTH && !births.contains(addr));
+        self.trails.extend(deaths.iter().map(|addr| (*addr, 0)));
+
+        self.births = births;
+        self.deaths = deaths;
+    }
+
+    /// 编辑或撤销后与网格同步, `changed`为状态发生变化的cell, 只更新这些cell
+    /// 新出现的活细胞从0开始计算, 不产生出生与死亡记录
+    pub fn sync(&mut self, changed: &HashSet<(i32, i32)>, alive_cells: &HashSet<(i32, i32)>) {
+        for addr in changed {
+            if alive_cells.contains(addr) {
+                self.ages.entry(*addr).or_insert(0);
+                self.trails.remove(addr);
+            } else {
+                self.ages.remove(addr);
+            }
+        }
+        self.births.clear();
+        self.deaths.clear();
+    }
+
+    /// 撤销(`generations`为负)或重做一段演化后同步
+    /// 没有翻转的活细胞的年龄随之增减, 翻转的cell与`sync`相同
+    pub fn sync_run(&mut self, changed: &HashSet<(i32, i32)>, alive_cells: &HashSet<(i32, i32)>,
+        generations: i64)
+    {
+        let shift = generations.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        self.ages
+            .iter_mut()
+            .filter(|(addr, _)| !changed.contains(addr))
+            .for_each(|(_, age)| *age = age.saturating_add_signed(shift));
+        self.sync(changed, alive_cells);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    fmt,
+    str::FromStr,
+};
+use crate::utils::error;
+
+/// 标注的形状, 坐标均为cell坐标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mark {
+    /// 文字的左上角与cell的左上角对齐
+    Label { at: (i32, i32), text: String },
+    /// 从一个cell的中心指向另一个cell的中心
+    Arrow { from: (i32, i32), to: (i32, i32) },
+    /// 框住闭区间 [min, max] 内的cell
+    Rect { min: (i32, i32), max: (i32, i32) },
+}
+
+/// 附加在世界坐标上的标注, 用于说明大型结构中各部分的作用
+/// 文本形式同时用于控制台与图案文件的注释行:
+/// `label 3 -2 [#rrggbb] 文字`, `arrow 0 0 5 5 [#rrggbb]`, `rect 0 0 5 5 [#rrggbb]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub mark: Mark,
+    /// 未指定时使用主题中的标注颜色
+    pub color: Option<(u8, u8, u8)>,
+}
+
+impl Annotation {
+    pub fn translated(&self, (dx, dy): (i32, i32)) -> Self {
+        let shift = |(x, y): (i32, i32)| (x.saturating_add(dx), y.saturating_add(dy));
+        let mark = match &self.mark {
+            Mark::Label { at, text } => Mark::Label { at: shift(*at), text: text.clone() },
+            Mark::Arrow { from, to } => Mark::Arrow { from: shift(*from), to: shift(*to) },
+            Mark::Rect { min, max } => Mark::Rect { min: shift(*min), max: shift(*max) },
+        };
+        Annotation { mark, color: self.color }
+    }
+
+    /// 涉及的cell范围 (min_x, min_y, max_x, max_y), 文字只计算起点
+    pub fn bounds(&self) -> (i32, i32, i32, i32) {
+        let (a, b) = match &self.mark {
+            Mark::Label { at, .. } => (*at, *at),
+            Mark::Arrow { from, to } => (*from, *to),
+            Mark::Rect { min, max } => (*min, *max),
+        };
+        (a.0.min(b.0), a.1.min(b.1), a.0.max(b.0), a.1.max(b.1))
+    }
+}
+
+impl FromStr for Annotation {
+    type Err = error::DynError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (kind, mut rest) = split_word(text.trim());
+        let count = match kind {
+            "label" => 2,
+            "arrow" | "rect" => 4,
+            _ => return Err(format!("unknown annotation `{}`, expected label, arrow or rect", kind).into()),
+        };
+        let mut numbers = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (word, tail) = split_word(rest);
+            numbers.push(word
+                .parse::<i32>()
+                .map_err(|_| format!("invalid coordinate `{}`", word))?);
+            rest = tail;
+        }
+        let (word, tail) = split_word(rest);
+        let color = parse_color(word);
+        if color.is_some() {
+            rest = tail;
+        }
+
+        let mark = match kind {
+            "label" if rest.is_empty() => return Err(String::from("a label needs some text").into()),
+            "label" => Mark::Label { at: (numbers[0], numbers[1]), text: rest.to_string() },
+            _ if !rest.is_empty() => return Err(format!("unexpected `{}` after {}", rest, kind).into()),
+            "arrow" => Mark::Arrow { from: (numbers[0], numbers[1]), to: (numbers[2], numbers[3]) },
+            _ => Mark::Rect {
+                min: (numbers[0].min(numbers[2]), numbers[1].min(numbers[3])),
+                max: (numbers[0].max(numbers[2]), numbers[1].max(numbers[3])),
+            },
+        };
+        Ok(Annotation { mark, color })
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = self.color
+            .map(|(r, g, b)| format!(" #{:02x}{:02x}{:02x}", r, g, b))
+            .unwrap_or_default();
+        match &self.mark {
+            Mark::Label { at, text } => write!(f, "label {} {}{} {}", at.0, at.1, color, text),
+            Mark::Arrow { from, to } => write!(f, "arrow {} {} {} {}{}", from.0, from.1, to.0, to.1, color),
+            Mark::Rect { min, max } => write!(f, "rect {} {} {} {}{}", min.0, min.1, max.0, max.1, color),
+        }
+    }
+}
+
+/// 第一个词与去掉前导空白的剩余部分
+fn split_word(text: &str) -> (&str, &str) {
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim_start()),
+        None => (text, ""),
+    }
+}
+
+/// 只接受 "#rrggbb"
+fn parse_color(word: &str) -> Option<(u8, u8, u8)> {
+    let hex = word
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.is_ascii())?;
+    let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((component(0)?, component(2)?, component(4)?))
+}
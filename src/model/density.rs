@@ -0,0 +1,47 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+
+/// 多级的活细胞计数汇总, 第k级的一个块覆盖 2^k x 2^k 个cell
+/// 块坐标为cell坐标右移k位(向负无穷取整), 第0级即cell本身, 不单独存储
+pub struct DensitySummary {
+    levels: Vec<HashMap<(i32, i32), u32>>,
+}
+
+impl DensitySummary {
+    pub fn build(alive_cells: &HashSet<(i32, i32)>, max_level: usize) -> Self {
+        let mut levels: Vec<HashMap<(i32, i32), u32>> = Vec::with_capacity(max_level);
+        if max_level == 0 {
+            return DensitySummary { levels };
+        }
+
+        let mut first = HashMap::new();
+        for (x, y) in alive_cells.iter() {
+            *first.entry((x >> 1, y >> 1)).or_insert(0) += 1;
+        }
+        levels.push(first);
+
+        for _ in 1..max_level {
+            let mut next = HashMap::new();
+            for ((x, y), cnt) in levels.last().unwrap().iter() {
+                *next.entry((x >> 1, y >> 1)).or_insert(0) += cnt;
+            }
+            levels.push(next);
+        }
+        DensitySummary { levels }
+    }
+
+    pub fn max_level(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// `level`需大于0, 第0级由`CellGrid`直接查询
+    pub fn count(&self, level: usize, block: &(i32, i32)) -> u32 {
+        self.levels
+            .get(level - 1)
+            .and_then(|blocks| blocks.get(block))
+            .copied()
+            .unwrap_or(0)
+    }
+}
@@ -1,9 +1,37 @@
-use super::cell;
-use std::collections::HashSet;
+use super::{
+    cell,
+    density::DensitySummary,
+};
+use std::{
+    collections::HashSet,
+    cell::OnceCell,
+};
+
+/// 网格边界的处理方式, 有效范围均为 (-upper_scale, upper_scale)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// 越界的cell视为永远死亡
+    Bounded,
+    /// 上下、左右边界相连
+    Torus,
+}
+
+impl Topology {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bounded" => Some(Topology::Bounded),
+            "torus" => Some(Topology::Torus),
+            _ => None,
+        }
+    }
+}
 
 pub struct CellGrid {
     alive_cells: HashSet<(i32, i32)>,
     upper_scale: i32,
+    topology: Topology,
+    revision: u64,
+    density: OnceCell<DensitySummary>,
 }
 
 impl CellGrid {
@@ -11,7 +39,7 @@ impl CellGrid {
         self.upper_scale
     }
     pub fn new(upper_scale: i32) -> Self {
-        CellGrid { alive_cells: HashSet::new(), upper_scale }
+        CellGrid::from_alive_cells(HashSet::new(), upper_scale)
     }
 
     pub fn is_valid(&self, (x, y): &(i32, i32)) -> bool {
@@ -22,16 +50,114 @@ impl CellGrid {
         false
     }
 
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// 按拓扑将坐标映射到有效范围内, 越界且不可环绕时返回None
+    pub fn normalize(&self, addr: &(i32, i32)) -> Option<(i32, i32)> {
+        match self.topology {
+            Topology::Bounded => self.is_valid(addr).then_some(*addr),
+            Topology::Torus => {
+                let bound = self.upper_scale - 1;
+                let side = 2 * bound + 1;
+                let wrap = |v: i32| (v + bound).rem_euclid(side) - bound;
+                Some((wrap(addr.0), wrap(addr.1)))
+            },
+        }
+    }
+
+    /// 每次修改都会递增, 用于判断视图是否需要重绘
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
     pub fn is_alive(&self, addr: &(i32, i32)) -> bool {
         self.alive_cells.contains(&addr)
     }
 
+    /// 返回cell的状态是否发生了变化
+    pub fn set_cell(&mut self, addr: &(i32, i32), alive: bool) -> bool {
+        let Some(addr) = self.normalize(addr) else {
+            return false;
+        };
+        let changed = if alive {
+            self.alive_cells.insert(addr)
+        } else {
+            self.alive_cells.remove(&addr)
+        };
+        if changed {
+            self.touch();
+        }
+        changed
+    }
+
+    pub fn toggle_cell(&mut self, addr: &(i32, i32)) -> bool {
+        let Some(addr) = self.normalize(addr) else {
+            return false;
+        };
+        let alive = !self.is_alive(&addr);
+        self.set_cell(&addr, alive)
+    }
+
+    /// 用`next`(通常是下一代)替换当前网格
+    pub fn replace(&mut self, next: CellGrid) {
+        let revision = self.revision + 1;
+        *self = next;
+        self.revision = revision;
+    }
+
+    fn touch(&mut self) {
+        self.revision += 1;
+        self.density.take();
+    }
+
     pub fn alive_cells(&self) -> &HashSet<(i32, i32)> {
         &self.alive_cells
     }
 
+    /// 活细胞的包围盒 (min_x, min_y, max_x, max_y), 闭区间
+    pub fn bounding_box(&self) -> Option<(i32, i32, i32, i32)> {
+        self.alive_cells
+            .iter()
+            .fold(None, |bbox, &(x, y)| match bbox {
+                None => Some((x, y, x, y)),
+                Some((min_x, min_y, max_x, max_y)) => {
+                    Some((min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)))
+                },
+            })
+    }
+
     pub fn from_alive_cells(alive_cells: HashSet<(i32, i32)>, upper_scale: i32) -> Self {
-        CellGrid { alive_cells, upper_scale }
+        CellGrid {
+            alive_cells,
+            upper_scale,
+            topology: Topology::Bounded,
+            revision: 0,
+            density: OnceCell::new(),
+        }
+    }
+
+    /// 汇总按需构建, 第`max_level`级的块足以覆盖整个网格
+    pub fn density_summary(&self) -> &DensitySummary {
+        self.density.get_or_init(|| {
+            let side = 2 * self.upper_scale as u32;
+            let max_level = side.next_power_of_two().trailing_zeros() as usize;
+            DensitySummary::build(&self.alive_cells, max_level)
+        })
+    }
+
+    /// 第`level`级块`block`中的活细胞数
+    pub fn count_in_block(&self, level: usize, block: &(i32, i32)) -> u32 {
+        if level == 0 {
+            return self.is_alive(block) as u32;
+        }
+        self.density_summary().count(level, block)
     }
 }
 
@@ -52,6 +178,6 @@ impl From<Vec<Vec<cell::Cell>>> for CellGrid {
                     .map(move |(y, _)| (x as i32, y as i32))
             }).collect();
         let upper_scale = std::cmp::max(grid.len(), grid[0].len()) as i32;
-        CellGrid { alive_cells, upper_scale }
+        CellGrid::from_alive_cells(alive_cells, upper_scale)
     }
 }
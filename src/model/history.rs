@@ -0,0 +1,180 @@
+use std::collections::{
+    HashSet,
+    VecDeque,
+};
+use super::rules::LifeRule;
+
+static MAX_ENTRIES: usize = 512;
+static MAX_STORED_CELLS: usize = 1 << 22;
+
+/// 一次修改中状态翻转的cell集合, 再翻转一次即可撤销
+/// 连续的修改合并时取对称差, 来回翻转的cell互相抵消
+#[derive(Debug, Default, Clone)]
+pub struct CellDiff {
+    flipped: HashSet<(i32, i32)>,
+}
+
+impl CellDiff {
+    pub fn from_flipped(flipped: HashSet<(i32, i32)>) -> Self {
+        CellDiff { flipped }
+    }
+
+    pub fn flip(&mut self, addr: (i32, i32)) {
+        if !self.flipped.remove(&addr) {
+            self.flipped.insert(addr);
+        }
+    }
+
+    pub fn merge(&mut self, other: CellDiff) {
+        other.flipped
+            .into_iter()
+            .for_each(|addr| self.flip(addr));
+    }
+
+    pub fn cells(&self) -> &HashSet<(i32, i32)> {
+        &self.flipped
+    }
+
+    pub fn len(&self) -> usize {
+        self.flipped.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flipped.is_empty()
+    }
+}
+
+pub enum Command {
+    /// 编辑、粘贴、变换
+    Edit(CellDiff),
+    /// 自上次编辑以来的连续演化, 撤销时一次回到编辑后的状态
+    Run { diff: CellDiff, generations: u64 },
+    SetRule { before: LifeRule, after: LifeRule },
+}
+
+impl Command {
+    fn stored_cells(&self) -> usize {
+        match self {
+            Command::Edit(diff) | Command::Run { diff, .. } => diff.len(),
+            Command::SetRule { .. } => 0,
+        }
+    }
+}
+
+/// 撤销/重做栈, 只保存差异, 总量超过上限时丢弃最早的记录
+#[derive(Default)]
+pub struct History {
+    undo_stack: VecDeque<Command>,
+    redo_stack: Vec<Command>,
+    group: Option<CellDiff>,
+    group_depth: usize,
+    stored_cells: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    /// 分组内的编辑合并为一条记录, 分组可以嵌套
+    pub fn begin_group(&mut self) {
+        if self.group_depth == 0 {
+            self.group = Some(CellDiff::default());
+        }
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        if self.group_depth == 0 {
+            return;
+        }
+        self.group_depth -= 1;
+        if self.group_depth == 0
+            && let Some(diff) = self.group.take()
+            && !diff.is_empty() {
+            self.push(Command::Edit(diff));
+        }
+    }
+
+    pub fn record_edit(&mut self, diff: CellDiff) {
+        if diff.is_empty() {
+            return;
+        }
+        match &mut self.group {
+            Some(group) => group.merge(diff),
+            None => self.push(Command::Edit(diff)),
+        }
+    }
+
+    pub fn record_step(&mut self, diff: CellDiff) {
+        if let Some(Command::Run { diff: run, generations }) = self.undo_stack.back_mut() {
+            self.stored_cells -= run.len();
+            run.merge(diff);
+            *generations += 1;
+            self.stored_cells += run.len();
+            self.redo_stack.clear();
+            self.trim();
+            return;
+        }
+        self.push(Command::Run { diff, generations: 1 });
+    }
+
+    pub fn record_rule(&mut self, before: LifeRule, after: LifeRule) {
+        if before != after {
+            self.push(Command::SetRule { before, after });
+        }
+    }
+
+    /// 下一条待撤销的记录为演化时返回其代数
+    pub fn undo_generations(&self) -> Option<u64> {
+        match self.undo_stack.back() {
+            Some(Command::Run { generations, .. }) => Some(*generations),
+            _ => None,
+        }
+    }
+
+    /// 下一条待重做的记录为演化时返回其代数
+    pub fn redo_generations(&self) -> Option<u64> {
+        match self.redo_stack.last() {
+            Some(Command::Run { generations, .. }) => Some(*generations),
+            _ => None,
+        }
+    }
+
+    /// 取出待撤销的命令, 调用者执行反向操作后交给`push_redo`
+    pub fn take_undo(&mut self) -> Option<Command> {
+        let command = self.undo_stack.pop_back()?;
+        self.stored_cells -= command.stored_cells();
+        Some(command)
+    }
+
+    pub fn push_redo(&mut self, command: Command) {
+        self.redo_stack.push(command);
+    }
+
+    /// 取出待重做的命令, 调用者执行后交给`push_undo`
+    pub fn take_redo(&mut self) -> Option<Command> {
+        self.redo_stack.pop()
+    }
+
+    /// 重新压入撤销栈, 不清空重做栈
+    pub fn push_undo(&mut self, command: Command) {
+        self.stored_cells += command.stored_cells();
+        self.undo_stack.push_back(command);
+        self.trim();
+    }
+
+    fn push(&mut self, command: Command) {
+        self.redo_stack.clear();
+        self.push_undo(command);
+    }
+
+    fn trim(&mut self) {
+        while self.undo_stack.len() > MAX_ENTRIES
+            || (self.stored_cells > MAX_STORED_CELLS && self.undo_stack.len() > 1) {
+            if let Some(command) = self.undo_stack.pop_front() {
+                self.stored_cells -= command.stored_cells();
+            }
+        }
+    }
+}
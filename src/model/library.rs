@@ -0,0 +1,160 @@
+use std::{
+    fs,
+    path::Path,
+    rc::Rc,
+    cell::RefCell,
+};
+use crate::utils::error;
+use super::{
+    pattern::Pattern,
+    pattern_io,
+};
+
+pub type RcPatternLibrary = Rc<RefCell<PatternLibrary>>;
+
+/// 内置图案, (分类的标识符, [(名称, RLE)])
+static BUILTIN_PATTERNS: [(&str, &[(&str, &str)]); 5] = [
+    ("still_lifes", &[
+        ("Block", "x = 2, y = 2\n2o$2o!"),
+        ("Beehive", "x = 4, y = 3\nb2o$o2bo$b2o!"),
+        ("Loaf", "x = 4, y = 4\nb2o$o2bo$bobo$2bo!"),
+        ("Boat", "x = 3, y = 3\n2o$obo$bo!"),
+        ("Tub", "x = 3, y = 3\nbo$obo$bo!"),
+    ]),
+    ("oscillators", &[
+        ("Blinker", "x = 3, y = 1\n3o!"),
+        ("Toad", "x = 4, y = 2\nb3o$3o!"),
+        ("Beacon", "x = 4, y = 4\n2o$2o$2b2o$2b2o!"),
+        ("Pulsar", "x = 13, y = 13\n2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$\
+            2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!"),
+        ("Pentadecathlon", "x = 10, y = 3\n2bo4bo2b$2ob4ob2o$2bo4bo!"),
+    ]),
+    ("spaceships", &[
+        ("Glider", "x = 3, y = 3\nbo$2bo$3o!"),
+        ("LWSS", "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!"),
+        ("MWSS", "x = 6, y = 5\n3bo2b$bo3bo$o5b$o4bo$5o!"),
+        ("HWSS", "x = 7, y = 5\n3b2o2b$bo4bo$o6b$o5bo$6o!"),
+    ]),
+    ("guns", &[
+        ("Gosper glider gun", "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$\
+            11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!"),
+    ]),
+    ("methuselahs", &[
+        ("R-pentomino", "x = 3, y = 3\nb2o$2o$bo!"),
+        ("Diehard", "x = 8, y = 3\n6bob$2o6b$bo3b3o!"),
+        ("Acorn", "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!"),
+    ]),
+];
+
+pub struct LibraryEntry {
+    name: String,
+    pattern: Pattern,
+}
+
+impl LibraryEntry {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+}
+
+pub struct Category {
+    /// 内置分类为标识符, 显示时使用`library.<标识符>`的译文; 用户的分类为目录名
+    name: String,
+    builtin: bool,
+    entries: Vec<LibraryEntry>,
+}
+
+impl Category {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        self.builtin
+    }
+
+    pub fn entries(&self) -> &[LibraryEntry] {
+        &self.entries
+    }
+}
+
+/// 按分类组织的图案库, 内置图案之外可以加入用户的图案目录
+pub struct PatternLibrary {
+    categories: Vec<Category>,
+    revision: u64,
+}
+
+impl PatternLibrary {
+    pub fn builtin() -> Self {
+        let categories = BUILTIN_PATTERNS
+            .iter()
+            .map(|(name, patterns)| {
+                let entries = patterns
+                    .iter()
+                    .map(|(name, rle)| LibraryEntry {
+                        name: name.to_string(),
+                        pattern: pattern_io::parse_rle(rle)
+                            .expect("built-in pattern should be valid")
+                            .pattern,
+                    })
+                    .collect();
+                Category { name: name.to_string(), builtin: true, entries }
+            })
+            .collect();
+        PatternLibrary { categories, revision: 0 }
+    }
+
+    pub fn categories(&self) -> &[Category] {
+        &self.categories
+    }
+
+    /// 每次内容变化时递增, 用于判断界面是否需要重绘
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// 目录及其子目录各自成为一个分类, 同名的用户分类会被替换
+    /// 无法解析的文件被跳过, 返回读入的图案数
+    pub fn add_directory(&mut self, path: &Path) -> error::Result<usize> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        let loaded = self.add_directory_as(path, name)?;
+        self.revision += 1;
+        Ok(loaded)
+    }
+
+    fn add_directory_as(&mut self, path: &Path, name: String) -> error::Result<usize> {
+        let mut paths: Vec<_> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        paths.sort();
+
+        let mut loaded = 0;
+        let mut entries = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                let sub_name = format!("{}/{}", name, path.file_name().unwrap_or_default().to_string_lossy());
+                loaded += self.add_directory_as(&path, sub_name).unwrap_or(0);
+            } else if pattern_io::is_pattern_file(&path)
+                && let Ok(file) = pattern_io::load(&path) {
+                entries.push(LibraryEntry {
+                    name: file.name.unwrap_or_default(),
+                    pattern: file.pattern,
+                });
+            }
+        }
+        loaded += entries.len();
+
+        self.categories.retain(|category| category.builtin || category.name != name);
+        if !entries.is_empty() {
+            self.categories.push(Category { name, builtin: false, entries });
+        }
+        Ok(loaded)
+    }
+}
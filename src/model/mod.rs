@@ -1,3 +1,16 @@
 pub mod cell;
 pub mod grid;
 pub mod rules;
+pub mod density;
+pub mod shape;
+pub mod universe;
+pub mod universe_set;
+pub mod pattern;
+pub mod history;
+pub mod statistics;
+pub mod pattern_io;
+pub mod library;
+pub mod random;
+pub mod activity;
+pub mod annotation;
+pub mod session;
@@ -0,0 +1,117 @@
+/// 与位置无关的图案, cell坐标相对于包围盒左上角, 范围为 [0, width) x [0, height)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    cells: Vec<(i32, i32)>,
+    width: i32,
+    height: i32,
+}
+
+impl Pattern {
+    pub fn new(cells: Vec<(i32, i32)>, width: i32, height: i32) -> Self {
+        Pattern { cells, width, height }
+    }
+
+    /// 以cell集合的包围盒作为图案的范围
+    pub fn from_cells<I: IntoIterator<Item = (i32, i32)>>(cells: I) -> Self {
+        let cells: Vec<_> = cells.into_iter().collect();
+        if cells.is_empty() {
+            return Pattern::new(cells, 0, 0);
+        }
+        let min_x = cells.iter().map(|(x, _)| *x).min().unwrap();
+        let min_y = cells.iter().map(|(_, y)| *y).min().unwrap();
+        let max_x = cells.iter().map(|(x, _)| *x).max().unwrap();
+        let max_y = cells.iter().map(|(_, y)| *y).max().unwrap();
+        let cells = cells
+            .into_iter()
+            .map(|(x, y)| (x - min_x, y - min_y))
+            .collect();
+        Pattern::new(cells, max_x - min_x + 1, max_y - min_y + 1)
+    }
+
+    pub fn cells(&self) -> &[(i32, i32)] {
+        &self.cells
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// 左上角放在`origin`时各cell的世界坐标
+    pub fn placed_at(&self, (ox, oy): (i32, i32)) -> Vec<(i32, i32)> {
+        self.cells
+            .iter()
+            .map(|(x, y)| (ox + x, oy + y))
+            .collect()
+    }
+
+    fn map<F: Fn(i32, i32) -> (i32, i32)>(&self, width: i32, height: i32, f: F) -> Self {
+        let cells = self.cells
+            .iter()
+            .map(|(x, y)| f(*x, *y))
+            .collect();
+        Pattern::new(cells, width, height)
+    }
+
+    /// 顺时针旋转90°
+    pub fn rotate_cw(&self) -> Self {
+        let h = self.height;
+        self.map(self.height, self.width, |x, y| (h - 1 - y, x))
+    }
+
+    /// 逆时针旋转90°
+    pub fn rotate_ccw(&self) -> Self {
+        let w = self.width;
+        self.map(self.height, self.width, |x, y| (y, w - 1 - x))
+    }
+
+    pub fn rotate_180(&self) -> Self {
+        let (w, h) = (self.width, self.height);
+        self.map(w, h, |x, y| (w - 1 - x, h - 1 - y))
+    }
+
+    pub fn flip_horizontal(&self) -> Self {
+        let w = self.width;
+        self.map(self.width, self.height, |x, y| (w - 1 - x, y))
+    }
+
+    pub fn flip_vertical(&self) -> Self {
+        let h = self.height;
+        self.map(self.width, self.height, |x, y| (x, h - 1 - y))
+    }
+
+    /// 沿主对角线翻转
+    pub fn transpose(&self) -> Self {
+        self.map(self.height, self.width, |x, y| (y, x))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    FlipHorizontal,
+    FlipVertical,
+    Transpose,
+}
+
+impl Pattern {
+    pub fn transformed(&self, transform: Transform) -> Self {
+        match transform {
+            Transform::RotateCw => self.rotate_cw(),
+            Transform::RotateCcw => self.rotate_ccw(),
+            Transform::Rotate180 => self.rotate_180(),
+            Transform::FlipHorizontal => self.flip_horizontal(),
+            Transform::FlipVertical => self.flip_vertical(),
+            Transform::Transpose => self.transpose(),
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    fmt,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    collections::HashSet,
+};
+use crate::utils::error;
+use super::{
+    annotation::Annotation,
+    pattern::Pattern,
+    rules::LifeRule,
+};
+
+/// 注释行中以此开头的内容为标注, 例如RLE中的 `#C @label 0 -2 glider`
+static ANNOTATION_PREFIX: char = '@';
+
+/// 从文件读入的图案及其附带的信息
+#[derive(Debug, Clone)]
+pub struct PatternFile {
+    pub name: Option<String>,
+    pub rule: Option<LifeRule>,
+    pub pattern: Pattern,
+    /// 坐标相对于图案的左上角
+    pub annotations: Vec<Annotation>,
+}
+
+/// 读写图案文件失败的原因, 显示为英文, 控制台与文件对话框中按当前语言另行显示
+#[derive(Debug)]
+pub enum PatternError {
+    Io(io::Error),
+    Unsupported(PathBuf),
+    RunLengthOverflow,
+    InvalidRleChar(char),
+    InvalidHeader(String),
+    InvalidPlaintextChar(char),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Io(e) => write!(f, "{}", e),
+            PatternError::Unsupported(path) => write!(f, "unsupported pattern file `{}`", path.display()),
+            PatternError::RunLengthOverflow => write!(f, "run length overflow in RLE pattern"),
+            PatternError::InvalidRleChar(c) => write!(f, "invalid character `{}` in RLE pattern", c),
+            PatternError::InvalidHeader(line) => write!(f, "invalid RLE header `{}`", line),
+            PatternError::InvalidPlaintextChar(c) => {
+                write!(f, "invalid character `{}` in plaintext pattern", c)
+            },
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<io::Error> for PatternError {
+    fn from(e: io::Error) -> Self {
+        PatternError::Io(e)
+    }
+}
+
+/// 按扩展名选择格式, 支持RLE(.rle)与plaintext(.cells)
+pub fn load(path: &Path) -> error::Result<PatternFile, PatternError> {
+    let text = fs::read_to_string(path)?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let mut file = match extension.as_deref() {
+        Some("rle") => parse_rle(&text)?,
+        Some("cells") => parse_plaintext(&text)?,
+        _ => return Err(PatternError::Unsupported(path.to_path_buf())),
+    };
+    if file.name.is_none() {
+        file.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+    }
+    Ok(file)
+}
+
+/// 按扩展名选择格式写入文件
+pub fn save(path: &Path, file: &PatternFile) -> error::Result<(), PatternError> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let text = match extension.as_deref() {
+        Some("rle") => to_rle(file),
+        Some("cells") => to_plaintext(file),
+        _ => return Err(PatternError::Unsupported(path.to_path_buf())),
+    };
+    fs::write(path, text)?;
+    Ok(())
+}
+
+pub fn is_pattern_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("rle") || ext.eq_ignore_ascii_case("cells"))
+}
+
+/// 解析RLE格式, 不认识的规则写法会被忽略
+pub fn parse_rle(text: &str) -> error::Result<PatternFile, PatternError> {
+    let mut name = None;
+    let mut rule = None;
+    let mut size = None;
+    let mut annotations = Vec::new();
+    let mut body = String::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(value) = comment.strip_prefix('N') {
+                name = Some(value.trim().to_string()).filter(|name| !name.is_empty());
+            } else if let Some(value) = comment.strip_prefix('C')
+                && let Some(annotation) = parse_annotation(value) {
+                annotations.push(annotation);
+            }
+        } else if size.is_none() && line.starts_with('x') {
+            let (width, height, header_rule) = parse_rle_header(line)?;
+            size = Some((width, height));
+            rule = header_rule.and_then(|rule| rule.parse().ok());
+        } else {
+            body.push_str(line);
+            if line.contains('!') {
+                break;
+            }
+        }
+    }
+
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let mut count: Option<i32> = None;
+    for c in body.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            let next = count.unwrap_or(0)
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(digit as i32));
+            count = Some(next.ok_or(PatternError::RunLengthOverflow)?);
+            continue;
+        }
+        let run = count.take().unwrap_or(1);
+        match c {
+            'b' | '.' => x += run,
+            'o' | 'A'..='Z' => {
+                cells.extend((x..x + run).map(|cx| (cx, y)));
+                x += run;
+            },
+            '$' => {
+                y += run;
+                x = 0;
+            },
+            '!' => break,
+            c if c.is_whitespace() => {},
+            c => return Err(PatternError::InvalidRleChar(c)),
+        }
+    }
+
+    let pattern = match size {
+        Some((width, height)) if cells.iter().all(|(x, y)| *x < width && *y < height) => {
+            Pattern::new(cells, width, height)
+        },
+        _ => bounding_pattern(cells, &mut annotations),
+    };
+    Ok(PatternFile { name, rule, pattern, annotations })
+}
+
+/// 以cell的包围盒作为图案, 标注随cell一起平移, 保持两者的相对位置
+fn bounding_pattern(cells: Vec<(i32, i32)>, annotations: &mut [Annotation]) -> Pattern {
+    let min_x = cells.iter().map(|(x, _)| *x).min().unwrap_or(0);
+    let min_y = cells.iter().map(|(_, y)| *y).min().unwrap_or(0);
+    annotations
+        .iter_mut()
+        .for_each(|annotation| *annotation = annotation.translated((-min_x, -min_y)));
+    Pattern::from_cells(cells)
+}
+
+/// 不是标注或无法解析时返回None, 按普通注释处理
+fn parse_annotation(comment: &str) -> Option<Annotation> {
+    comment
+        .trim()
+        .strip_prefix(ANNOTATION_PREFIX)?
+        .parse()
+        .ok()
+}
+
+/// 解析 "x = 3, y = 3, rule = B3/S23"
+fn parse_rle_header(line: &str) -> error::Result<(i32, i32, Option<&str>), PatternError> {
+    let invalid = || PatternError::InvalidHeader(line.to_string());
+    let mut width = None;
+    let mut height = None;
+    let mut rule = None;
+    for item in line.split(',') {
+        let Some((key, value)) = item.split_once('=') else {
+            return Err(invalid());
+        };
+        match key.trim() {
+            "x" => width = Some(value.trim().parse::<i32>().map_err(|_| invalid())?),
+            "y" => height = Some(value.trim().parse::<i32>().map_err(|_| invalid())?),
+            "rule" => rule = Some(value.trim()),
+            _ => {},
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) if width >= 0 && height >= 0 => Ok((width, height, rule)),
+        _ => Err(invalid()),
+    }
+}
+
+/// 解析plaintext格式, `!`开头的行为注释, `O`或`*`为活细胞
+pub fn parse_plaintext(text: &str) -> error::Result<PatternFile, PatternError> {
+    let mut name = None;
+    let mut annotations = Vec::new();
+    let mut cells = Vec::new();
+    let mut y = 0;
+    for line in text.lines() {
+        if let Some(comment) = line.strip_prefix('!') {
+            if let Some(value) = comment.strip_prefix("Name:") {
+                name = Some(value.trim().to_string()).filter(|name| !name.is_empty());
+            } else if let Some(annotation) = parse_annotation(comment) {
+                annotations.push(annotation);
+            }
+            continue;
+        }
+        for (x, c) in line.trim_end().chars().enumerate() {
+            match c {
+                'O' | '*' => cells.push((x as i32, y)),
+                '.' => {},
+                c => return Err(PatternError::InvalidPlaintextChar(c)),
+            }
+        }
+        y += 1;
+    }
+    let pattern = bounding_pattern(cells, &mut annotations);
+    Ok(PatternFile { name, rule: None, pattern, annotations })
+}
+
+/// 写为RLE格式, 每行不超过70个字符
+pub fn to_rle(file: &PatternFile) -> String {
+    let pattern = &file.pattern;
+    let alive: HashSet<_> = pattern.cells().iter().copied().collect();
+    let mut runs: Vec<(i32, char)> = Vec::new();
+    let mut push = |count: i32, tag: char| match runs.last_mut() {
+        Some((last, last_tag)) if *last_tag == tag => *last += count,
+        _ => runs.push((count, tag)),
+    };
+    for y in 0..pattern.height() {
+        let mut x = 0;
+        while x < pattern.width() {
+            let state = alive.contains(&(x, y));
+            let start = x;
+            while x < pattern.width() && alive.contains(&(x, y)) == state {
+                x += 1;
+            }
+            // 行尾的死细胞省略
+            if state || x < pattern.width() {
+                push(x - start, if state { 'o' } else { 'b' });
+            }
+        }
+        push(1, '$');
+    }
+    while runs.last().is_some_and(|(_, tag)| *tag == '$') {
+        runs.pop();
+    }
+    runs.push((1, '!'));
+
+    let mut text = String::new();
+    if let Some(name) = &file.name {
+        text.push_str(&format!("#N {}\n", name));
+    }
+    for annotation in &file.annotations {
+        text.push_str(&format!("#C {}{}\n", ANNOTATION_PREFIX, annotation));
+    }
+    text.push_str(&format!("x = {}, y = {}", pattern.width(), pattern.height()));
+    if let Some(rule) = file.rule {
+        text.push_str(&format!(", rule = {}", rule));
+    }
+    text.push('\n');
+    let mut line = String::new();
+    for (count, tag) in runs {
+        let item = if count > 1 { format!("{}{}", count, tag) } else { tag.to_string() };
+        if line.len() + item.len() > 70 {
+            text.push_str(&line);
+            text.push('\n');
+            line.clear();
+        }
+        line.push_str(&item);
+    }
+    text.push_str(&line);
+    text.push('\n');
+    text
+}
+
+/// 写为plaintext格式, plaintext不记录规则
+pub fn to_plaintext(file: &PatternFile) -> String {
+    let pattern = &file.pattern;
+    let alive: HashSet<_> = pattern.cells().iter().copied().collect();
+    let mut text = String::new();
+    if let Some(name) = &file.name {
+        text.push_str(&format!("!Name: {}\n", name));
+    }
+    for annotation in &file.annotations {
+        text.push_str(&format!("!{}{}\n", ANNOTATION_PREFIX, annotation));
+    }
+    for y in 0..pattern.height() {
+        let row: String = (0..pattern.width())
+            .map(|x| if alive.contains(&(x, y)) { 'O' } else { '.' })
+            .collect();
+        text.push_str(row.trim_end_matches('.'));
+        text.push('\n');
+    }
+    text
+}
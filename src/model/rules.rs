@@ -1,48 +1,173 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+};
 use super::grid;
+
 pub trait RulesInterface {
-    fn next_gen(grid: &grid::CellGrid) -> grid::CellGrid;
+    fn next_gen(&self, grid: &grid::CellGrid) -> grid::CellGrid;
+}
+
+/// 用B/S记号描述的life-like规则, 如康威生命游戏为 B3/S23
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifeRule {
+    birth: [bool; 9],
+    survive: [bool; 9],
 }
 
-struct SimpleRules;
+impl LifeRule {
+    pub fn conway() -> Self {
+        let mut rule = LifeRule { birth: [false; 9], survive: [false; 9] };
+        rule.birth[3] = true;
+        rule.survive[2] = true;
+        rule.survive[3] = true;
+        rule
+    }
+
+    pub fn is_born(&self, neighbors: usize) -> bool {
+        self.birth.get(neighbors).copied().unwrap_or(false)
+    }
+
+    pub fn survives(&self, neighbors: usize) -> bool {
+        self.survive.get(neighbors).copied().unwrap_or(false)
+    }
+
+    /// 根据当前状态与活邻居数决定下一代是否存活
+    pub fn next_state(&self, alive: bool, neighbors: usize) -> bool {
+        if alive {
+            self.survives(neighbors)
+        } else {
+            self.is_born(neighbors)
+        }
+    }
+
+    /// 单个cell的活邻居数, 坐标按网格拓扑映射
+    pub fn count_neighbors(grid: &grid::CellGrid, (x, y): (i32, i32)) -> usize {
+        (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+            .filter(|offset| *offset != (0, 0))
+            .filter_map(|(dx, dy)| grid.normalize(&(x + dx, y + dy)))
+            .filter(|addr| grid.is_alive(addr))
+            .count()
+    }
 
-impl RulesInterface for SimpleRules {
-    fn next_gen(grid: &grid::CellGrid) -> grid::CellGrid {
+    /// 活细胞及其邻居的活邻居数, 坐标按网格拓扑映射
+    pub fn neighbor_counts(grid: &grid::CellGrid) -> HashMap<(i32, i32), usize> {
         let mut neighbors: HashMap<(i32, i32), usize> = HashMap::new();
         grid.alive_cells()
             .iter()
             .for_each(|(x, y)| {
                 for dx in -1..=1 {
                     for dy in -1..=1 {
-                        let neighbor = neighbors.entry((*x+dx, *y+dy)).or_insert(0);
+                        if dx == 0 && dy == 0 {
+                            continue;
+                        }
+                        let Some(addr) = grid.normalize(&(*x+dx, *y+dy)) else {
+                            continue;
+                        };
+                        let neighbor = neighbors.entry(addr).or_insert(0);
                         *neighbor += 1;
                     }
                 }
             });
+        neighbors
+    }
+}
 
-        let next_grid = neighbors
-            .iter()
-            .filter(|&(addr, cnt)| -> bool {
-                if !grid.is_valid(addr) {
-                        return false;
-                    }
-                match cnt {
-                    2 => {
-                        if grid.is_alive(addr) {
-                            return true;
-                        } else {
-                            return false;
-                        }
-                    },
-                    3 => { return true; },
-                    _ => { return false; }
-                }
-            })
-            .map(|((x, y), _)| -> (i32, i32) {
-                (*x, *y)
-            })
+impl Default for LifeRule {
+    fn default() -> Self {
+        LifeRule::conway()
+    }
+}
+
+impl RulesInterface for LifeRule {
+    fn next_gen(&self, grid: &grid::CellGrid) -> grid::CellGrid {
+        let next_grid = LifeRule::neighbor_counts(grid)
+            .into_iter()
+            .filter(|(addr, cnt)| self.next_state(grid.is_alive(addr), *cnt))
+            .map(|(addr, _)| addr)
             .collect();
-            
+
         grid::CellGrid::from_alive_cells(next_grid, grid.upper_scale())
+            .with_topology(grid.topology())
+    }
+}
+
+/// 规则解析失败的原因, 显示为英文, 控制台中按当前语言另行显示
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    /// 某一部分不以B或S开头
+    Syntax(String),
+    DuplicatedSection(String),
+    /// (不合法的邻居数, 规则)
+    InvalidCount(char, String),
+    MissingSection(String),
+    BirthOnZero,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Syntax(text) => write!(f, "invalid rule `{}`, expected B/S notation like B3/S23", text),
+            RuleError::DuplicatedSection(text) => write!(f, "invalid rule `{}`, duplicated section", text),
+            RuleError::InvalidCount(c, text) => write!(f, "invalid neighbor count `{}` in rule `{}`", c, text),
+            RuleError::MissingSection(text) => {
+                write!(f, "invalid rule `{}`, both B and S sections are required", text)
+            },
+            RuleError::BirthOnZero => write!(f, "B0 rules are not supported"),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+impl FromStr for LifeRule {
+    type Err = RuleError;
+
+    /// 接受 "B3/S23", "b36/s23", "S23/B3" 等写法, 不区分大小写
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut rule = LifeRule { birth: [false; 9], survive: [false; 9] };
+        let mut seen_birth = false;
+        let mut seen_survive = false;
+        for part in text.trim().split('/') {
+            let mut chars = part.trim().chars();
+            let (counts, seen) = match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => (&mut rule.birth, &mut seen_birth),
+                Some('S') => (&mut rule.survive, &mut seen_survive),
+                _ => return Err(RuleError::Syntax(text.to_string())),
+            };
+            if *seen {
+                return Err(RuleError::DuplicatedSection(text.to_string()));
+            }
+            *seen = true;
+            for c in chars {
+                let Some(n) = c.to_digit(10).filter(|n| *n <= 8) else {
+                    return Err(RuleError::InvalidCount(c, text.to_string()));
+                };
+                counts[n as usize] = true;
+            }
+        }
+        if !seen_birth || !seen_survive {
+            return Err(RuleError::MissingSection(text.to_string()));
+        }
+        if rule.birth[0] {
+            return Err(RuleError::BirthOnZero);
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for LifeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |counts: &[bool; 9]| -> String {
+            counts
+                .iter()
+                .enumerate()
+                .filter(|(_, on)| **on)
+                .map(|(n, _)| char::from(b'0' + n as u8))
+                .collect()
+        };
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survive))
     }
 }
@@ -0,0 +1,110 @@
+use std::{
+    fs,
+    path::Path,
+};
+use crate::utils::error;
+use super::{
+    annotation::Annotation,
+    universe_set::UniverseSet,
+};
+
+/// 退出时保存、启动时恢复的内容, 目前只有各宇宙的标注
+/// 格式如下, 每个标注的写法与控制台的`note`命令相同:
+/// ```toml
+/// [universes.main]
+/// annotations = ["label 0 -3 glider gun", "rect -20 -5 20 5 #ff8000"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    annotations: Vec<(String, Vec<Annotation>)>,
+}
+
+impl Session {
+    /// 没有标注的宇宙不会被记录, `previous`中当前布局没有的宇宙原样保留
+    pub fn capture(universes: &UniverseSet, previous: &Session) -> Self {
+        let mut annotations: Vec<_> = universes
+            .iter()
+            .map(|(name, universe)| (name.to_string(), universe.borrow().annotations().to_vec()))
+            .filter(|(_, annotations)| !annotations.is_empty())
+            .collect();
+        annotations.extend(previous.annotations
+            .iter()
+            .filter(|(name, _)| universes.get(name).is_none())
+            .cloned());
+        Session { annotations }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+
+    /// 替换各宇宙的标注, 返回布局中不存在的宇宙名
+    pub fn restore(&self, universes: &UniverseSet) -> Vec<String> {
+        let mut missing = Vec::new();
+        for (name, annotations) in &self.annotations {
+            match universes.get(name) {
+                Some(universe) => universe.borrow_mut().set_annotations(annotations.clone()),
+                None => missing.push(name.clone()),
+            }
+        }
+        missing
+    }
+
+    pub fn load(path: &Path) -> error::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Session::from_toml(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    pub fn from_toml(text: &str) -> error::Result<Self> {
+        let table: toml::Table = text.parse()?;
+        if let Some(key) = table.keys().find(|key| *key != "universes") {
+            return Err(format!("unknown key `{}`, expected universes", key).into());
+        }
+        let Some(universes) = table.get("universes") else {
+            return Ok(Session::default());
+        };
+        let universes = universes
+            .as_table()
+            .ok_or("universes: expected a table")?;
+        let mut annotations = Vec::new();
+        for (name, value) in universes {
+            let entry = format!("universes.{}.annotations", name);
+            let values = value
+                .get("annotations")
+                .and_then(|value| value.as_array())
+                .ok_or_else(|| format!("{}: expected an array of strings", entry))?;
+            let parsed = values
+                .iter()
+                .map(|value| {
+                    let text = value
+                        .as_str()
+                        .ok_or_else(|| format!("{}: expected an array of strings", entry))?;
+                    text.parse().map_err(|e| format!("{}: `{}`: {}", entry, text, e).into())
+                })
+                .collect::<error::Result<Vec<Annotation>>>()?;
+            annotations.push((name.clone(), parsed));
+        }
+        Ok(Session { annotations })
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut universes = toml::Table::new();
+        for (name, annotations) in &self.annotations {
+            let values = annotations
+                .iter()
+                .map(|annotation| toml::Value::from(annotation.to_string()))
+                .collect::<Vec<_>>();
+            let mut universe = toml::Table::new();
+            universe.insert(String::from("annotations"), toml::Value::from(values));
+            universes.insert(name.clone(), toml::Value::from(universe));
+        }
+        let mut table = toml::Table::new();
+        table.insert(String::from("universes"), toml::Value::from(universes));
+        table.to_string()
+    }
+
+    pub fn save(&self, path: &Path) -> error::Result<()> {
+        fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::{
+    HashSet,
+    VecDeque,
+};
+use super::grid;
+
+/// Bresenham直线, 包含两个端点, 相邻的点八连通
+pub fn line(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - from.0).abs();
+    let dy = -(to.1 - from.1).abs();
+    let sx = if from.0 < to.0 { 1 } else { -1 };
+    let sy = if from.1 < to.1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut points = Vec::with_capacity((dx - dy) as usize + 1);
+    loop {
+        points.push((x, y));
+        if (x, y) == to {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+    points
+}
+
+fn normalize_corners(a: (i32, i32), b: (i32, i32)) -> ((i32, i32), (i32, i32)) {
+    ((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1)))
+}
+
+pub fn filled_rect(a: (i32, i32), b: (i32, i32)) -> Vec<(i32, i32)> {
+    let ((min_x, min_y), (max_x, max_y)) = normalize_corners(a, b);
+    (min_y..=max_y)
+        .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+        .collect()
+}
+
+pub fn hollow_rect(a: (i32, i32), b: (i32, i32)) -> Vec<(i32, i32)> {
+    let ((min_x, min_y), (max_x, max_y)) = normalize_corners(a, b);
+    filled_rect(a, b)
+        .into_iter()
+        .filter(|(x, y)| *x == min_x || *x == max_x || *y == min_y || *y == max_y)
+        .collect()
+}
+
+/// 从`start`开始四连通地填充与其状态相同的区域
+/// 搜索限制在`bounds` (min_x, min_y, max_x, max_y) 内且最多`limit`个cell,
+/// 坐标按网格拓扑映射, 返回映射后的坐标
+pub fn flood_region(cell_grid: &grid::CellGrid, start: (i32, i32),
+    bounds: (i32, i32, i32, i32), limit: usize) -> Vec<(i32, i32)>
+{
+    let (min_x, min_y, max_x, max_y) = bounds;
+    let in_bounds = |(x, y): (i32, i32)| min_x <= x && x <= max_x && min_y <= y && y <= max_y;
+    let Some(origin) = cell_grid.normalize(&start) else {
+        return Vec::new();
+    };
+    if !in_bounds(start) {
+        return Vec::new();
+    }
+
+    let target = cell_grid.is_alive(&origin);
+    let mut visited = HashSet::from([origin]);
+    let mut region = vec![origin];
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, y)) = queue.pop_front() {
+        for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+            if region.len() >= limit {
+                return region;
+            }
+            if !in_bounds(next) {
+                continue;
+            }
+            let Some(addr) = cell_grid.normalize(&next) else {
+                continue;
+            };
+            if cell_grid.is_alive(&addr) != target || !visited.insert(addr) {
+                continue;
+            }
+            region.push(addr);
+            queue.push_back(next);
+        }
+    }
+    region
+}
+
+/// 从活细胞`start`出发, 收集间距(切比雪夫距离)不超过`gap`的活细胞, 用于识别飞船等由不相连部分组成的物体
+/// 搜索限制在`bounds`内且最多`limit`个cell, 返回映射后的坐标
+pub fn cluster(cell_grid: &grid::CellGrid, start: (i32, i32), gap: i32,
+    bounds: (i32, i32, i32, i32), limit: usize) -> Vec<(i32, i32)>
+{
+    let (min_x, min_y, max_x, max_y) = bounds;
+    let in_bounds = |(x, y): (i32, i32)| min_x <= x && x <= max_x && min_y <= y && y <= max_y;
+    let Some(origin) = cell_grid.normalize(&start) else {
+        return Vec::new();
+    };
+    if !in_bounds(start) || !cell_grid.is_alive(&origin) {
+        return Vec::new();
+    }
+
+    let mut visited = HashSet::from([origin]);
+    let mut cells = vec![origin];
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, y)) = queue.pop_front() {
+        for dy in -gap..=gap {
+            for dx in -gap..=gap {
+                if cells.len() >= limit {
+                    return cells;
+                }
+                let next = (x + dx, y + dy);
+                if !in_bounds(next) {
+                    continue;
+                }
+                let Some(addr) = cell_grid.normalize(&next) else {
+                    continue;
+                };
+                if !cell_grid.is_alive(&addr) || !visited.insert(addr) {
+                    continue;
+                }
+                cells.push(addr);
+                queue.push_back(next);
+            }
+        }
+    }
+    cells
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+static MAX_SAMPLES: usize = 1 << 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationStats {
+    pub generation: u64,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+/// 最近若干代的种群数量记录, 按代数递增排列
+pub struct PopulationHistory {
+    samples: VecDeque<GenerationStats>,
+}
+
+impl PopulationHistory {
+    pub fn new(population: usize) -> Self {
+        let initial = GenerationStats { generation: 0, population, births: 0, deaths: 0 };
+        PopulationHistory { samples: VecDeque::from([initial]) }
+    }
+
+    pub fn samples(&self) -> &VecDeque<GenerationStats> {
+        &self.samples
+    }
+
+    pub fn record(&mut self, stats: GenerationStats) {
+        self.truncate(stats.generation.saturating_sub(1));
+        self.samples.push_back(stats);
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    /// 撤销演化或编辑后同步当前代的数据, 丢弃之后的记录
+    pub fn sync(&mut self, generation: u64, population: usize) {
+        self.truncate(generation);
+        match self.samples.back_mut() {
+            Some(last) if last.generation == generation => last.population = population,
+            _ => self.samples.push_back(
+                GenerationStats { generation, population, births: 0, deaths: 0 }),
+        }
+    }
+
+    fn truncate(&mut self, generation: u64) {
+        while self.samples.back().is_some_and(|last| last.generation > generation) {
+            self.samples.pop_back();
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use super::{
+    grid,
+    activity::CellActivity,
+    annotation::Annotation,
+    pattern::Pattern,
+    random::XorShift,
+    statistics::{
+        GenerationStats,
+        PopulationHistory,
+    },
+    history::{
+        CellDiff,
+        Command,
+        History,
+    },
+    rules::{
+        LifeRule,
+        RulesInterface,
+    },
+};
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    collections::HashSet,
+};
+
+pub type RcUniverse = Rc<RefCell<Universe>>;
+
+/// 被模拟的宇宙, 所有对网格的修改都经过这里并记录到历史中
+pub struct Universe {
+    grid: grid::CellGrid,
+    rule: LifeRule,
+    generation: u64,
+    history: History,
+    statistics: PopulationHistory,
+    activity: CellActivity,
+    /// 标注不记录到历史中, 撤销不影响标注
+    annotations: Vec<Annotation>,
+    annotation_revision: u64,
+}
+
+impl Universe {
+    pub fn new(grid: grid::CellGrid) -> Self {
+        let statistics = PopulationHistory::new(grid.alive_cells().len());
+        let activity = CellActivity::new(grid.alive_cells());
+        Universe {
+            grid,
+            rule: LifeRule::default(),
+            generation: 0,
+            history: History::new(),
+            statistics,
+            activity,
+            annotations: Vec::new(),
+            annotation_revision: 0,
+        }
+    }
+
+    pub fn with_rule(grid: grid::CellGrid, rule: LifeRule) -> Self {
+        Universe { rule, ..Universe::new(grid) }
+    }
+
+    pub fn grid(&self) -> &grid::CellGrid {
+        &self.grid
+    }
+
+    pub fn rule(&self) -> LifeRule {
+        self.rule
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn statistics(&self) -> &PopulationHistory {
+        &self.statistics
+    }
+
+    pub fn activity(&self) -> &CellActivity {
+        &self.activity
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// 每次修改标注都会递增, 用于判断视图是否需要重绘
+    pub fn annotation_revision(&self) -> u64 {
+        self.annotation_revision
+    }
+
+    pub fn add_annotation(&mut self, annotation: Annotation) {
+        self.annotations.push(annotation);
+        self.annotation_revision += 1;
+    }
+
+    pub fn set_annotations(&mut self, annotations: Vec<Annotation>) {
+        self.annotations = annotations;
+        self.annotation_revision += 1;
+    }
+
+    /// 删除与`bounds`闭区间相交的标注, 返回删除的数量
+    pub fn remove_annotations(&mut self, bounds: (i32, i32, i32, i32)) -> usize {
+        let (min_x, min_y, max_x, max_y) = bounds;
+        let before = self.annotations.len();
+        self.annotations.retain(|annotation| {
+            let (left, top, right, bottom) = annotation.bounds();
+            right < min_x || max_x < left || bottom < min_y || max_y < top
+        });
+        let removed = before - self.annotations.len();
+        if removed > 0 {
+            self.annotation_revision += 1;
+        }
+        removed
+    }
+
+    pub fn set_rule(&mut self, rule: LifeRule) {
+        self.history.record_rule(self.rule, rule);
+        self.rule = rule;
+    }
+
+    /// `f`中的所有编辑合并为一条撤销记录
+    pub fn grouped<R, F: FnOnce(&mut Self) -> R>(&mut self, f: F) -> R {
+        self.history.begin_group();
+        let result = f(self);
+        self.history.end_group();
+        result
+    }
+
+    pub fn begin_group(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_group(&mut self) {
+        self.history.end_group();
+    }
+
+    /// 返回状态实际发生变化的cell数
+    pub fn set_cells(&mut self, cells: &[(i32, i32)], alive: bool) -> usize {
+        let mut diff = CellDiff::default();
+        for addr in cells {
+            if let Some(addr) = self.grid.normalize(addr)
+                && self.grid.set_cell(&addr, alive) {
+                diff.flip(addr);
+            }
+        }
+        let changed = diff.len();
+        if changed > 0 {
+            self.statistics.sync(self.generation, self.grid.alive_cells().len());
+            self.activity.sync(diff.cells(), self.grid.alive_cells());
+        }
+        self.history.record_edit(diff);
+        changed
+    }
+
+    pub fn toggle_cell(&mut self, addr: &(i32, i32)) -> bool {
+        let Some(addr) = self.grid.normalize(addr) else {
+            return false;
+        };
+        let alive = !self.grid.is_alive(&addr);
+        self.set_cells(&[addr], alive) > 0
+    }
+
+    /// 复制`bounds` (min_x, min_y, max_x, max_y) 闭区间内的cell, 图案大小与区域一致
+    pub fn extract(&self, bounds: (i32, i32, i32, i32)) -> Pattern {
+        let (min_x, min_y, max_x, max_y) = bounds;
+        let cells = self.grid
+            .alive_cells()
+            .iter()
+            .filter(|(x, y)| min_x <= *x && *x <= max_x && min_y <= *y && *y <= max_y)
+            .map(|(x, y)| (x - min_x, y - min_y))
+            .collect();
+        Pattern::new(cells, max_x - min_x + 1, max_y - min_y + 1)
+    }
+
+    /// `inside`为false时清除区域外的所有cell
+    pub fn clear_region(&mut self, bounds: (i32, i32, i32, i32), inside: bool) -> usize {
+        let (min_x, min_y, max_x, max_y) = bounds;
+        let cells: Vec<_> = self.grid
+            .alive_cells()
+            .iter()
+            .filter(|(x, y)| {
+                let contained = min_x <= *x && *x <= max_x && min_y <= *y && *y <= max_y;
+                contained == inside
+            })
+            .copied()
+            .collect();
+        self.set_cells(&cells, false)
+    }
+
+    pub fn clear(&mut self) -> usize {
+        let cells: Vec<_> = self.grid.alive_cells().iter().copied().collect();
+        self.set_cells(&cells, false)
+    }
+
+    /// 以`density`的概率随机填充区域, 区域内原有的cell被覆盖
+    pub fn randomize(&mut self, bounds: (i32, i32, i32, i32), density: f64) -> usize {
+        let (min_x, min_y, max_x, max_y) = bounds;
+        let mut rng = XorShift::from_time();
+        let cells: Vec<_> = (min_y..=max_y)
+            .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+            .filter(|_| rng.next_f64() < density)
+            .collect();
+        self.grouped(|universe| {
+            universe.clear_region(bounds, true);
+            universe.set_cells(&cells, true)
+        })
+    }
+
+    /// 以`origin`为左上角叠加图案, 不清除原有的活细胞
+    pub fn stamp(&mut self, pattern: &Pattern, origin: (i32, i32)) -> usize {
+        self.set_cells(&pattern.placed_at(origin), true)
+    }
+
+    /// 连续的演化合并为一条记录, 撤销时回到最近一次编辑后的状态
+    pub fn step(&mut self) {
+        let next = self.rule.next_gen(&self.grid);
+        let flipped: HashSet<_> = self.grid
+            .alive_cells()
+            .symmetric_difference(next.alive_cells())
+            .copied()
+            .collect();
+        let births = flipped
+            .iter()
+            .filter(|addr| next.is_alive(addr))
+            .count();
+        let deaths = flipped.len() - births;
+        self.grid.replace(next);
+        self.generation += 1;
+        self.activity.record_step(&flipped, self.grid.alive_cells());
+        self.history.record_step(CellDiff::from_flipped(flipped));
+        self.statistics.record(GenerationStats {
+            generation: self.generation,
+            population: self.grid.alive_cells().len(),
+            births,
+            deaths,
+        });
+    }
+
+    pub fn undo_generations(&self) -> Option<u64> {
+        self.history.undo_generations()
+    }
+
+    pub fn redo_generations(&self) -> Option<u64> {
+        self.history.redo_generations()
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(command) = self.history.take_undo() else {
+            return false;
+        };
+        self.apply(&command, true);
+        self.history.push_redo(command);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(command) = self.history.take_redo() else {
+            return false;
+        };
+        self.apply(&command, false);
+        self.history.push_undo(command);
+        true
+    }
+
+    fn apply(&mut self, command: &Command, reverse: bool) {
+        match command {
+            Command::Edit(diff) => self.flip(diff),
+            Command::Run { diff, generations } => {
+                self.flip(diff);
+                if reverse {
+                    self.generation -= generations;
+                } else {
+                    self.generation += generations;
+                }
+            },
+            Command::SetRule { before, after } => {
+                self.rule = if reverse { *before } else { *after };
+            },
+        }
+        // 只有翻转的cell需要与活动记录同步, 演化还需要调整存活细胞的年龄
+        match command {
+            Command::Edit(diff) => self.activity.sync(diff.cells(), self.grid.alive_cells()),
+            Command::Run { diff, generations } => {
+                let generations = if reverse { -(*generations as i64) } else { *generations as i64 };
+                self.activity.sync_run(diff.cells(), self.grid.alive_cells(), generations);
+            },
+            Command::SetRule { .. } => return,
+        }
+        self.statistics.sync(self.generation, self.grid.alive_cells().len());
+    }
+
+    fn flip(&mut self, diff: &CellDiff) {
+        diff.cells()
+            .iter()
+            .for_each(|addr| { self.grid.toggle_cell(addr); });
+    }
+}
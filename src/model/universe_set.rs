@@ -0,0 +1,169 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use crate::utils::error;
+use super::{
+    grid,
+    rules::LifeRule,
+    universe::{
+        RcUniverse,
+        Universe,
+    },
+};
+
+pub type RcUniverseSet = Rc<RefCell<UniverseSet>>;
+
+pub static MAIN_UNIVERSE: &str = "main";
+
+/// 同步演化的若干宇宙, 用于并排比较不同的规则
+/// 第一个宇宙为`main`, 其他宇宙由布局文件定义, 网格大小与拓扑与`main`相同
+pub struct UniverseSet {
+    universes: Vec<(String, RcUniverse)>,
+    /// 最近一次被编辑或操作的宇宙, 编辑的撤销/重做作用于它
+    active: usize,
+    /// 尚未推进的代数, 分散到之后的若干帧中执行
+    pending_steps: u64,
+}
+
+impl UniverseSet {
+    pub fn new(main: RcUniverse) -> Self {
+        UniverseSet { universes: vec![(MAIN_UNIVERSE.to_string(), main)], active: 0, pending_steps: 0 }
+    }
+
+    pub fn get(&self, name: &str) -> Option<RcUniverse> {
+        self.universes
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, universe)| universe.clone())
+    }
+
+    /// 已存在同名的宇宙时返回它, 不会修改其规则
+    pub fn get_or_create(&mut self, name: &str, rule: LifeRule) -> RcUniverse {
+        if let Some(universe) = self.get(name) {
+            return universe;
+        }
+        let universe = {
+            let main = self.universes[0].1.borrow();
+            let grid = grid::CellGrid::new(main.grid().upper_scale())
+                .with_topology(main.grid().topology());
+            Universe::with_rule(grid, rule)
+        };
+        let universe = Rc::new(RefCell::new(universe));
+        self.universes.push((name.to_string(), universe.clone()));
+        universe
+    }
+
+    pub fn count(&self) -> usize {
+        self.universes.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RcUniverse)> {
+        self.universes
+            .iter()
+            .map(|(name, universe)| (name.as_str(), universe))
+    }
+
+    pub fn name_of(&self, universe: &RcUniverse) -> Option<&str> {
+        self.universes
+            .iter()
+            .find(|(_, known)| Rc::ptr_eq(known, universe))
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn active(&self) -> RcUniverse {
+        self.universes[self.active].1.clone()
+    }
+
+    pub fn set_active(&mut self, universe: &RcUniverse) {
+        if let Some(idx) = self.universes.iter().position(|(_, known)| Rc::ptr_eq(known, universe)) {
+            self.active = idx;
+        }
+    }
+
+    /// 所有宇宙一起推进一代
+    pub fn step(&self) {
+        self.universes
+            .iter()
+            .for_each(|(_, universe)| universe.borrow_mut().step());
+    }
+
+    /// 之后由`run_pending`分批推进, 避免一次推进很多代时界面卡住
+    pub fn queue_steps(&mut self, count: u64) {
+        self.pending_steps = self.pending_steps.saturating_add(count);
+    }
+
+    pub fn pending_steps(&self) -> u64 {
+        self.pending_steps
+    }
+
+    pub fn cancel_pending(&mut self) {
+        self.pending_steps = 0;
+    }
+
+    /// 在`budget`时间内推进等待中的代, 至少推进一代, 返回推进的代数
+    pub fn run_pending(&mut self, budget: Duration) -> u64 {
+        let start = Instant::now();
+        let mut count = 0;
+        while self.pending_steps > 0 && (count == 0 || start.elapsed() < budget) {
+            self.step();
+            self.pending_steps -= 1;
+            count += 1;
+        }
+        count
+    }
+
+    /// 撤销最近操作的宇宙, 返回是否撤销了, 见`lockstep`
+    pub fn undo(&self) -> Result<bool, String> {
+        self.lockstep(Universe::undo_generations, Universe::undo)
+    }
+
+    pub fn redo(&self) -> Result<bool, String> {
+        self.lockstep(Universe::redo_generations, Universe::redo)
+    }
+
+    /// 编辑与规则的记录只作用于最近操作的宇宙
+    /// 演化记录则所有宇宙一起撤销/重做, 否则比较中的宇宙不再处于同一代;
+    /// 某个宇宙的下一条记录不是代数相同的演化时 (例如演化之后被单独编辑过) 不执行, 返回该宇宙的名称
+    fn lockstep(&self, generations: fn(&Universe) -> Option<u64>, apply: fn(&mut Universe) -> bool)
+        -> Result<bool, String>
+    {
+        let active = self.active();
+        let Some(run) = generations(&active.borrow()) else {
+            return Ok(apply(&mut active.borrow_mut()));
+        };
+        if let Some((name, _)) = self.universes
+            .iter()
+            .find(|(_, universe)| generations(&universe.borrow()) != Some(run)) {
+            return Err(name.clone());
+        }
+        self.universes
+            .iter()
+            .for_each(|(_, universe)| { apply(&mut universe.borrow_mut()); });
+        Ok(true)
+    }
+
+    /// 把`source`中的所有cell复制到其他宇宙, 返回被修改的宇宙数
+    /// 每个宇宙的复制作为一条撤销记录
+    pub fn sync_from(&self, source: &RcUniverse) -> error::Result<usize> {
+        if self.name_of(source).is_none() {
+            return Err(String::from("the universe is not part of the set").into());
+        }
+        let cells: Vec<_> = source.borrow().grid().alive_cells().iter().copied().collect();
+        let targets: Vec<_> = self.universes
+            .iter()
+            .filter(|(_, universe)| !Rc::ptr_eq(universe, source))
+            .collect();
+        for (_, universe) in targets.iter() {
+            universe.borrow_mut().grouped(|universe| {
+                universe.clear();
+                universe.set_cells(&cells, true);
+            });
+        }
+        Ok(targets.len())
+    }
+}
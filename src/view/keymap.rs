@@ -0,0 +1,508 @@
+use std::{
+    fmt,
+    fs,
+    path::Path,
+    str::FromStr,
+};
+use sdl2::{
+    keyboard::{
+        Keycode,
+        Mod,
+    },
+    mouse::MouseButton,
+};
+use crate::utils::error;
+use super::widget::event::WidgetEvent;
+
+pub type RcKeymap = std::rc::Rc<std::cell::RefCell<Keymap>>;
+
+/// 绑定生效的范围, 相机、图表与覆盖层的绑定优先于全局绑定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Camera,
+    /// 人口曲线图
+    Graph,
+    /// 显示中的覆盖层, 先于其下的控件收到按键
+    Overlay,
+}
+
+impl Scope {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scope::Global => "global",
+            Scope::Camera => "camera",
+            Scope::Graph => "graph",
+            Scope::Overlay => "overlay",
+        }
+    }
+
+    pub fn all() -> [Scope; 4] {
+        [Scope::Global, Scope::Camera, Scope::Graph, Scope::Overlay]
+    }
+
+    fn overlaps(&self, other: Scope) -> bool {
+        *self == other || *self == Scope::Global || other == Scope::Global
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ToggleRun,
+    Step,
+    Undo,
+    Redo,
+    ToggleConsole,
+    ToggleHelp,
+    OpenFile,
+    SaveFile,
+    ToggleFullscreen,
+    ToggleExclusiveFullscreen,
+    CycleLanguage,
+    ToolPan,
+    ToolPencil,
+    ToolEraser,
+    ToolToggle,
+    ToolLine,
+    ToolRect,
+    ToolFilledRect,
+    ToolFill,
+    ToolSelect,
+    Copy,
+    Cut,
+    Paste,
+    Cancel,
+    ClearSelection,
+    ClearOutside,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    FlipHorizontal,
+    FlipVertical,
+    Transpose,
+    CycleColorMode,
+    ZoomToFit,
+    Follow,
+    NudgeLeft,
+    NudgeRight,
+    NudgeUp,
+    NudgeDown,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    Draw,
+    DrawInverse,
+    DragPan,
+    GraphToggleBirths,
+    GraphToggleDeaths,
+    GraphScrollBack,
+    GraphScrollForward,
+    GraphLatest,
+    CloseOverlay,
+}
+
+/// (动作, 配置文件中的名称, 范围, 默认绑定), 说明在语言文件的`action.<名称>`中
+type ActionInfo = (Action, &'static str, Scope, &'static [&'static str]);
+
+static ACTIONS: [ActionInfo; 53] = [
+    (Action::Quit, "quit", Scope::Global, &["Ctrl+Q"]),
+    (Action::ToggleRun, "toggle_run", Scope::Global, &["Space"]),
+    (Action::Step, "step", Scope::Global, &["N"]),
+    (Action::Undo, "undo", Scope::Global, &["Ctrl+Z"]),
+    (Action::Redo, "redo", Scope::Global, &["Ctrl+Shift+Z", "Ctrl+Y"]),
+    (Action::ToggleConsole, "toggle_console", Scope::Global, &["`"]),
+    (Action::ToggleHelp, "toggle_help", Scope::Global, &["F1"]),
+    (Action::OpenFile, "open_file", Scope::Global, &["Ctrl+O"]),
+    (Action::SaveFile, "save_file", Scope::Global, &["Ctrl+S"]),
+    (Action::ToggleFullscreen, "toggle_fullscreen", Scope::Global, &["F11"]),
+    (Action::ToggleExclusiveFullscreen, "toggle_exclusive_fullscreen", Scope::Global, &["Alt+Return"]),
+    (Action::CycleLanguage, "cycle_language", Scope::Global, &["Ctrl+L"]),
+    (Action::ToolPan, "tool_pan", Scope::Camera, &["1"]),
+    (Action::ToolPencil, "tool_pencil", Scope::Camera, &["2"]),
+    (Action::ToolEraser, "tool_eraser", Scope::Camera, &["3"]),
+    (Action::ToolToggle, "tool_toggle", Scope::Camera, &["4"]),
+    (Action::ToolLine, "tool_line", Scope::Camera, &["5"]),
+    (Action::ToolRect, "tool_rect", Scope::Camera, &["6"]),
+    (Action::ToolFilledRect, "tool_filled_rect", Scope::Camera, &["7"]),
+    (Action::ToolFill, "tool_fill", Scope::Camera, &["8"]),
+    (Action::ToolSelect, "tool_select", Scope::Camera, &["9"]),
+    (Action::Copy, "copy", Scope::Camera, &["Ctrl+C"]),
+    (Action::Cut, "cut", Scope::Camera, &["Ctrl+X"]),
+    (Action::Paste, "paste", Scope::Camera, &["Ctrl+V"]),
+    (Action::Cancel, "cancel", Scope::Camera, &["Escape"]),
+    (Action::ClearSelection, "clear_selection", Scope::Camera, &["Delete"]),
+    (Action::ClearOutside, "clear_outside", Scope::Camera, &["Shift+Delete"]),
+    (Action::RotateCw, "rotate_cw", Scope::Camera, &["R"]),
+    (Action::RotateCcw, "rotate_ccw", Scope::Camera, &["Shift+R"]),
+    (Action::Rotate180, "rotate_180", Scope::Camera, &["Ctrl+R"]),
+    (Action::FlipHorizontal, "flip_horizontal", Scope::Camera, &["H"]),
+    (Action::FlipVertical, "flip_vertical", Scope::Camera, &["V"]),
+    (Action::Transpose, "transpose", Scope::Camera, &["T"]),
+    (Action::CycleColorMode, "cycle_color_mode", Scope::Camera, &["C"]),
+    (Action::ZoomToFit, "zoom_to_fit", Scope::Camera, &["Home"]),
+    (Action::Follow, "follow", Scope::Camera, &["F"]),
+    (Action::NudgeLeft, "nudge_left", Scope::Camera, &["Shift+Left"]),
+    (Action::NudgeRight, "nudge_right", Scope::Camera, &["Shift+Right"]),
+    (Action::NudgeUp, "nudge_up", Scope::Camera, &["Shift+Up"]),
+    (Action::NudgeDown, "nudge_down", Scope::Camera, &["Shift+Down"]),
+    (Action::PanLeft, "pan_left", Scope::Camera, &["Left", "A"]),
+    (Action::PanRight, "pan_right", Scope::Camera, &["Right", "D"]),
+    (Action::PanUp, "pan_up", Scope::Camera, &["Up", "W"]),
+    (Action::PanDown, "pan_down", Scope::Camera, &["Down", "S"]),
+    (Action::Draw, "draw", Scope::Camera, &["Mouse:Left"]),
+    (Action::DrawInverse, "draw_inverse", Scope::Camera, &["Mouse:Right"]),
+    (Action::DragPan, "drag_pan", Scope::Camera, &["Mouse:Middle"]),
+    (Action::GraphToggleBirths, "graph_toggle_births", Scope::Graph, &["B"]),
+    (Action::GraphToggleDeaths, "graph_toggle_deaths", Scope::Graph, &["D"]),
+    (Action::GraphScrollBack, "graph_scroll_back", Scope::Graph, &["Left"]),
+    (Action::GraphScrollForward, "graph_scroll_forward", Scope::Graph, &["Right"]),
+    (Action::GraphLatest, "graph_latest", Scope::Graph, &["End"]),
+    (Action::CloseOverlay, "close_overlay", Scope::Overlay, &["Escape"]),
+];
+
+impl Action {
+    fn info(&self) -> &'static ActionInfo {
+        ACTIONS
+            .iter()
+            .find(|info| info.0 == *self)
+            .expect("every action should be listed in ACTIONS")
+    }
+
+    pub fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.iter().map(|info| info.0)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.info().1
+    }
+
+    pub fn scope(&self) -> Scope {
+        self.info().2
+    }
+}
+
+impl FromStr for Action {
+    type Err = error::DynError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ACTIONS
+            .iter()
+            .find(|info| info.1 == name)
+            .map(|info| info.0)
+            .ok_or_else(|| format!("unknown action `{}`", name).into())
+    }
+}
+
+/// 只区分左右无关的四种修饰键, 忽略NumLock/CapsLock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub gui: bool,
+}
+
+impl Modifiers {
+    pub fn from_sdl(keymod: Mod) -> Self {
+        Modifiers {
+            ctrl: keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+            shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+            alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+            gui: keymod.intersects(Mod::LGUIMOD | Mod::RGUIMOD),
+        }
+    }
+
+    /// 识别修饰键名称时修改自身, 不是修饰键时返回false
+    fn add(&mut self, name: &str) -> bool {
+        match name.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => self.ctrl = true,
+            "shift" => self.shift = true,
+            "alt" | "option" => self.alt = true,
+            "gui" | "cmd" | "super" | "meta" => self.gui = true,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// 触发动作的按键组合或鼠标按键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Key { keycode: Keycode, modifiers: Modifiers },
+    Mouse(MouseButton),
+}
+
+impl FromStr for Trigger {
+    type Err = error::DynError;
+
+    /// 接受 "Ctrl+Shift+Z", "Space", "`", "Mouse:Left" 等写法, 不区分大小写
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if let Some((prefix, button)) = text.split_once(':')
+            && prefix.eq_ignore_ascii_case("mouse") {
+            let button = match button.trim().to_ascii_lowercase().as_str() {
+                "left" => MouseButton::Left,
+                "middle" => MouseButton::Middle,
+                "right" => MouseButton::Right,
+                "x1" => MouseButton::X1,
+                "x2" => MouseButton::X2,
+                _ => return Err(format!("unknown mouse button `{}` in `{}`", button, text).into()),
+            };
+            return Ok(Trigger::Mouse(button));
+        }
+
+        // 按键名本身可能含有`+`(如 "Keypad +"), 因此只从前面剥离修饰键
+        let mut modifiers = Modifiers::default();
+        let mut rest = text;
+        while let Some((head, tail)) = rest.split_once('+')
+            && !tail.is_empty()
+            && modifiers.add(head.trim()) {
+            rest = tail;
+        }
+        let key = rest.trim();
+        let keycode = Keycode::from_name(key)
+            .ok_or_else(|| format!("unknown key `{}` in `{}`", key, text))?;
+        Ok(Trigger::Key { keycode, modifiers })
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Key { keycode, modifiers } => {
+                let names = [
+                    (modifiers.ctrl, "Ctrl"),
+                    (modifiers.shift, "Shift"),
+                    (modifiers.alt, "Alt"),
+                    (modifiers.gui, "Gui"),
+                ];
+                for (_, name) in names.iter().filter(|(pressed, _)| *pressed) {
+                    write!(f, "{}+", name)?;
+                }
+                write!(f, "{}", keycode.name())
+            },
+            Trigger::Mouse(button) => write!(f, "Mouse:{:?}", button),
+        }
+    }
+}
+
+/// 按键组合/鼠标按键到命名动作的映射, 用户配置中出现的动作替换其默认绑定
+pub struct Keymap {
+    bindings: Vec<(Trigger, Action)>,
+    /// 配置中的错误与冲突, 启动时输出并显示在帮助中
+    problems: Vec<String>,
+}
+
+impl Keymap {
+    pub fn defaults() -> Self {
+        let bindings = ACTIONS
+            .iter()
+            .flat_map(|(action, _, _, defaults)| defaults
+                .iter()
+                .map(|text| (text.parse().expect("default binding should be valid"), *action)))
+            .collect();
+        Keymap { bindings, problems: Vec::new() }
+    }
+
+    /// 读取失败时返回错误, 单个条目的错误记录在`problems`中并跳过该条目
+    pub fn load(path: &Path) -> error::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut keymap = Keymap::from_toml(&text)?;
+        keymap.problems
+            .iter_mut()
+            .for_each(|problem| *problem = format!("{}: {}", path.display(), problem));
+        Ok(keymap)
+    }
+
+    /// 格式为 `[bindings]` 表, 值为一个或多个绑定, 空数组表示取消绑定:
+    /// ```toml
+    /// [bindings]
+    /// undo = "Ctrl+Z"
+    /// redo = ["Ctrl+Shift+Z", "Ctrl+Y"]
+    /// ```
+    pub fn from_toml(text: &str) -> error::Result<Self> {
+        let table: toml::Table = text.parse()?;
+        let mut keymap = Keymap::defaults();
+        let mut user_bindings = Vec::new();
+        for (key, value) in table.iter() {
+            let toml::Value::Table(entries) = value else {
+                keymap.problems.push(format!("unexpected key `{}`, bindings belong in [bindings]", key));
+                continue;
+            };
+            if key != "bindings" {
+                keymap.problems.push(format!("unknown table [{}]", key));
+                continue;
+            }
+            for (name, value) in entries.iter() {
+                match Self::parse_entry(name, value) {
+                    Ok(entry) => user_bindings.push(entry),
+                    Err(e) => keymap.problems.push(format!("`{}`: {}", name, e)),
+                }
+            }
+        }
+
+        // 配置中出现的动作不再保留默认绑定, 其余默认绑定在用户绑定之后加入
+        let mut defaults = std::mem::take(&mut keymap.bindings);
+        defaults.retain(|(_, bound)| user_bindings.iter().all(|(action, _)| action != bound));
+        for (action, triggers) in user_bindings {
+            for trigger in triggers {
+                match keymap.conflict(trigger, action.scope()) {
+                    Some(other) => keymap.problems.push(format!(
+                        "`{}` is bound to both `{}` and `{}`, keeping `{}`",
+                        trigger, other.name(), action.name(), other.name())),
+                    None => keymap.bindings.push((trigger, action)),
+                }
+            }
+        }
+        // 用户的绑定优先, 被占用的默认绑定让出
+        for (trigger, action) in defaults {
+            match keymap.conflict(trigger, action.scope()) {
+                Some(other) => keymap.problems.push(format!(
+                    "`{}` now triggers `{}`, `{}` loses its default binding",
+                    trigger, other.name(), action.name())),
+                None => keymap.bindings.push((trigger, action)),
+            }
+        }
+        Ok(keymap)
+    }
+
+    fn parse_entry(name: &str, value: &toml::Value) -> error::Result<(Action, Vec<Trigger>)> {
+        let action: Action = name.parse()?;
+        let texts: Vec<&str> = match value {
+            toml::Value::String(text) => vec![text.as_str()],
+            toml::Value::Array(values) => values
+                .iter()
+                .map(|value| value.as_str().ok_or("bindings should be strings"))
+                .collect::<Result<_, _>>()?,
+            _ => return Err(String::from("expected a string or an array of strings").into()),
+        };
+        let triggers = texts
+            .into_iter()
+            .map(str::parse)
+            .collect::<error::Result<_>>()?;
+        Ok((action, triggers))
+    }
+
+    fn conflict(&self, trigger: Trigger, scope: Scope) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, action)| *bound == trigger && action.scope().overlaps(scope))
+            .map(|(_, action)| *action)
+    }
+
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    pub fn key_action(&self, keycode: Keycode, keymod: Mod, scope: Scope) -> Option<Action> {
+        let trigger = Trigger::Key { keycode, modifiers: Modifiers::from_sdl(keymod) };
+        self.find(trigger, scope)
+    }
+
+    pub fn mouse_action(&self, button: MouseButton, scope: Scope) -> Option<Action> {
+        self.find(Trigger::Mouse(button), scope)
+    }
+
+    /// 按下按键或鼠标按键的事件对应的动作, 忽略按键的自动重复
+    pub fn event_action(&self, event: &WidgetEvent, scope: Scope) -> Option<Action> {
+        match *event {
+            WidgetEvent::KeyDown { keycode, keymod, repeat: false } => {
+                self.key_action(keycode, keymod, scope)
+            },
+            WidgetEvent::MouseButtonDown { button, .. } => self.mouse_action(button, scope),
+            _ => None,
+        }
+    }
+
+    fn find(&self, trigger: Trigger, scope: Scope) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, action)| *bound == trigger && action.scope() == scope)
+            .map(|(_, action)| *action)
+    }
+
+    pub fn triggers(&self, action: Action) -> Vec<Trigger> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(trigger, _)| *trigger)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(text: &str) -> Trigger {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn defaults_do_not_conflict() {
+        let keymap = Keymap::from_toml("").unwrap();
+        assert!(keymap.problems().is_empty());
+        assert_eq!(keymap.triggers(Action::CloseOverlay), vec![trigger("Escape")]);
+        assert_eq!(keymap.triggers(Action::Cancel), vec![trigger("Escape")]);
+    }
+
+    #[test]
+    fn every_action_has_a_description() {
+        let locale = super::super::locale::LocaleManager::bundled();
+        for action in Action::all() {
+            assert!(locale.lookup(&format!("action.{}", action.name())).is_some(), "{}", action.name());
+        }
+    }
+
+    #[test]
+    fn user_binding_replaces_defaults_of_the_action() {
+        let keymap = Keymap::from_toml("[bindings]\nundo = \"Ctrl+U\"").unwrap();
+        assert_eq!(keymap.triggers(Action::Undo), vec![trigger("Ctrl+U")]);
+        assert_eq!(keymap.triggers(Action::Redo), vec![trigger("Ctrl+Shift+Z"), trigger("Ctrl+Y")]);
+        assert!(keymap.problems().is_empty());
+    }
+
+    #[test]
+    fn empty_array_unbinds() {
+        let keymap = Keymap::from_toml("[bindings]\nundo = []").unwrap();
+        assert!(keymap.triggers(Action::Undo).is_empty());
+        assert!(keymap.problems().is_empty());
+    }
+
+    #[test]
+    fn displaced_default_is_reported() {
+        let keymap = Keymap::from_toml("[bindings]\nfollow = \"C\"").unwrap();
+        assert_eq!(keymap.triggers(Action::Follow), vec![trigger("C")]);
+        assert!(keymap.triggers(Action::CycleColorMode).is_empty());
+        assert_eq!(keymap.problems().len(), 1);
+        assert!(keymap.problems()[0].contains("cycle_color_mode"));
+    }
+
+    #[test]
+    fn global_binding_displaces_camera_default() {
+        let keymap = Keymap::from_toml("[bindings]\ntoggle_run = \"R\"").unwrap();
+        assert_eq!(keymap.triggers(Action::ToggleRun), vec![trigger("R")]);
+        assert!(keymap.triggers(Action::RotateCw).is_empty());
+        assert!(keymap.problems()[0].contains("rotate_cw"));
+    }
+
+    #[test]
+    fn clash_between_user_entries_keeps_the_first_and_is_reported() {
+        let keymap = Keymap::from_toml("[bindings]\nredo = \"Ctrl+K\"\nundo = \"Ctrl+K\"").unwrap();
+        // 表中的键按名称排序, redo先于undo
+        assert_eq!(keymap.triggers(Action::Redo), vec![trigger("Ctrl+K")]);
+        assert!(keymap.triggers(Action::Undo).is_empty());
+        assert_eq!(keymap.problems().len(), 1);
+        assert!(keymap.problems()[0].contains("keeping `redo`"));
+    }
+
+    #[test]
+    fn clash_within_camera_scope_is_reported() {
+        let keymap = Keymap::from_toml("[bindings]\ncopy = \"Ctrl+Shift+K\"\npaste = \"Ctrl+Shift+K\"").unwrap();
+        assert_eq!(keymap.triggers(Action::Copy), vec![trigger("Ctrl+Shift+K")]);
+        assert!(keymap.triggers(Action::Paste).is_empty());
+        assert_eq!(keymap.problems().len(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    env,
+    fmt,
+    fs,
+    path::Path,
+};
+use crate::utils::error;
+
+pub type RcLocaleManager = std::rc::Rc<std::cell::RefCell<LocaleManager>>;
+
+/// 随程序发布的语言, 第一个是缺少译文时的后备
+static BUNDLED: [(&str, &str); 2] = [
+    ("en", include_str!("../../locales/en.toml")),
+    ("zh", include_str!("../../locales/zh.toml")),
+];
+static LOCALE_EXTENSION: &str = "toml";
+
+/// 一种语言的全部界面文字, 键为`<表>.<名称>`
+struct Locale {
+    id: String,
+    name: String,
+    messages: HashMap<String, String>,
+}
+
+impl Locale {
+    fn new(id: &str) -> Self {
+        Locale { id: id.to_string(), name: id.to_string(), messages: HashMap::new() }
+    }
+
+    /// 格式如下, 表可以嵌套, `name`是在语言列表中显示的名称:
+    /// ```toml
+    /// name = "English"
+    ///
+    /// [console]
+    /// rule_set = "rule set to {0}"
+    /// ```
+    fn parse(id: &str, text: &str) -> error::Result<Self> {
+        let table: toml::Table = text.parse()?;
+        let mut locale = Locale::new(id);
+        locale.merge(&table, "")?;
+        Ok(locale)
+    }
+
+    /// 用`table`中的文字覆盖已有的文字
+    fn merge(&mut self, table: &toml::Table, prefix: &str) -> error::Result<()> {
+        for (key, value) in table.iter() {
+            let entry = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            match value {
+                toml::Value::String(text) if entry == "name" => self.name = text.clone(),
+                toml::Value::String(text) => {
+                    self.messages.insert(entry, text.clone());
+                },
+                toml::Value::Table(table) => self.merge(table, &entry)?,
+                _ => return Err(format!("{}: expected a string or a table", entry).into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 界面文字的目录, 内置英文与中文, 可以从目录中读取更多语言或覆盖内置的译文
+pub struct LocaleManager {
+    locales: Vec<Locale>,
+    active: usize,
+    revision: u64,
+}
+
+impl LocaleManager {
+    pub fn bundled() -> Self {
+        let locales = BUNDLED
+            .iter()
+            .map(|(id, text)| Locale::parse(id, text).expect("bundled locale should be valid"))
+            .collect();
+        LocaleManager { locales, active: 0, revision: 0 }
+    }
+
+    /// 读取目录中的`<id>.toml`, 与已有语言同名时合并, 返回无法读取的文件及原因
+    pub fn load_dir(&mut self, dir: &Path) -> error::Result<Vec<String>> {
+        let mut problems = Vec::new();
+        let mut paths: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == LOCALE_EXTENSION))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let Some(id) = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let loaded = fs::read_to_string(&path)
+                .map_err(error::DynError::from)
+                .and_then(|text| Ok(text.parse::<toml::Table>()?))
+                .and_then(|table| match self.locales.iter_mut().find(|locale| locale.id == id) {
+                    Some(locale) => locale.merge(&table, ""),
+                    None => {
+                        let mut locale = Locale::new(&id);
+                        locale.merge(&table, "")?;
+                        self.locales.push(locale);
+                        Ok(())
+                    },
+                });
+            if let Err(e) = loaded {
+                problems.push(format!("{}: {}", path.display(), e));
+            }
+        }
+        self.revision += 1;
+        Ok(problems)
+    }
+
+    /// 按LC_ALL, LC_MESSAGES, LANG的顺序取系统语言, 例如`zh_CN.UTF-8`对应`zh`
+    pub fn select_system(&mut self) {
+        let system = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty());
+        if let Some(system) = system {
+            let id = system.split(['_', '.', '@']).next().unwrap_or_default();
+            self.select(id).ok();
+        }
+    }
+
+    /// 每次切换语言时递增, 用于判断是否需要重绘
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn id(&self) -> &str {
+        &self.locales[self.active].id
+    }
+
+    /// 已知语言的`id (名称)`
+    pub fn available_names(&self) -> Vec<String> {
+        self.locales
+            .iter()
+            .map(|locale| format!("{} ({})", locale.id, locale.name))
+            .collect()
+    }
+
+    pub fn select(&mut self, id: &str) -> error::Result<()> {
+        let idx = self.locales
+            .iter()
+            .position(|locale| locale.id == id)
+            .ok_or_else(|| format!("unknown language `{}`, available: {}", id, self.available_names().join(", ")))?;
+        if idx != self.active {
+            self.active = idx;
+            self.revision += 1;
+        }
+        Ok(())
+    }
+
+    pub fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.locales.len();
+        self.revision += 1;
+    }
+
+    /// 当前语言缺少时使用英文
+    pub fn lookup(&self, key: &str) -> Option<&str> {
+        self.locales[self.active].messages
+            .get(key)
+            .or_else(|| self.locales[0].messages.get(key))
+            .map(String::as_str)
+    }
+
+    /// 找不到时返回键本身, 便于发现遗漏的译文
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(key).unwrap_or(key)
+    }
+
+    /// 把`{0}`, `{1}`...替换为对应的参数
+    pub fn format(&self, key: &str, args: &[&dyn fmt::Display]) -> String {
+        let mut text = self.text(key).to_string();
+        for (i, arg) in args.iter().enumerate() {
+            text = text.replace(&format!("{{{}}}", i), &arg.to_string());
+        }
+        text
+    }
+}
@@ -1,3 +1,7 @@
 pub mod theme;
+pub mod text;
+pub mod locale;
+pub mod keymap;
+pub mod window_state;
 pub mod renderer;
 pub mod widget;
@@ -7,7 +7,11 @@ use sdl2::{
         TextureCreator,
         Canvas,
     },
-    video,
+    video::{
+        self,
+        FullscreenType,
+        WindowPos,
+    },
     event::{
         Event,
     },
@@ -21,12 +25,22 @@ use super::{
         camera,
         widget_base,
         layout,
+        tooltip,
+        event::WidgetEvent,
     },
     theme,
+    text,
+    locale,
+    keymap,
+    window_state::{
+        PresentMode,
+        WindowState,
+    },
 };
 use std::{
     rc::Rc,
     cell::RefCell,
+    time::Duration,
 };
 
 type WindowCanvas = Canvas<video::Window>;
@@ -37,32 +51,55 @@ type WindowCanvas = Canvas<video::Window>;
 #[allow(unused)]
 pub struct RendererManager {
     theme_manager: theme::RcThemeManager,
+    locale: locale::RcLocaleManager,
     video_subsystem: sdl2::VideoSubsystem,
     canvas: Rc<RefCell<WindowCanvas>>,
     widget_base_builder: widget_base::WidgetBaseBuilder,
+    tooltip: tooltip::Tooltip,
+    /// 最近一次应用到窗口的主题设置(标题, 宽, 高)
+    window_settings: (String, u32, u32),
+    present_mode: PresentMode,
+    max_fps: u32,
+    /// 进入全屏前的窗口位置与尺寸, 退出时保存
+    windowed_geometry: (i32, i32, u32, u32),
 }
 
 impl RendererManager {
-    pub fn new(theme_manager: theme::RcThemeManager, sdl_context: &mut sdl2::Sdl)
+    pub fn new(theme_manager: theme::RcThemeManager, text_renderer: text::RcTextRenderer,
+        locale: locale::RcLocaleManager, keymap: keymap::RcKeymap, window_state: &WindowState,
+        sdl_context: &mut sdl2::Sdl)
         -> Result<RendererManager, error::DynError>
     {
         let video_subsystem = sdl_context.video()?;
 
+        let window_settings = Self::theme_window_settings(&theme_manager.borrow(), &locale.borrow());
         let window = {
-            let theme_manager = theme_manager.borrow();
-            video_subsystem
-                .window(theme_manager.title_name(),
-                        theme_manager.initial_width(),
-                        theme_manager.initial_height())
+            let (title, width, height) = &window_settings;
+            let (width, height) = window_state.size.unwrap_or((*width, *height));
+            let mut builder = video_subsystem.window(title, width, height);
+            match window_state.position {
+                Some((x, y)) => builder.position(x, y),
+                None => builder.position_centered(),
+            };
+            // 高DPI显示器上绘制区域的像素数大于窗口尺寸
+            builder
+                .resizable()
+                .allow_highdpi()
                 .build()?
         };
+        let windowed_geometry = {
+            let (x, y) = window.position();
+            let (width, height) = window.size();
+            (x, y, width, height)
+        };
 
-        let canvas = window.into_canvas().build()?;
-        //let texture_creator = canvas.texture_creator();
-
+        let canvas = match window_state.present_mode {
+            PresentMode::VSync => window.into_canvas().present_vsync().build()?,
+            PresentMode::Immediate => window.into_canvas().build()?,
+        };
         let rc_canvas = Rc::new(RefCell::new(canvas));
         let widget_base_builder = widget_base::WidgetBaseBuilder::new(
-            theme_manager.clone(), rc_canvas.clone());
+            theme_manager.clone(), rc_canvas.clone(), text_renderer, locale.clone(), keymap);
         
         //let camera = {
         //    let ref_theme_manager = theme_manager.borrow();
@@ -76,19 +113,142 @@ impl RendererManager {
         //        texture_creator,
         //    )?
         //};
-        let renderer_manager = RendererManager {
+        let tooltip = tooltip::Tooltip::new(widget_base_builder.build());
+        let mut renderer_manager = RendererManager {
             theme_manager: theme_manager.clone(),
+            locale,
             video_subsystem,
             canvas: rc_canvas,
             widget_base_builder,
+            tooltip,
+            window_settings,
+            present_mode: window_state.present_mode,
+            max_fps: window_state.max_fps,
+            windowed_geometry,
         };
+        if window_state.fullscreen != FullscreenType::Off
+            && let Err(e) = renderer_manager.set_fullscreen(window_state.fullscreen) {
+            eprintln!("failed to enter fullscreen: {}", e);
+        }
 
         Ok(renderer_manager)
     }
+    /// 主题未指定标题时使用当前语言的标题
+    fn theme_window_settings(theme_manager: &theme::ThemeManager, locale: &locale::LocaleManager)
+        -> (String, u32, u32)
+    {
+        let title = theme_manager.title_name().unwrap_or_else(|| locale.text("app.title"));
+        (title.to_string(), theme_manager.initial_width(), theme_manager.initial_height())
+    }
+
+    /// 主题或语言切换后更新窗口标题与尺寸, 只在设置改变时生效, 不覆盖用户调整过的窗口尺寸
+    pub fn apply_window_theme(&mut self) -> error::Result<()> {
+        let settings = Self::theme_window_settings(&self.theme_manager.borrow(), &self.locale.borrow());
+        if settings == self.window_settings {
+            return Ok(());
+        }
+        let (title, width, height) = &settings;
+        {
+            let mut canvas = self.canvas.borrow_mut();
+            let window = canvas.window_mut();
+            if *title != self.window_settings.0 {
+                window.set_title(title)?;
+            }
+            if (*width, *height) != (self.window_settings.1, self.window_settings.2) {
+                // 全屏时只修改退出全屏后恢复的尺寸
+                if window.fullscreen_state() == FullscreenType::Off {
+                    window.set_size(*width, *height)?;
+                } else {
+                    self.windowed_geometry.2 = *width;
+                    self.windowed_geometry.3 = *height;
+                }
+            }
+        }
+        self.window_settings = settings;
+        Ok(())
+    }
+
+    /// 已经处于`fullscreen`模式时回到窗口模式
+    pub fn toggle_fullscreen(&mut self, fullscreen: FullscreenType) -> error::Result<()> {
+        let current = self.canvas.borrow().window().fullscreen_state();
+        if current == fullscreen {
+            self.set_fullscreen(FullscreenType::Off)
+        } else {
+            self.set_fullscreen(fullscreen)
+        }
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: FullscreenType) -> error::Result<()> {
+        let mut canvas = self.canvas.borrow_mut();
+        let window = canvas.window_mut();
+        let current = window.fullscreen_state();
+        if current == FullscreenType::Off {
+            let (x, y) = window.position();
+            let (width, height) = window.size();
+            self.windowed_geometry = (x, y, width, height);
+        }
+        // 在两种全屏模式之间切换时先回到窗口模式, 独占全屏才会重新设置显示模式
+        if current != FullscreenType::Off && fullscreen != FullscreenType::Off {
+            window.set_fullscreen(FullscreenType::Off)?;
+        }
+        window.set_fullscreen(fullscreen)?;
+        if fullscreen == FullscreenType::Off {
+            let (x, y, width, height) = self.windowed_geometry;
+            window.set_size(width, height)?;
+            window.set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+        }
+        Ok(())
+    }
+
+    /// 退出时保存的窗口状态
+    pub fn window_state(&self) -> WindowState {
+        let canvas = self.canvas.borrow();
+        let window = canvas.window();
+        let fullscreen = window.fullscreen_state();
+        let (x, y, width, height) = match fullscreen {
+            FullscreenType::Off => {
+                let (x, y) = window.position();
+                let (width, height) = window.size();
+                (x, y, width, height)
+            },
+            _ => self.windowed_geometry,
+        };
+        WindowState {
+            position: Some((x, y)),
+            size: Some((width, height)),
+            fullscreen,
+            present_mode: self.present_mode,
+            max_fps: self.max_fps,
+        }
+    }
+
+    /// 立即呈现且限制了帧率时, 每帧的最短时间
+    pub fn frame_interval(&self) -> Option<Duration> {
+        match (self.present_mode, self.max_fps) {
+            (PresentMode::Immediate, fps) if fps > 0 => Some(Duration::from_secs(1) / fps),
+            _ => None,
+        }
+    }
+
+    /// 绘制区域与窗口尺寸之比, 鼠标事件的坐标需要乘以该比例
+    pub fn pixel_scale(&self) -> (f64, f64) {
+        let canvas = self.canvas.borrow();
+        let (width, height) = canvas.window().size();
+        let (output_width, output_height) = canvas
+            .output_size()
+            .unwrap_or((width, height));
+        (output_width as f64 / width.max(1) as f64, output_height as f64 / height.max(1) as f64)
+    }
+
     pub fn get_shared_canvas(&self) -> Rc<RefCell<WindowCanvas>> {
         self.canvas.clone()
     }
     
+    /// 所有输入事件都应经过这里, 以便提示判断光标是否静止
+    pub fn track_pointer(&mut self, event: &WidgetEvent) {
+        self.tooltip.track(event);
+    }
+
     pub fn views_iterator(&mut self, layout: &mut layout::Layout) -> Result<(), error::DynError> {
         let bg_color = {
             let theme_manager = self.theme_manager.borrow();
@@ -96,14 +256,19 @@ impl RendererManager {
         };
 
         self.clear_canvas(bg_color);
-        layout.rendering(self.canvas.clone(), self.get_window_rect())?;
+        let window_rect = self.get_window_rect();
+        layout.rendering(self.canvas.clone(), window_rect)?;
+        self.tooltip.rendering(layout, window_rect)?;
         self.canvas.borrow_mut().present();
         Ok(())
     }
 
+    /// 绘制区域的像素尺寸, 高DPI下大于窗口尺寸
     fn get_window_rect(&self) -> Rect {
         let canvas = self.canvas.borrow();
-        let (width, height) = canvas.window().size();
+        let (width, height) = canvas
+            .output_size()
+            .unwrap_or_else(|_| canvas.window().size());
         Rect::new(0, 0, width, height)
     }
 
@@ -0,0 +1,319 @@
+use sdl2::{
+    ttf,
+    pixels::{
+        Color,
+        PixelFormatEnum,
+    },
+    rect::Rect,
+    render::{
+        self,
+        BlendMode,
+        Texture,
+        TextureCreator,
+    },
+    video,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::OnceLock,
+};
+use crate::utils::error;
+
+pub type RcTextRenderer = std::rc::Rc<std::cell::RefCell<TextRenderer>>;
+
+pub static FONT_SIZE: u16 = 14;
+/// 主题未指定字体时依次尝试的系统字体, 只包含当前平台的路径
+#[cfg(target_os = "windows")]
+static FONT_CANDIDATES: &[&str] = &["C:\\Windows\\Fonts\\arial.ttf"];
+#[cfg(target_os = "macos")]
+static FONT_CANDIDATES: &[&str] = &[
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+static FONT_CANDIDATES: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+];
+/// 主题未指定后备字体时, 用第一个能加载的作为CJK字符的后备
+#[cfg(target_os = "windows")]
+static CJK_FONT_CANDIDATES: &[&str] = &["C:\\Windows\\Fonts\\msyh.ttc"];
+#[cfg(target_os = "macos")]
+static CJK_FONT_CANDIDATES: &[&str] = &["/System/Library/Fonts/PingFang.ttc"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+static CJK_FONT_CANDIDATES: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+];
+/// 字体借用ttf上下文, 上下文只初始化一次, 在程序运行期间一直存在
+static TTF_CONTEXT: OnceLock<ttf::Sdl2TtfContext> = OnceLock::new();
+static PAGE_SIZE: u32 = 1024;
+/// 图集页数达到上限时清空重建, 避免长时间运行后显存无限增长
+static MAX_PAGES: usize = 4;
+
+/// 字符所用的字体及其宽度, 测量与绘制都以此为准, 保证两者一致
+#[derive(Clone, Copy)]
+struct Metrics {
+    font: usize,
+    advance: u32,
+}
+
+/// 字形在图集中的位置, 空白字符没有位置
+#[derive(Clone, Copy)]
+struct Glyph {
+    page: usize,
+    rect: Option<Rect>,
+}
+
+struct Page {
+    texture: Texture,
+    size: u32,
+    /// 当前行的起点与行高
+    cursor: (u32, u32),
+    row_height: u32,
+}
+
+/// 把光栅化后的白色字形按行排在若干张纹理上, 绘制时用color mod着色
+/// 字形通过`Texture::update`上传, 不会改变canvas的渲染目标, 因此可以在`with_texture_canvas`中使用
+#[derive(Default)]
+struct GlyphAtlas {
+    pages: Vec<Page>,
+    glyphs: HashMap<char, Glyph>,
+}
+
+impl GlyphAtlas {
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        // unsafe_textures下需手动销毁, 此时canvas仍然存活
+        self.pages
+            .drain(..)
+            .for_each(|page| unsafe { page.texture.destroy() });
+    }
+
+    /// 在现有页中找位置, 放不下时新建一页
+    fn allocate(&mut self, texture_creator: &TextureCreator<video::WindowContext>, width: u32, height: u32)
+        -> error::Result<(usize, Rect)>
+    {
+        if let Some(page) = self.pages.last_mut() {
+            if page.cursor.0 + width > page.size {
+                page.cursor = (0, page.cursor.1 + page.row_height);
+                page.row_height = 0;
+            }
+            if page.cursor.1 + height <= page.size {
+                let rect = Rect::new(page.cursor.0 as i32, page.cursor.1 as i32, width, height);
+                page.cursor.0 += width;
+                page.row_height = page.row_height.max(height);
+                return Ok((self.pages.len() - 1, rect));
+            }
+        }
+        if self.pages.len() >= MAX_PAGES {
+            self.clear();
+        }
+        let size = PAGE_SIZE.max(width).max(height);
+        let mut texture = texture_creator.create_texture_streaming(PixelFormatEnum::ARGB8888, size, size)?;
+        texture.set_blend_mode(BlendMode::Blend);
+        self.pages.push(Page { texture, size, cursor: (width, 0), row_height: height });
+        Ok((self.pages.len() - 1, Rect::new(0, 0, width, height)))
+    }
+}
+
+/// 基于TTF的文字绘制, 找不到字体时不绘制任何文字
+/// 第一个字体为主字体, 其余为后备字体, 每个字符使用第一个包含该字形的字体
+pub struct TextRenderer {
+    context: &'static ttf::Sdl2TtfContext,
+    fonts: Vec<ttf::Font<'static, 'static>>,
+    metrics: RefCell<HashMap<char, Metrics>>,
+    atlas: RefCell<GlyphAtlas>,
+}
+
+impl TextRenderer {
+    pub fn new() -> error::Result<Self> {
+        let context = match TTF_CONTEXT.get() {
+            Some(context) => context,
+            None => {
+                let context = ttf::init()?;
+                TTF_CONTEXT.get_or_init(|| context)
+            },
+        };
+        let mut renderer = TextRenderer {
+            context,
+            fonts: Vec::new(),
+            metrics: RefCell::new(HashMap::new()),
+            atlas: RefCell::new(GlyphAtlas::default()),
+        };
+        if let Err(e) = renderer.set_font(None, &[], FONT_SIZE) {
+            eprintln!("text: {}", e);
+        }
+        Ok(renderer)
+    }
+
+    /// 更换字体, `path`为None时依次尝试默认字体, `fallbacks`为空时尝试常见的CJK字体
+    /// 任一字体加载失败时保留原来的字体
+    pub fn set_font(&mut self, path: Option<&str>, fallbacks: &[String], size: u16) -> error::Result<()> {
+        let load = |path: &str| self.context
+            .load_font(path, size)
+            .map_err(|e| error::DynError::from(format!("failed to load font `{}`: {}", path, e)));
+        let mut fonts = vec![match path {
+            Some(path) => load(path)?,
+            None => FONT_CANDIDATES
+                .iter()
+                .find_map(|path| load(path).ok())
+                .ok_or("no default font available")?,
+        }];
+        if fallbacks.is_empty() {
+            fonts.extend(CJK_FONT_CANDIDATES.iter().find_map(|path| load(path).ok()));
+        } else {
+            for path in fallbacks.iter() {
+                fonts.push(load(path)?);
+            }
+        }
+        self.fonts = fonts;
+        self.metrics.borrow_mut().clear();
+        self.atlas.borrow_mut().clear();
+        Ok(())
+    }
+
+    pub fn line_height(&self) -> u32 {
+        self.fonts
+            .iter()
+            .map(|font| font.height().max(0) as u32)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn metrics_of(&self, ch: char) -> Metrics {
+        if let Some(metrics) = self.metrics.borrow().get(&ch) {
+            return *metrics;
+        }
+        // TTF_GlyphIsProvided只接受BMP内的字符, 之外的字符交给主字体
+        let font = match u16::try_from(ch as u32) {
+            Ok(_) => self.fonts
+                .iter()
+                .position(|font| font.find_glyph(ch).is_some())
+                .unwrap_or(0),
+            Err(_) => 0,
+        };
+        let advance = match self.fonts.get(font) {
+            Some(font) if !ch.is_control() => font.size_of_char(ch).map_or(0, |(width, _)| width),
+            _ => 0,
+        };
+        let metrics = Metrics { font, advance };
+        self.metrics.borrow_mut().insert(ch, metrics);
+        metrics
+    }
+
+    pub fn size_of(&self, text: &str) -> (u32, u32) {
+        if self.fonts.is_empty() {
+            return (0, 0);
+        }
+        let width = text.chars().map(|ch| self.metrics_of(ch).advance).sum();
+        (width, self.line_height())
+    }
+
+    /// 把文字按`max_width`折成多行, 保留原有的换行
+    /// 优先在空白处断行, CJK字符前后也可以断行, 单个词超过宽度时在字符间断开
+    pub fn wrap<'a>(&self, text: &'a str, max_width: u32) -> Vec<&'a str> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut start = 0;
+            let mut width = 0;
+            // 最近一个可以断行的位置, 断开后该位置的字符成为下一行的开头
+            let mut breakable = None;
+            let mut prev: Option<char> = None;
+            for (i, ch) in paragraph.char_indices() {
+                if i > start && let Some(prev) = prev
+                    && !ch.is_whitespace()
+                    && (prev.is_whitespace() || is_wide(prev) || is_wide(ch))
+                {
+                    breakable = Some(i);
+                }
+                let advance = self.metrics_of(ch).advance;
+                // 行尾的空白不计入宽度
+                if width + advance > max_width && i > start && !ch.is_whitespace() {
+                    let end = breakable.unwrap_or(i);
+                    lines.push(paragraph[start..end].trim_end());
+                    start = end;
+                    width = self.size_of(&paragraph[start..i]).0;
+                    breakable = None;
+                }
+                width += advance;
+                prev = Some(ch);
+            }
+            lines.push(paragraph[start..].trim_end());
+        }
+        lines
+    }
+
+    /// 字形不在图集中时光栅化并上传
+    fn glyph_of(&self, texture_creator: &TextureCreator<video::WindowContext>, ch: char, metrics: Metrics)
+        -> error::Result<Glyph>
+    {
+        let mut atlas = self.atlas.borrow_mut();
+        if let Some(glyph) = atlas.glyphs.get(&ch) {
+            return Ok(*glyph);
+        }
+        // 空白等零宽度的字符渲染会失败, 只占位不绘制
+        let surface = match ch.is_whitespace() || metrics.advance == 0 {
+            true => None,
+            false => self.fonts[metrics.font]
+                .render_char(ch)
+                .blended(Color::WHITE)
+                .ok(),
+        };
+        let glyph = match surface {
+            Some(surface) => {
+                let surface = surface.convert_format(PixelFormatEnum::ARGB8888)?;
+                let (page, rect) = atlas.allocate(texture_creator, surface.width(), surface.height())?;
+                let pitch = surface.pitch() as usize;
+                let texture = &mut atlas.pages[page].texture;
+                surface.with_lock(|pixels| texture.update(rect, pixels, pitch))?;
+                Glyph { page, rect: Some(rect) }
+            },
+            None => Glyph { page: 0, rect: None },
+        };
+        atlas.glyphs.insert(ch, glyph);
+        Ok(glyph)
+    }
+
+    /// 在canvas当前的渲染目标上绘制单行文字, 返回文字占用的矩形
+    pub fn draw_text(&self, canvas: &mut render::WindowCanvas,
+        texture_creator: &TextureCreator<video::WindowContext>,
+        text: &str, (x, y): (i32, i32), color: Color) -> error::Result<Rect>
+    {
+        if self.fonts.is_empty() {
+            return Ok(Rect::new(x, y, 1, 1));
+        }
+        let line_height = self.line_height().max(1);
+        let mut pen = x;
+        for ch in text.chars() {
+            let metrics = self.metrics_of(ch);
+            let glyph = self.glyph_of(texture_creator, ch, metrics)?;
+            if let Some(rect) = glyph.rect {
+                let mut atlas = self.atlas.borrow_mut();
+                let texture = &mut atlas.pages[glyph.page].texture;
+                texture.set_color_mod(color.r, color.g, color.b);
+                texture.set_alpha_mod(color.a);
+                // 后备字体的行高可能不同, 纵向居中对齐
+                let offset = (line_height as i32 - rect.height() as i32) / 2;
+                canvas.copy(texture, rect, Rect::new(pen, y + offset, rect.width(), rect.height()))?;
+            }
+            pen += metrics.advance as i32;
+        }
+        Ok(Rect::new(x, y, ((pen - x) as u32).max(1), line_height))
+    }
+}
+
+/// 东亚宽字符, 字符之间可以断行
+fn is_wide(ch: char) -> bool {
+    matches!(ch as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x20000..=0x3FFFD)
+}
@@ -4,67 +4,741 @@ use sdl2::{
         Color
     }
 };
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
 
 pub type RcThemeManager = std::rc::Rc<std::cell::RefCell<ThemeManager>>;
 
-static TITLE_NAME: &'static str = "game of life";
 static WINDOW_WIDTH: u32 = 1280;
 static WINDOW_HEIGHT: u32 = 720;
+static FONT_SIZE: u16 = 14;
 static WINDOW_BG_COLOR: Color = Color::WHITE;
 static CAMERA_BG_COLOR: Color = Color::GRAY;
 static GRID_LINE_COLOR: Color = Color::BLACK;
+static GRID_MAJOR_COLOR: Color = Color::RGB(40, 40, 120);
+/// 每隔多少条网格线画一条主线
+static GRID_MAJOR_EVERY: i32 = 10;
+static CELL_ALIVE_COLOR: Color = Color::WHITE;
+static CELL_DEAD_COLOR: Color = Color::GRAY;
+static PREVIEW_COLOR: Color = Color::RGBA(255, 200, 0, 160);
+static SELECTION_COLOR: Color = Color::RGB(0, 160, 255);
+/// 标注未指定颜色时使用
+static ANNOTATION_COLOR: Color = Color::RGB(200, 60, 0);
+static MINIMAP_BG_COLOR: Color = Color::RGB(64, 64, 64);
+static VIEWPORT_INDICATOR_COLOR: Color = Color::RED;
+static GRAPH_BG_COLOR: Color = Color::RGB(32, 32, 32);
+static GRAPH_AXIS_COLOR: Color = Color::RGB(128, 128, 128);
+static POPULATION_COLOR: Color = Color::RGB(80, 220, 80);
+static BIRTHS_COLOR: Color = Color::RGB(80, 160, 255);
+static DEATHS_COLOR: Color = Color::RGB(255, 96, 96);
+static TEXT_COLOR: Color = Color::WHITE;
+static PANEL_BG_COLOR: Color = Color::RGB(48, 48, 48);
+static PANEL_HIGHLIGHT_COLOR: Color = Color::RGB(72, 88, 112);
+static CONSOLE_BG_COLOR: Color = Color::RGBA(0, 0, 0, 200);
+static CONSOLE_ECHO_COLOR: Color = Color::RGB(160, 160, 160);
+static CONSOLE_ERROR_COLOR: Color = Color::RGB(255, 96, 96);
+static BORN_COLOR: Color = Color::RGB(80, 220, 80);
+static DYING_COLOR: Color = Color::RGB(220, 60, 60);
+static TOOLTIP_BG_COLOR: Color = Color::RGBA(24, 24, 24, 230);
+static TOOLTIP_TEXT_COLOR: Color = Color::WHITE;
+/// 光标静止多久后显示提示, 毫秒
+static TOOLTIP_DELAY: u32 = 600;
+/// 相机跳转时平移与缩放的过渡时间, 毫秒, 0表示立即跳转
+static CAMERA_TRANSITION: u32 = 300;
+static AGE_RAMP: [Color; 5] = [
+    Color::WHITE,
+    Color::RGB(255, 230, 80),
+    Color::RGB(255, 140, 0),
+    Color::RGB(200, 40, 40),
+    Color::RGB(110, 0, 130),
+];
+static TRAIL_RAMP: [Color; 2] = [Color::RGB(90, 110, 200), CELL_DEAD_COLOR];
 
-#[derive(Debug)]
-pub struct ThemeManager {
-    title_name: String,
+static DEFAULT_THEME: &str = "light";
+pub static BUILTIN_THEMES: [&str; 3] = ["light", "dark", "high-contrast"];
+/// `base`链的最大长度, 防止主题互相继承
+static MAX_BASE_DEPTH: usize = 8;
+
+/// 由若干颜色均匀分布组成的色阶, 相邻颜色之间线性插值
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRamp {
+    stops: Vec<Color>,
+}
+
+impl ColorRamp {
+    fn new(stops: &[Color]) -> Self {
+        ColorRamp { stops: stops.to_vec() }
+    }
+
+    /// `t`在[0, 1]之间, 超出范围时取两端的颜色
+    pub fn sample(&self, t: f64) -> Color {
+        let last = self.stops.len() - 1;
+        let position = t.clamp(0., 1.) * last as f64;
+        let idx = (position.floor() as usize).min(last.saturating_sub(1));
+        let (from, to) = (self.stops[idx], self.stops[(idx + 1).min(last)]);
+        let fraction = position - idx as f64;
+        let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * fraction).round() as u8;
+        Color::RGBA(lerp(from.r, to.r), lerp(from.g, to.g), lerp(from.b, to.b), lerp(from.a, to.a))
+    }
+}
+
+/// 一套主题的全部取值
+#[derive(Debug, Clone)]
+struct Theme {
+    /// 未指定时使用当前语言的标题
+    title_name: Option<String>,
+    /// 窗口状态文件中保存的尺寸优先于这两项
     window_init_width: u32,
     window_init_height: u32,
+    font_path: Option<String>,
+    font_size: u16,
+    /// 主字体缺少字形时依次尝试, 为空时使用常见的CJK字体
+    fallback_fonts: Vec<String>,
+    tooltip_delay: u32,
+    camera_transition: u32,
+    /// 0表示不区分主线
+    grid_major_every: i32,
     window_bg_color: Color,
     camera_bg_color: Color,
     default_widget_color: Color,
     grid_line_color: Color,
+    grid_major_color: Color,
+    cell_alive_color: Color,
+    cell_dead_color: Color,
+    preview_color: Color,
+    selection_color: Color,
+    annotation_color: Color,
+    minimap_bg_color: Color,
+    viewport_indicator_color: Color,
+    graph_bg_color: Color,
+    graph_axis_color: Color,
+    population_color: Color,
+    births_color: Color,
+    deaths_color: Color,
+    text_color: Color,
+    panel_bg_color: Color,
+    panel_highlight_color: Color,
+    console_bg_color: Color,
+    console_echo_color: Color,
+    console_error_color: Color,
+    born_color: Color,
+    dying_color: Color,
+    tooltip_bg_color: Color,
+    tooltip_text_color: Color,
+    age_ramp: ColorRamp,
+    /// 应以死细胞的颜色结尾, 使尾迹逐渐消失
+    trail_ramp: ColorRamp,
 }
 
-impl ThemeManager {
-    #[allow(unused)]
-    pub fn default_init() -> Result<Self, error::DynError> {
-        let theme_manager = ThemeManager {
-            title_name: TITLE_NAME.to_string(),
+impl Theme {
+    fn light() -> Self {
+        Theme {
+            title_name: None,
             window_init_width: WINDOW_WIDTH,
             window_init_height: WINDOW_HEIGHT,
+            font_path: None,
+            font_size: FONT_SIZE,
+            fallback_fonts: Vec::new(),
+            tooltip_delay: TOOLTIP_DELAY,
+            camera_transition: CAMERA_TRANSITION,
+            grid_major_every: GRID_MAJOR_EVERY,
             window_bg_color: WINDOW_BG_COLOR,
             camera_bg_color: CAMERA_BG_COLOR,
             default_widget_color: WINDOW_BG_COLOR,
             grid_line_color: GRID_LINE_COLOR,
+            grid_major_color: GRID_MAJOR_COLOR,
+            cell_alive_color: CELL_ALIVE_COLOR,
+            cell_dead_color: CELL_DEAD_COLOR,
+            preview_color: PREVIEW_COLOR,
+            selection_color: SELECTION_COLOR,
+            annotation_color: ANNOTATION_COLOR,
+            minimap_bg_color: MINIMAP_BG_COLOR,
+            viewport_indicator_color: VIEWPORT_INDICATOR_COLOR,
+            graph_bg_color: GRAPH_BG_COLOR,
+            graph_axis_color: GRAPH_AXIS_COLOR,
+            population_color: POPULATION_COLOR,
+            births_color: BIRTHS_COLOR,
+            deaths_color: DEATHS_COLOR,
+            text_color: TEXT_COLOR,
+            panel_bg_color: PANEL_BG_COLOR,
+            panel_highlight_color: PANEL_HIGHLIGHT_COLOR,
+            console_bg_color: CONSOLE_BG_COLOR,
+            console_echo_color: CONSOLE_ECHO_COLOR,
+            console_error_color: CONSOLE_ERROR_COLOR,
+            born_color: BORN_COLOR,
+            dying_color: DYING_COLOR,
+            tooltip_bg_color: TOOLTIP_BG_COLOR,
+            tooltip_text_color: TOOLTIP_TEXT_COLOR,
+            age_ramp: ColorRamp::new(&AGE_RAMP),
+            trail_ramp: ColorRamp::new(&TRAIL_RAMP),
+        }
+    }
+
+    fn dark() -> Self {
+        Theme {
+            window_bg_color: Color::RGB(24, 24, 24),
+            camera_bg_color: Color::RGB(12, 12, 12),
+            default_widget_color: Color::RGB(24, 24, 24),
+            grid_line_color: Color::RGB(48, 48, 48),
+            grid_major_color: Color::RGB(88, 88, 88),
+            cell_alive_color: Color::RGB(230, 230, 230),
+            cell_dead_color: Color::RGB(20, 20, 20),
+            annotation_color: Color::RGB(255, 170, 60),
+            minimap_bg_color: Color::RGB(36, 36, 36),
+            graph_bg_color: Color::RGB(28, 28, 28),
+            panel_bg_color: Color::RGB(32, 32, 32),
+            age_ramp: ColorRamp::new(&[Color::RGB(230, 230, 230), Color::RGB(80, 200, 255),
+                Color::RGB(40, 80, 220), Color::RGB(90, 30, 160)]),
+            trail_ramp: ColorRamp::new(&[Color::RGB(60, 80, 160), Color::RGB(20, 20, 20)]),
+            ..Theme::light()
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Theme {
+            window_bg_color: Color::BLACK,
+            camera_bg_color: Color::BLACK,
+            default_widget_color: Color::BLACK,
+            grid_line_color: Color::RGB(96, 96, 96),
+            grid_major_color: Color::RGB(200, 200, 200),
+            cell_alive_color: Color::WHITE,
+            cell_dead_color: Color::BLACK,
+            preview_color: Color::RGBA(0, 255, 255, 200),
+            selection_color: Color::RGB(0, 255, 0),
+            annotation_color: Color::MAGENTA,
+            minimap_bg_color: Color::BLACK,
+            viewport_indicator_color: Color::YELLOW,
+            graph_bg_color: Color::BLACK,
+            graph_axis_color: Color::WHITE,
+            population_color: Color::RGB(0, 255, 0),
+            births_color: Color::RGB(0, 200, 255),
+            deaths_color: Color::RGB(255, 0, 255),
+            panel_bg_color: Color::BLACK,
+            panel_highlight_color: Color::RGB(0, 0, 160),
+            console_bg_color: Color::RGBA(0, 0, 0, 240),
+            console_echo_color: Color::RGB(200, 200, 200),
+            console_error_color: Color::RGB(255, 64, 64),
+            born_color: Color::RGB(0, 255, 0),
+            dying_color: Color::RGB(255, 0, 0),
+            tooltip_bg_color: Color::BLACK,
+            tooltip_text_color: Color::YELLOW,
+            age_ramp: ColorRamp::new(&[Color::WHITE, Color::YELLOW, Color::RGB(255, 0, 255)]),
+            trail_ramp: ColorRamp::new(&[Color::RGB(0, 128, 255), Color::BLACK]),
+            ..Theme::light()
+        }
+    }
+
+    fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Theme::light()),
+            "dark" => Some(Theme::dark()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// 配置文件中颜色名称(去掉`_color`后缀)对应的字段
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        let color = match name {
+            "window_bg" => &mut self.window_bg_color,
+            "camera_bg" => &mut self.camera_bg_color,
+            "default_widget" => &mut self.default_widget_color,
+            "grid_line" => &mut self.grid_line_color,
+            "grid_major" => &mut self.grid_major_color,
+            "cell_alive" => &mut self.cell_alive_color,
+            "cell_dead" => &mut self.cell_dead_color,
+            "preview" => &mut self.preview_color,
+            "selection" => &mut self.selection_color,
+            "annotation" => &mut self.annotation_color,
+            "minimap_bg" => &mut self.minimap_bg_color,
+            "viewport_indicator" => &mut self.viewport_indicator_color,
+            "graph_bg" => &mut self.graph_bg_color,
+            "graph_axis" => &mut self.graph_axis_color,
+            "population" => &mut self.population_color,
+            "births" => &mut self.births_color,
+            "deaths" => &mut self.deaths_color,
+            "text" => &mut self.text_color,
+            "panel_bg" => &mut self.panel_bg_color,
+            "panel_highlight" => &mut self.panel_highlight_color,
+            "console_bg" => &mut self.console_bg_color,
+            "console_echo" => &mut self.console_echo_color,
+            "console_error" => &mut self.console_error_color,
+            "born" => &mut self.born_color,
+            "dying" => &mut self.dying_color,
+            "tooltip_bg" => &mut self.tooltip_bg_color,
+            "tooltip_text" => &mut self.tooltip_text_color,
+            _ => return None,
+        };
+        Some(color)
+    }
+
+    /// 用`[themes.<name>]`表中的值覆盖, `prefix`用于错误信息中定位条目
+    fn apply(&mut self, table: &toml::Table, prefix: &str) -> error::Result<()> {
+        for (key, value) in table.iter() {
+            let entry = format!("{}.{}", prefix, key);
+            match key.as_str() {
+                "base" => {},
+                "title" => self.title_name = Some(expect_str(value, &entry)?.to_string()),
+                "window_width" => self.window_init_width = expect_int(value, &entry, 1, 1 << 14)?,
+                "window_height" => self.window_init_height = expect_int(value, &entry, 1, 1 << 14)?,
+                "font" => self.font_path = Some(expect_str(value, &entry)?.to_string()),
+                "font_size" => self.font_size = expect_int(value, &entry, 1, 256)?,
+                "fallback_fonts" => {
+                    let toml::Value::Array(paths) = value else {
+                        return Err(format!("{}: expected an array of paths", entry).into());
+                    };
+                    self.fallback_fonts = paths
+                        .iter()
+                        .map(|path| expect_str(path, &entry).map(str::to_string))
+                        .collect::<error::Result<_>>()?;
+                },
+                "tooltip_delay" => self.tooltip_delay = expect_int(value, &entry, 0, 10_000)?,
+                "camera_transition" => self.camera_transition = expect_int(value, &entry, 0, 5_000)?,
+                "grid_major_every" => self.grid_major_every = expect_int(value, &entry, 0, 1_000)?,
+                "colors" => {
+                    let toml::Value::Table(colors) = value else {
+                        return Err(format!("{}: expected a table", entry).into());
+                    };
+                    for (name, value) in colors.iter() {
+                        let entry = format!("{}.{}", entry, name);
+                        let color = self.color_mut(name)
+                            .ok_or_else(|| format!("{}: unknown colour", entry))?;
+                        *color = parse_color(value).map_err(|e| format!("{}: {}", entry, e))?;
+                    }
+                },
+                "ramps" => {
+                    let toml::Value::Table(ramps) = value else {
+                        return Err(format!("{}: expected a table", entry).into());
+                    };
+                    for (name, value) in ramps.iter() {
+                        let entry = format!("{}.{}", entry, name);
+                        let ramp = match name.as_str() {
+                            "age" => &mut self.age_ramp,
+                            "trail" => &mut self.trail_ramp,
+                            _ => return Err(format!("{}: unknown colour ramp", entry).into()),
+                        };
+                        *ramp = parse_ramp(value).map_err(|e| format!("{}: {}", entry, e))?;
+                    }
+                },
+                _ => return Err(format!("{}: unknown key", entry).into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn expect_str<'a>(value: &'a toml::Value, entry: &str) -> error::Result<&'a str> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("{}: expected a string", entry).into())
+}
+
+fn expect_int<T: TryFrom<i64>>(value: &toml::Value, entry: &str, min: i64, max: i64)
+    -> error::Result<T>
+{
+    value
+        .as_integer()
+        .filter(|n| (min..=max).contains(n))
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| format!("{}: expected an integer within [{}, {}]", entry, min, max).into())
+}
+
+/// 接受 "#rrggbb", "#rrggbbaa" 以及 [r, g, b] 或 [r, g, b, a]
+fn parse_color(value: &toml::Value) -> error::Result<Color> {
+    let components: Vec<u8> = match value {
+        toml::Value::String(text) => {
+            let hex = text
+                .strip_prefix('#')
+                .filter(|hex| (hex.len() == 6 || hex.len() == 8) && hex.is_ascii())
+                .ok_or_else(|| format!("invalid colour `{}`, expected #rrggbb or #rrggbbaa", text))?;
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("invalid colour `{}`", text))?
+        },
+        toml::Value::Array(values) if values.len() == 3 || values.len() == 4 => values
+            .iter()
+            .map(|value| value.as_integer().and_then(|n| u8::try_from(n).ok()))
+            .collect::<Option<_>>()
+            .ok_or("colour components should be integers within [0, 255]")?,
+        _ => return Err(String::from("expected a colour like \"#rrggbb\" or [r, g, b]").into()),
+    };
+    let alpha = components.get(3).copied().unwrap_or(255);
+    Ok(Color::RGBA(components[0], components[1], components[2], alpha))
+}
+
+/// 至少两个颜色组成的数组
+fn parse_ramp(value: &toml::Value) -> error::Result<ColorRamp> {
+    let stops = match value {
+        toml::Value::Array(values) if values.len() >= 2 => values
+            .iter()
+            .map(parse_color)
+            .collect::<error::Result<Vec<_>>>()?,
+        _ => return Err(String::from("expected an array of at least two colours").into()),
+    };
+    Ok(ColorRamp { stops })
+}
+
+/// 主题文件的格式:
+/// ```toml
+/// active = "dark"
+///
+/// [themes.dark]           # 与内置主题同名时以内置主题为基础
+/// font = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"
+/// fallback_fonts = ["/usr/share/fonts/truetype/wqy/wqy-microhei.ttc"]
+/// window_width = 1280    # window.toml中保存了尺寸时以其为准
+/// window_height = 720
+/// tooltip_delay = 400     # 毫秒
+/// camera_transition = 0   # 毫秒, 0表示相机立即跳转
+/// grid_major_every = 8    # 0表示没有主线
+/// colors = { cell_alive = "#e0e0e0" }
+///
+/// [themes.solarized]
+/// base = "dark"           # 其他主题默认以light为基础
+/// colors = { camera_bg = [0, 43, 54] }
+/// ramps = { age = ["#ffffff", "#ff8000", "#800080"] }
+/// ```
+struct ThemeFile {
+    table: toml::Table,
+    modified: Option<SystemTime>,
+}
+
+impl ThemeFile {
+    fn read(path: &Path) -> error::Result<Self> {
+        let modified = fs::metadata(path)?.modified().ok();
+        let text = fs::read_to_string(path)?;
+        let table = text
+            .parse()
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(ThemeFile { table, modified })
+    }
+
+    fn active(&self) -> error::Result<Option<&str>> {
+        self.table
+            .get("active")
+            .map(|value| expect_str(value, "active"))
+            .transpose()
+    }
+
+    fn themes(&self) -> error::Result<Option<&toml::Table>> {
+        match self.table.get("themes") {
+            None => Ok(None),
+            Some(toml::Value::Table(themes)) => Ok(Some(themes)),
+            Some(_) => Err(String::from("themes: expected a table").into()),
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.themes()
+            .ok()
+            .flatten()
+            .map(|themes| themes.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn resolve(&self, name: &str, depth: usize) -> error::Result<Theme> {
+        if depth > MAX_BASE_DEPTH {
+            return Err(format!("themes.{}: `base` chain is too long or cyclic", name).into());
+        }
+        let Some(value) = self.themes()?.and_then(|themes| themes.get(name)) else {
+            return Theme::builtin(name).ok_or_else(|| format!("unknown theme `{}`", name).into());
+        };
+        let prefix = format!("themes.{}", name);
+        let toml::Value::Table(table) = value else {
+            return Err(format!("{}: expected a table", prefix).into());
+        };
+        let mut theme = match table.get("base") {
+            Some(base) => {
+                let base = expect_str(base, &format!("{}.base", prefix))?;
+                if base == name {
+                    Theme::builtin(name)
+                        .ok_or_else(|| format!("{}: a theme cannot be based on itself", prefix))?
+                } else {
+                    self.resolve(base, depth + 1)?
+                }
+            },
+            None => Theme::builtin(name).unwrap_or_else(Theme::light),
+        };
+        theme.apply(table, &prefix)?;
+        Ok(theme)
+    }
+}
+
+/// 当前使用的主题, 可以来自主题文件并在文件修改后重新读取
+#[derive(Debug)]
+pub struct ThemeManager {
+    name: String,
+    theme: Theme,
+    source: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    /// 文件中上一次读到的`active`, 只有它改变时才切换主题
+    file_active: Option<String>,
+    revision: u64,
+}
+
+impl ThemeManager {
+    #[allow(unused)]
+    pub fn default_init() -> Result<Self, error::DynError> {
+        let theme_manager = ThemeManager {
+            name: DEFAULT_THEME.to_string(),
+            theme: Theme::light(),
+            source: None,
+            modified: None,
+            file_active: None,
+            revision: 0,
         };
         Ok(theme_manager)
     }
 
-    pub fn title_name(&self) -> &str {
-        self.title_name.as_str()
+    /// 使用文件中`active`指定的主题, 未指定时使用light
+    pub fn from_file(path: PathBuf) -> error::Result<Self> {
+        let file = ThemeFile::read(&path)?;
+        let file_active = file.active()?.map(str::to_string);
+        let name = file_active.clone().unwrap_or_else(|| DEFAULT_THEME.to_string());
+        let theme = file
+            .resolve(&name, 0)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(ThemeManager {
+            name,
+            theme,
+            source: Some(path),
+            modified: file.modified,
+            file_active,
+            revision: 0,
+        })
+    }
+
+    /// 每次主题内容变化时递增, 用于判断是否需要重绘
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// 内置主题以及主题文件中定义的主题
+    pub fn available_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = BUILTIN_THEMES
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let file_names = self.source
+            .as_deref()
+            .and_then(|path| ThemeFile::read(path).ok())
+            .map(|file| file.names())
+            .unwrap_or_default();
+        for name in file_names {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// 切换主题, 失败时保持当前主题不变
+    pub fn select(&mut self, name: &str) -> error::Result<()> {
+        let theme = match self.source.as_deref() {
+            Some(path) => ThemeFile::read(path)?
+                .resolve(name, 0)
+                .map_err(|e| format!("{}: {}", path.display(), e))?,
+            None => Theme::builtin(name).ok_or_else(|| format!("unknown theme `{}`", name))?,
+        };
+        self.name = name.to_string();
+        self.theme = theme;
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// 主题文件的修改时间变化时重新读取, 没有变化时返回None
+    /// 出错时保持当前主题, 修复文件后会再次尝试
+    pub fn reload_if_changed(&mut self) -> Option<error::Result<()>> {
+        let path = self.source.clone()?;
+        let modified = fs::metadata(&path).ok()?.modified().ok();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let reload = || -> error::Result<(Option<String>, String, Theme)> {
+            let file = ThemeFile::read(&path)?;
+            let file_active = file.active()?.map(str::to_string);
+            let name = if file_active != self.file_active {
+                file_active.clone().unwrap_or_else(|| DEFAULT_THEME.to_string())
+            } else {
+                self.name.clone()
+            };
+            let theme = file.resolve(&name, 0)?;
+            Ok((file_active, name, theme))
+        };
+        let result = reload().map(|(file_active, name, theme)| {
+            self.file_active = file_active;
+            self.name = name;
+            self.theme = theme;
+            self.revision += 1;
+        });
+        Some(result.map_err(|e| format!("{}: {}", path.display(), e).into()))
+    }
+
+    pub fn title_name(&self) -> Option<&str> {
+        self.theme.title_name.as_deref()
     }
 
     pub fn initial_width(&self) -> u32 {
-        self.window_init_width
+        self.theme.window_init_width
     }
 
     pub fn initial_height(&self) -> u32 {
-        self.window_init_height
+        self.theme.window_init_height
+    }
+
+    pub fn font_path(&self) -> Option<&str> {
+        self.theme.font_path.as_deref()
+    }
+
+    pub fn font_size(&self) -> u16 {
+        self.theme.font_size
+    }
+
+    pub fn fallback_fonts(&self) -> &[String] {
+        &self.theme.fallback_fonts
+    }
+
+    pub fn tooltip_delay(&self) -> Duration {
+        Duration::from_millis(self.theme.tooltip_delay as u64)
+    }
+
+    pub fn camera_transition(&self) -> Duration {
+        Duration::from_millis(self.theme.camera_transition as u64)
     }
 
     pub fn window_bg_color(&self) -> Color {
-        self.window_bg_color.clone()
+        self.theme.window_bg_color
     }
 
     pub fn camera_bg_color(&self) -> Color {
-        self.camera_bg_color.clone()
+        self.theme.camera_bg_color
     }
 
     pub fn default_widget_color(&self) -> Color {
-        self.default_widget_color.clone()
+        self.theme.default_widget_color
     }
 
     pub fn grid_line_color(&self) -> Color {
-        self.grid_line_color.clone()
+        self.theme.grid_line_color
+    }
+
+    pub fn grid_major_color(&self) -> Color {
+        self.theme.grid_major_color
+    }
+
+    pub fn grid_major_every(&self) -> i32 {
+        self.theme.grid_major_every
+    }
+
+    pub fn cell_alive_color(&self) -> Color {
+        self.theme.cell_alive_color
+    }
+
+    pub fn cell_dead_color(&self) -> Color {
+        self.theme.cell_dead_color
+    }
+
+    pub fn preview_color(&self) -> Color {
+        self.theme.preview_color
+    }
+
+    pub fn selection_color(&self) -> Color {
+        self.theme.selection_color
+    }
+
+    pub fn annotation_color(&self) -> Color {
+        self.theme.annotation_color
+    }
+
+    pub fn minimap_bg_color(&self) -> Color {
+        self.theme.minimap_bg_color
+    }
+
+    pub fn viewport_indicator_color(&self) -> Color {
+        self.theme.viewport_indicator_color
+    }
+
+    pub fn graph_bg_color(&self) -> Color {
+        self.theme.graph_bg_color
+    }
+
+    pub fn graph_axis_color(&self) -> Color {
+        self.theme.graph_axis_color
+    }
+
+    pub fn population_color(&self) -> Color {
+        self.theme.population_color
+    }
+
+    pub fn births_color(&self) -> Color {
+        self.theme.births_color
+    }
+
+    pub fn deaths_color(&self) -> Color {
+        self.theme.deaths_color
+    }
+
+    pub fn text_color(&self) -> Color {
+        self.theme.text_color
+    }
+
+    pub fn panel_bg_color(&self) -> Color {
+        self.theme.panel_bg_color
+    }
+
+    pub fn panel_highlight_color(&self) -> Color {
+        self.theme.panel_highlight_color
+    }
+
+    pub fn console_bg_color(&self) -> Color {
+        self.theme.console_bg_color
+    }
+
+    pub fn console_echo_color(&self) -> Color {
+        self.theme.console_echo_color
+    }
+
+    pub fn console_error_color(&self) -> Color {
+        self.theme.console_error_color
+    }
+
+    pub fn born_color(&self) -> Color {
+        self.theme.born_color
+    }
+
+    pub fn dying_color(&self) -> Color {
+        self.theme.dying_color
+    }
+
+    pub fn tooltip_bg_color(&self) -> Color {
+        self.theme.tooltip_bg_color
+    }
+
+    pub fn tooltip_text_color(&self) -> Color {
+        self.theme.tooltip_text_color
+    }
+
+    pub fn age_ramp(&self) -> &ColorRamp {
+        &self.theme.age_ramp
+    }
+
+    pub fn trail_ramp(&self) -> &ColorRamp {
+        &self.theme.trail_ramp
     }
 }
@@ -3,119 +3,947 @@ use sdl2::{
         self,
         Texture,
         TextureCreator,
-        Canvas,
     },
-    pixels::Color,
+    pixels::{
+        Color,
+        PixelFormatEnum,
+    },
+    keyboard::{
+        Keycode,
+        Mod,
+    },
+    mouse::MouseButton,
     rect::{
+        Point,
         Rect,
-        Point
     },
     video,
-    pixels::PixelFormatEnum,
 };
 use std::{
     rc::Rc,
     cell::RefCell,
+    time::Instant,
 };
 use crate::{
     utils::error,
-    model::grid,
+    model::{
+        shape,
+        annotation::Mark,
+        pattern::{
+            Pattern,
+            Transform,
+        },
+        universe::RcUniverse,
+        universe_set::RcUniverseSet,
+        rules::LifeRule,
+    },
+};
+use super::super::{
+    theme,
+    keymap::{
+        Action,
+        Scope,
+    },
+};
+use super::{
+    widget_base,
+    viewport::{
+        Viewport,
+        RcViewport,
+    },
+    edit_tool::EditTool,
+    color_mode::{
+        self,
+        ColorMode,
+    },
+    selection::Selection,
+    follow::Follow,
+    event::{
+        WidgetEvent,
+        EventResult,
+    },
 };
-use super::super::theme;
-use super::widget_base;
 
-static CELL_PIXEL_UPPER: usize = 128;
+static DEFAULT_SCALE: f64 = 20.;
+static ZOOM_STEP: f64 = 1.25;
+static PAN_STEP: f64 = 32.;    // 键盘每次平移的像素数
+static MAX_FILL_CELLS: usize = 1 << 16;
+static LABEL_MARGIN: i32 = 8;
+/// 网格线间距(像素)小于START时完全隐藏, 大于END时完全显示
+static LINE_FADE_START: f64 = 4.;
+static LINE_FADE_END: f64 = 12.;
+/// 箭头两翼的长度(像素)范围以及与箭杆的夹角
+static ARROW_HEAD_MIN: f64 = 6.;
+static ARROW_HEAD_MAX: f64 = 16.;
+static ARROW_HEAD_ANGLE: f64 = std::f64::consts::PI / 7.;
+
+/// 其他widget请求放置的图案, 相机在下一帧将其作为粘贴预览
+pub type RcPasteRequest = Rc<RefCell<Option<Pattern>>>;
+
 pub struct ViewGrid {
-    scale: usize,   // 一个cell对应几个像素
-    cell_grid: Rc<RefCell<grid::CellGrid>>,
+    universe: RcUniverse,
     theme_manager: theme::RcThemeManager,
 }
 
 impl ViewGrid {
-    pub fn new(scale: usize, cell_grid: Rc<RefCell<grid::CellGrid>>, theme_manager: theme::RcThemeManager)
-        -> error::Result<Self>
+    pub fn new(universe: RcUniverse, theme_manager: theme::RcThemeManager) -> Self {
+        ViewGrid { universe, theme_manager }
+    }
+
+    pub fn fill_grid(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture,
+        viewport: &Viewport, texture_creator: &TextureCreator<video::WindowContext>,
+        mode: ColorMode) -> error::Result<()>
     {
-        if scale < 0 || scale > CELL_PIXEL_UPPER {
-            return Err(String::from("Invalid Scale").into());
-        }
-        Ok(ViewGrid { scale, cell_grid, theme_manager})
+        self.fill_population(canvas.clone(), texture, viewport, texture_creator, mode)?;
+        self.draw_lines(canvas, texture, viewport)
     }
-    
-    pub fn fill_grid(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture)
-        -> error::Result<()>
+
+    /// 只绘制活细胞(以及尾迹), 一个cell不足一个像素时按密度绘制, 不区分着色方式
+    pub fn fill_population(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture,
+        viewport: &Viewport, texture_creator: &TextureCreator<video::WindowContext>,
+        mode: ColorMode) -> error::Result<()>
     {
-        self.draw_lines(canvas.clone(), texture)?;
-        Ok(())
+        if viewport.scale() < 1. {
+            return self.fill_density(canvas, texture, viewport, texture_creator);
+        }
+        self.fill_cells(canvas, texture, viewport, mode)
     }
 
-    fn draw_lines(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture)
-        -> error::Result<()>
+    /// 按颜色分组, 每组一次绘制, 后面的组覆盖前面的组
+    fn fill_cells(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture,
+        viewport: &Viewport, mode: ColorMode) -> error::Result<()>
     {
-        let query = texture.query();
+        let (min_x, min_y, max_x, max_y) = viewport.visible_cells();
+        let visible = |(x, y): &(i32, i32)| min_x <= *x && *x <= max_x && min_y <= *y && *y <= max_y;
+        let universe = self.universe.borrow();
+        let activity = universe.activity();
+        let alive_cells = universe.grid().alive_cells();
+        let theme_manager = self.theme_manager.borrow();
+        let cell_color = theme_manager.cell_alive_color();
+        let rects = |cells: &mut dyn Iterator<Item = &(i32, i32)>| -> Vec<Rect> {
+            cells
+                .filter(|addr| visible(addr))
+                .map(|addr| viewport.cell_to_screen_rect(*addr))
+                .collect()
+        };
+        // 按色阶量化分组
+        let ramp_groups = |cells: &mut dyn Iterator<Item = (&(i32, i32), usize)>,
+            ramp: &theme::ColorRamp| -> Vec<(Color, Vec<Rect>)>
+        {
+            let mut steps = vec![Vec::new(); color_mode::RAMP_STEPS];
+            for (addr, step) in cells.filter(|(addr, _)| visible(addr)) {
+                steps[step].push(viewport.cell_to_screen_rect(*addr));
+            }
+            steps
+                .into_iter()
+                .enumerate()
+                .map(|(step, rects)| (ramp.sample(color_mode::step_position(step)), rects))
+                .collect()
+        };
+
+        let mut groups = Vec::new();
+        match mode {
+            ColorMode::Plain => groups.push((cell_color, rects(&mut alive_cells.iter()))),
+            ColorMode::Age => {
+                let mut cells = alive_cells
+                    .iter()
+                    .map(|addr| (addr, color_mode::age_step(activity.age(addr).unwrap_or(0))));
+                groups.extend(ramp_groups(&mut cells, theme_manager.age_ramp()));
+            },
+            ColorMode::Changes => {
+                let births = activity.births();
+                groups.push((cell_color, rects(&mut alive_cells.difference(births))));
+                groups.push((theme_manager.born_color(), rects(&mut births.iter())));
+                groups.push((theme_manager.dying_color(), rects(&mut activity.deaths().iter())));
+            },
+            ColorMode::Trails => {
+                let mut cells = activity
+                    .trails()
+                    .iter()
+                    .map(|(addr, trail)| (addr, color_mode::trail_step(*trail)));
+                groups.extend(ramp_groups(&mut cells, theme_manager.trail_ramp()));
+                groups.push((cell_color, rects(&mut alive_cells.iter())));
+            },
+        }
+        drop(theme_manager);
+
         let mut canvas = canvas.borrow_mut();
-        canvas.set_draw_color(self.theme_manager.borrow().grid_line_color());
-        canvas.with_texture_canvas(texture, |canvas|{
-            for i in 0 .. query.width as i32 {
-                let x = i * self.scale as i32 + i;
-                canvas.draw_line((x, 0), (x, query.height as i32)).unwrap();
+        let mut result = Ok(());
+        canvas.with_texture_canvas(texture, |canvas| {
+            for (color, rects) in groups.iter().filter(|(_, rects)| !rects.is_empty()) {
+                canvas.set_draw_color(*color);
+                if let Err(e) = canvas.fill_rects(rects) {
+                    result = Err(e);
+                    return;
+                }
+            }
+        })?;
+        result?;
+        Ok(())
+    }
+
+    /// 每个像素的活细胞数, 由密度汇总自顶向下遍历得到, 只访问非空的块
+    fn count_density(&self, viewport: &Viewport) -> Vec<u32> {
+        let (width, height) = viewport.size();
+        let mut counts = vec![0u32; (width * height) as usize];
+        let universe = self.universe.borrow();
+        let cell_grid = universe.grid();
+        let summary = cell_grid.density_summary();
+        let top = summary.max_level();
+        let footprint = 1. / viewport.scale();    // 一个像素覆盖的cell边长
+        let level = (footprint.log2().floor().max(0.) as usize).min(top);
+
+        let bound = cell_grid.upper_scale() - 1;
+        let (min_x, min_y, max_x, max_y) = viewport.visible_cells();
+        let (min_x, min_y) = (min_x.max(-bound), min_y.max(-bound));
+        let (max_x, max_y) = (max_x.min(bound), max_y.min(bound));
+        if min_x > max_x || min_y > max_y {
+            return counts;
+        }
+
+        let mut stack: Vec<(usize, (i32, i32))> = ((min_y >> top)..=(max_y >> top))
+            .flat_map(|by| ((min_x >> top)..=(max_x >> top)).map(move |bx| (top, (bx, by))))
+            .collect();
+        while let Some((lv, (bx, by))) = stack.pop() {
+            let side = 1 << lv;
+            let (x0, y0) = (bx << lv, by << lv);
+            if x0 + side <= min_x || x0 > max_x || y0 + side <= min_y || y0 > max_y {
+                continue;
+            }
+            let cnt = cell_grid.count_in_block(lv, &(bx, by));
+            if cnt == 0 {
+                continue;
             }
-            for i in 0 .. query.height as i32 {
-                let y = i * self.scale as i32 + i;
-                canvas.draw_line((0, y), (query.width as i32, y)).unwrap();
+            if lv > level {
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    stack.push((lv - 1, (bx * 2 + dx, by * 2 + dy)));
+                }
+                continue;
             }
+
+            // 块不大于像素, 按块中心归属到唯一的像素
+            let half = side as f64 / 2.;
+            let (sx, sy) = viewport.world_to_screen((x0 as f64 + half, y0 as f64 + half));
+            let (sx, sy) = (sx.floor() as i64, sy.floor() as i64);
+            if 0 <= sx && sx < width as i64 && 0 <= sy && sy < height as i64 {
+                counts[(sy * width as i64 + sx) as usize] += cnt;
+            }
+        }
+        counts
+    }
+
+    fn fill_density(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture,
+        viewport: &Viewport, texture_creator: &TextureCreator<video::WindowContext>)
+        -> error::Result<()>
+    {
+        let (width, height) = viewport.size();
+        let area = (1. / viewport.scale()).powi(2);
+        let cell_color = self.theme_manager.borrow().cell_alive_color();
+        let pixels: Vec<u8> = self.count_density(viewport)
+            .iter()
+            .flat_map(|cnt| {
+                let fraction = (*cnt as f64 / area).min(1.);
+                [cell_color.r, cell_color.g, cell_color.b, (fraction * 255.).round() as u8]
+            })
+            .collect();
+
+        let mut density = texture_creator.create_texture_streaming(
+            PixelFormatEnum::RGBA32, width, height)?;
+        density.set_blend_mode(render::BlendMode::Blend);
+        let mut result = density.update(None, &pixels, width as usize * 4)
+            .map_err(|e| e.to_string());
+        if result.is_ok() {
+            canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+                result = canvas.copy(&density, None, None);
+            })?;
+        }
+        // unsafe_textures下需手动销毁, canvas此时仍然存活
+        unsafe { density.destroy(); }
+        result?;
+        Ok(())
+    }
+
+    fn fill_preview(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture,
+        viewport: &Viewport, cells: &[(i32, i32)]) -> error::Result<()>
+    {
+        let rects: Vec<_> = cells
+            .iter()
+            .map(|addr| viewport.cell_to_screen_rect(*addr))
+            .collect();
+        let preview_color = self.theme_manager.borrow().preview_color();
+        let mut result = Ok(());
+        canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_blend_mode(render::BlendMode::Blend);
+            canvas.set_draw_color(preview_color);
+            result = canvas.fill_rects(&rects);
+            canvas.set_blend_mode(render::BlendMode::None);
+        })?;
+        result?;
+        Ok(())
+    }
+
+    fn draw_selection(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture,
+        viewport: &Viewport, selection: &Selection) -> error::Result<()>
+    {
+        let (min_x, min_y, max_x, max_y) = selection.bounds();
+        let top_left = viewport.cell_to_screen_rect((min_x, min_y));
+        let bottom_right = viewport.cell_to_screen_rect((max_x, max_y));
+        let outline = Rect::new(top_left.x(), top_left.y(),
+            (bottom_right.right() - top_left.x()).max(1) as u32,
+            (bottom_right.bottom() - top_left.y()).max(1) as u32);
+        let selection_color = self.theme_manager.borrow().selection_color();
+        let mut result = Ok(());
+        canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_draw_color(selection_color);
+            result = canvas.draw_rect(outline);
         })?;
+        result?;
         Ok(())
     }
 
-    pub fn paint_grid_lines(&self, line_color: Color,
-        canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture)
+    /// 网格线固定在世界坐标上, 每隔`grid_major_every`条画一条主线
+    /// 线间距过小时逐渐淡出, 主线间距更大所以比普通线更晚消失
+    fn draw_lines(&self, canvas: Rc<RefCell<render::WindowCanvas>>, texture: &mut Texture,
+        viewport: &Viewport) -> error::Result<()>
     {
+        let theme_manager = self.theme_manager.borrow();
+        let every = theme_manager.grid_major_every();
+        let scale = viewport.scale();
+        let minor_fade = line_fade(scale);
+        let major_fade = if every > 0 { line_fade(scale * every as f64) } else { 0. };
+        if minor_fade <= 0. && major_fade <= 0. {
+            return Ok(());
+        }
+        let minor_color = faded(theme_manager.grid_line_color(), minor_fade);
+        let major_color = faded(theme_manager.grid_major_color(), major_fade);
+        drop(theme_manager);
+
+        let (min_x, min_y, max_x, max_y) = viewport.visible_cells();
+        // 普通线不可见时只需遍历主线
+        let step = if minor_fade <= 0. { every } else { 1 };
+        let first = |min: i32| min.div_euclid(step) * step;
+        let is_major = |i: i32| every > 0 && i.rem_euclid(every) == 0;
+        let (mut minor, mut major) = (Vec::new(), Vec::new());
         let query = texture.query();
-        let width = query.width;
-        let height = query.height;
-        let mut canvas = canvas.borrow_mut();
-        for i in 0..width {
-            
+        let (width, height) = (query.width as i32, query.height as i32);
+        for x in (first(min_x) ..= max_x + 1).step_by(step as usize) {
+            let sx = viewport.world_to_screen((x as f64, 0.)).0.floor() as i32;
+            let line = (Point::new(sx, 0), Point::new(sx, height));
+            if is_major(x) { major.push(line) } else { minor.push(line) }
         }
+        for y in (first(min_y) ..= max_y + 1).step_by(step as usize) {
+            let sy = viewport.world_to_screen((0., y as f64)).1.floor() as i32;
+            let line = (Point::new(0, sy), Point::new(width, sy));
+            if is_major(y) { major.push(line) } else { minor.push(line) }
+        }
+
+        let mut result = Ok(());
+        canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            let blend_mode = canvas.blend_mode();
+            canvas.set_blend_mode(render::BlendMode::Blend);
+            for (color, lines) in [(minor_color, &minor), (major_color, &major)] {
+                if color.a == 0 {
+                    continue;
+                }
+                canvas.set_draw_color(color);
+                for &(from, to) in lines.iter() {
+                    result = canvas.draw_line(from, to);
+                    if result.is_err() {
+                        break;
+                    }
+                }
+            }
+            canvas.set_blend_mode(blend_mode);
+        })?;
+        result?;
+        Ok(())
     }
+}
 
-    fn get_rect(&self, x: i32, y: i32) -> Rect {
-        Rect::new(x, y, self.scale as u32, self.scale as u32)
+fn line_fade(spacing: f64) -> f64 {
+    ((spacing - LINE_FADE_START) / (LINE_FADE_END - LINE_FADE_START)).clamp(0., 1.)
+}
+
+/// 箭杆与箭头两翼组成的折线, 起点与终点重合时为空
+fn arrow_lines(from: (f64, f64), to: (f64, f64), scale: f64) -> Vec<Point> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy);
+    if length < 1. {
+        return Vec::new();
     }
+    let head = (scale * 1.5).clamp(ARROW_HEAD_MIN, ARROW_HEAD_MAX).min(length);
+    let back = dy.atan2(dx) + std::f64::consts::PI;
+    let wing = |angle: f64| Point::new((to.0 + head * angle.cos()) as i32, (to.1 + head * angle.sin()) as i32);
+    let tip = Point::new(to.0 as i32, to.1 as i32);
+    vec![Point::new(from.0 as i32, from.1 as i32), tip,
+        wing(back + ARROW_HEAD_ANGLE), tip, wing(back - ARROW_HEAD_ANGLE)]
+}
+
+fn faded(color: Color, fade: f64) -> Color {
+    Color::RGBA(color.r, color.g, color.b, (color.a as f64 * fade).round() as u8)
+}
+
+enum Drag {
+    Pan,
+    /// 铅笔/橡皮的笔画, 记录上一次采样的cell用于插值
+    Stroke { alive: bool, last: (i32, i32) },
+    /// 直线/矩形, 松开鼠标时提交
+    Shape { alive: bool, start: (i32, i32), end: (i32, i32) },
+    Select { start: (i32, i32) },
 }
 
 pub struct Camera {
     widget_base: widget_base::WidgetBase,
-    pos: (i32, i32),
+    viewport: RcViewport,
     view_grid: ViewGrid,
     texture_cache: Option<Texture>,
+    dirty: bool,
+    drawn_viewport: Option<Viewport>,
+    /// 网格的修改次数、代数与标注的修改次数, 代数显示在标签中
+    drawn_revision: Option<(u64, u64, u64)>,
+    tool: EditTool,
+    drag: Option<Drag>,
+    /// 开始拖拽的按键, 松开其他按键不会结束拖拽
+    drag_button: MouseButton,
+    hover_cell: Option<(i32, i32)>,
+    selection: Option<Selection>,
+    clipboard: Option<Pattern>,
+    /// 等待放置的粘贴内容, 左上角跟随光标
+    floating: Option<Pattern>,
+    paste_request: RcPasteRequest,
+    color_mode: ColorMode,
+    universes: RcUniverseSet,
+    /// 跟随的物体, 模拟推进时相机保持它居中
+    follow: Option<Follow>,
 }
 
 impl Camera {
-    pub fn new(widget_base: widget_base::WidgetBase, theme_manager: theme::RcThemeManager)
-        -> Result<Self, error::DynError>
+    pub fn new(widget_base: widget_base::WidgetBase, theme_manager: theme::RcThemeManager,
+        universe: RcUniverse, universes: RcUniverseSet) -> Result<Self, error::DynError>
     {
-        let pos = (0, 0);
-        let cell_grid = grid::CellGrid::new(1000);
-        let cell_grid = Rc::new(RefCell::new(cell_grid));
-        let view_grid = ViewGrid::new(20, cell_grid, theme_manager.clone())?;
-        
-        Ok(Camera { widget_base, pos, view_grid, texture_cache: None })
-    }
-    
+        let viewport = Rc::new(RefCell::new(Viewport::new((0., 0.), DEFAULT_SCALE)?));
+        let view_grid = ViewGrid::new(universe, theme_manager.clone());
+
+        Ok(Camera {
+            widget_base,
+            viewport,
+            view_grid,
+            texture_cache: None,
+            dirty: true,
+            drawn_viewport: None,
+            drawn_revision: None,
+            tool: EditTool::Pencil,
+            drag: None,
+            drag_button: MouseButton::Left,
+            hover_cell: None,
+            selection: None,
+            clipboard: None,
+            floating: None,
+            paste_request: Rc::new(RefCell::new(None)),
+            color_mode: ColorMode::Plain,
+            universes,
+            follow: None,
+        })
+    }
+
+    /// 视口可以与小地图等widget共享
+    pub fn viewport(&self) -> RcViewport {
+        self.viewport.clone()
+    }
+
+    pub fn paste_request(&self) -> RcPasteRequest {
+        self.paste_request.clone()
+    }
+
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        self.color_mode = color_mode;
+        self.dirty = true;
+    }
+
+    pub fn set_tool(&mut self, tool: EditTool) {
+        self.tool = tool;
+        if let Some(Drag::Stroke { .. }) = self.drag.take() {
+            self.view_grid.universe.borrow_mut().end_group();
+        }
+        if tool != EditTool::Select {
+            self.floating = None;
+        }
+        self.dirty = true;
+    }
+
+    pub fn copy_selection(&mut self) {
+        if let Some(selection) = self.selection {
+            self.clipboard = Some(self.view_grid.universe.borrow().extract(selection.bounds()));
+        }
+    }
+
+    pub fn cut_selection(&mut self) {
+        self.copy_selection();
+        self.clear_selection(true);
+    }
+
+    /// 剪贴板内容作为浮动预览跟随光标, 点击后放置
+    pub fn paste(&mut self) {
+        if let Some(pattern) = self.clipboard.clone() {
+            self.begin_paste(pattern);
+        }
+    }
+
+    pub fn begin_paste(&mut self, pattern: Pattern) {
+        self.set_tool(EditTool::Select);
+        self.floating = Some(pattern);
+    }
+
+    pub fn cancel(&mut self) {
+        if self.floating.take().is_none() {
+            self.selection = None;
+        }
+        self.dirty = true;
+    }
+
+    pub fn clear_selection(&mut self, inside: bool) {
+        if let Some(selection) = self.selection {
+            self.view_grid.universe.borrow_mut().clear_region(selection.bounds(), inside);
+        }
+    }
+
+    /// 有浮动内容时变换浮动内容, 否则原地变换选区内的cell
+    pub fn transform(&mut self, transform: Transform) {
+        if let Some(floating) = &self.floating {
+            self.floating = Some(floating.transformed(transform));
+            self.dirty = true;
+            return;
+        }
+        let Some(selection) = self.selection else {
+            return;
+        };
+        let pattern = self.view_grid.universe.borrow_mut().grouped(|universe| {
+            let pattern = universe.extract(selection.bounds()).transformed(transform);
+            universe.clear_region(selection.bounds(), true);
+            universe.stamp(&pattern, selection.origin());
+            pattern
+        });
+        self.selection = Some(Selection::with_size(selection.origin(), pattern.width(), pattern.height()));
+    }
+
+    /// 将选区连同其中的cell移动一格
+    pub fn nudge(&mut self, dx: i32, dy: i32) {
+        let Some(selection) = self.selection else {
+            return;
+        };
+        let moved = selection.translated(dx, dy);
+        self.view_grid.universe.borrow_mut().grouped(|universe| {
+            let pattern = universe.extract(selection.bounds());
+            universe.clear_region(selection.bounds(), true);
+            universe.stamp(&pattern, moved.origin());
+        });
+        self.selection = Some(moved);
+    }
+
+    /// 手动平移时停止跟随
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.stop_follow();
+        self.viewport.borrow_mut().pan_pixels(dx, dy);
+    }
+
+    /// 平滑地缩放到显示所有活细胞, 没有活细胞时回到原点
+    pub fn zoom_to_fit(&mut self) {
+        self.stop_follow();
+        let bounds = self.view_grid.universe.borrow().grid().bounding_box();
+        let duration = self.widget_base.theme_manager.borrow().camera_transition();
+        self.viewport.borrow_mut().zoom_to_fit(bounds, duration);
+    }
+
+    /// 正在跟随时停止; 否则有选区时跟随选区内的细胞, 没有选区时跟随光标处(或紧邻光标)的一组细胞
+    pub fn toggle_follow(&mut self) {
+        if self.follow.is_some() {
+            self.stop_follow();
+            return;
+        }
+        let universe = self.view_grid.universe.borrow();
+        let grid = universe.grid();
+        let seeds: Vec<_> = match (self.selection, self.hover_cell) {
+            (Some(selection), _) => {
+                let (min_x, min_y, max_x, max_y) = selection.bounds();
+                grid.alive_cells()
+                    .iter()
+                    .filter(|(x, y)| min_x <= *x && *x <= max_x && min_y <= *y && *y <= max_y)
+                    .copied()
+                    .collect()
+            },
+            (None, Some((x, y))) => shape::filled_rect((x - 1, y - 1), (x + 1, y + 1))
+                .into_iter()
+                .filter(|cell| grid.normalize(cell).is_some_and(|addr| grid.is_alive(&addr)))
+                .min_by_key(|(cx, cy)| (cx - x).abs() + (cy - y).abs())
+                .into_iter()
+                .collect(),
+            (None, None) => Vec::new(),
+        };
+        self.follow = Follow::start(grid, &seeds, universe.generation());
+        drop(universe);
+        self.dirty = true;
+    }
+
+    fn stop_follow(&mut self) {
+        if self.follow.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// 模拟推进后把相机移向跟随物体的新位置, 物体消失时停止跟随
+    fn update_follow(&mut self) {
+        let Some(follow) = &mut self.follow else {
+            return;
+        };
+        let found = {
+            let universe = self.view_grid.universe.borrow();
+            follow.update(universe.grid(), universe.generation())
+        };
+        if !found {
+            self.stop_follow();
+            return;
+        }
+        let centroid = follow.centroid();
+        let mut viewport = self.viewport.borrow_mut();
+        let (center, scale) = viewport.target();
+        if center != centroid {
+            let duration = self.widget_base.theme_manager.borrow().camera_transition();
+            viewport.animate_to(centroid, scale, duration);
+        }
+    }
+
+    pub fn zoom_at(&mut self, factor: f64, anchor: (f64, f64)) {
+        self.viewport.borrow_mut().zoom_at(factor, anchor);
+    }
+
+    fn paint(&mut self, cells: &[(i32, i32)], alive: bool) {
+        self.view_grid.universe.borrow_mut().set_cells(cells, alive);
+    }
+
+    fn flood_fill(&mut self, start: (i32, i32)) {
+        let (region, alive) = {
+            let universe = self.view_grid.universe.borrow();
+            let cell_grid = universe.grid();
+            let region = shape::flood_region(cell_grid, start, self.viewport.borrow().visible_cells(),
+                MAX_FILL_CELLS);
+            let alive = region.first().is_some_and(|origin| !cell_grid.is_alive(origin));
+            (region, alive)
+        };
+        self.paint(&region, alive);
+    }
+
     fn fill_base_element(&self, texture: &mut Texture) -> Result<(), error::DynError> {
-        let bg_color = {
+        let viewport = *self.viewport.borrow();
+        let (bg_color, dead_color) = {
             let theme_manager = self.widget_base.theme_manager.borrow();
-            theme_manager.camera_bg_color()
+            (theme_manager.camera_bg_color(), theme_manager.cell_dead_color())
         };
+        // 网格有效范围内的区域使用死细胞的颜色, 范围之外为背景色
+        let (min_x, min_y, max_x, max_y) = viewport.visible_cells();
+        let bound = self.view_grid.universe.borrow().grid().upper_scale() - 1;
+        let (min_x, min_y) = (min_x.max(-bound), min_y.max(-bound));
+        let (max_x, max_y) = (max_x.min(bound), max_y.min(bound));
+        let grid_rect = (min_x <= max_x && min_y <= max_y).then(|| viewport
+            .cell_to_screen_rect((min_x, min_y))
+            .union(viewport.cell_to_screen_rect((max_x, max_y))));
 
+        let mut result = Ok(());
         self.widget_base.canvas.borrow_mut().with_texture_canvas(
             texture, |canvas| {
             canvas.set_draw_color(bg_color);
             canvas.clear();
+            if let Some(grid_rect) = grid_rect {
+                canvas.set_draw_color(dead_color);
+                result = canvas.fill_rect(grid_rect);
+            }
         })?;
-        self.view_grid.fill_grid(self.widget_base.canvas.clone(), texture)?;
-        Ok(())
+        result?;
+        self.view_grid.fill_grid(self.widget_base.canvas.clone(), texture, &viewport,
+            &self.widget_base.texture_creator, self.color_mode)?;
+        if let Some(Drag::Shape { start, end, .. }) = self.drag {
+            let cells = self.tool.shape_cells(start, end);
+            self.view_grid.fill_preview(self.widget_base.canvas.clone(), texture, &viewport,
+                &cells)?;
+        }
+        if let Some(selection) = &self.selection {
+            self.view_grid.draw_selection(self.widget_base.canvas.clone(), texture, &viewport,
+                selection)?;
+        }
+        if let (Some(floating), Some(cell)) = (&self.floating, self.hover_cell) {
+            self.view_grid.fill_preview(self.widget_base.canvas.clone(), texture, &viewport,
+                &floating.placed_at(cell))?;
+        }
+        self.draw_annotations(texture, &viewport)?;
+        self.draw_labels(texture)
+    }
+
+    /// 标注随相机移动与缩放, 文字保持原本的大小
+    fn draw_annotations(&self, texture: &mut Texture, viewport: &Viewport) -> error::Result<()> {
+        let universe = self.view_grid.universe.borrow();
+        if universe.annotations().is_empty() {
+            return Ok(());
+        }
+        let default_color = self.widget_base.theme_manager.borrow().annotation_color();
+        let (min_x, min_y, max_x, max_y) = viewport.visible_cells();
+        let (width, _) = viewport.size();
+        let center = |(x, y): (i32, i32)| {
+            let (sx, sy) = viewport.world_to_screen((x as f64 + 0.5, y as f64 + 0.5));
+            (sx.floor(), sy.floor())
+        };
+        let mut result = Ok(());
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            for annotation in universe.annotations() {
+                let (left, top, right, bottom) = annotation.bounds();
+                // 文字向右延伸, 只按左边界裁剪
+                let right = if matches!(annotation.mark, Mark::Label { .. }) { max_x } else { right };
+                if right < min_x || max_x < left || bottom < min_y || max_y < top {
+                    continue;
+                }
+                let color = annotation.color
+                    .map(|(r, g, b)| Color::RGB(r, g, b))
+                    .unwrap_or(default_color);
+                canvas.set_draw_color(color);
+                result = match &annotation.mark {
+                    Mark::Label { at, text } => {
+                        let (x, y) = viewport.world_to_screen((at.0 as f64, at.1 as f64));
+                        if x >= width as f64 {
+                            continue;
+                        }
+                        self.widget_base
+                            .draw_text(canvas, text, (x.floor() as i32, y.floor() as i32), color)
+                            .map(|_| ())
+                    },
+                    Mark::Rect { min, max } => {
+                        let outline = viewport.cell_to_screen_rect(*min).union(viewport.cell_to_screen_rect(*max));
+                        canvas.draw_rect(outline).map_err(Into::into)
+                    },
+                    Mark::Arrow { from, to } => {
+                        let (from, to) = (center(*from), center(*to));
+                        let lines = arrow_lines(from, to, viewport.scale());
+                        canvas.draw_lines(lines.as_slice()).map_err(Into::into)
+                    },
+                };
+                if result.is_err() {
+                    return;
+                }
+            }
+        })?;
+        result
+    }
+
+    /// 左上角的说明: 有多个宇宙时显示所属宇宙的规则与代数, 非默认着色时显示着色方式, 以及跟随的物体
+    fn draw_labels(&self, texture: &mut Texture) -> error::Result<()> {
+        let mut labels = Vec::new();
+        {
+            let locale = self.widget_base.locale.borrow();
+            let universes = self.universes.borrow();
+            if universes.count() > 1 {
+                let universe = self.view_grid.universe.borrow();
+                let name = universes.name_of(&self.view_grid.universe).unwrap_or_default();
+                labels.push(locale.format("camera.universe", &[&name, &universe.rule(),
+                    &universe.generation(), &universe.grid().alive_cells().len()]));
+            }
+            if self.color_mode != ColorMode::Plain {
+                let mode = locale.text(&format!("color_mode.{}", self.color_mode.name())).to_string();
+                labels.push(locale.format("camera.colour", &[&mode]));
+            }
+            if let Some(follow) = &self.follow {
+                labels.push(locale.format("camera.following", &[&follow.population()]));
+            }
+        }
+        if labels.is_empty() {
+            return Ok(());
+        }
+        let text_color = self.widget_base.theme_manager.borrow().text_color();
+        let line_height = self.widget_base.text_renderer.borrow().line_height() as i32;
+        let mut result = Ok(());
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            for (i, label) in labels.iter().enumerate() {
+                let pos = (LABEL_MARGIN, LABEL_MARGIN + i as i32 * line_height);
+                if let Err(e) = self.widget_base.draw_text(canvas, label, pos, text_color) {
+                    result = Err(e);
+                    return;
+                }
+            }
+        })?;
+        result
+    }
+
+    /// 光标下cell的坐标、状态、活邻居数、年龄以及下一代的结果
+    fn inspect_cell(&self, cell: (i32, i32)) -> String {
+        let locale = self.widget_base.locale.borrow();
+        let universe = self.view_grid.universe.borrow();
+        let grid = universe.grid();
+        let Some(addr) = grid.normalize(&cell) else {
+            return format!("{}\n{}", locale.format("camera.cell", &[&cell.0, &cell.1]),
+                locale.text("camera.outside"));
+        };
+        let rule = universe.rule();
+        let alive = grid.is_alive(&addr);
+        let neighbors = LifeRule::count_neighbors(grid, addr);
+        let state = match universe.activity().age(&addr) {
+            Some(age) if alive => locale.format("camera.alive_age", &[&age]),
+            _ if alive => locale.text("camera.alive").to_string(),
+            _ => locale.text("camera.dead").to_string(),
+        };
+        let fate = match (alive, rule.next_state(alive, neighbors)) {
+            (true, true) => "camera.survives",
+            (true, false) => "camera.dies",
+            (false, true) => "camera.born",
+            (false, false) => "camera.stays_dead",
+        };
+        format!("{}\n{}\n{}\n{}", locale.format("camera.cell", &[&addr.0, &addr.1]), state,
+            locale.format("camera.neighbours", &[&neighbors, &rule]), locale.text(fate))
+    }
+
+    fn handle_mouse_down(&mut self, x: i32, y: i32, button: MouseButton) -> EventResult {
+        if self.drag.is_some() {
+            return EventResult::Handled;
+        }
+        let action = self.widget_base.keymap.borrow().mouse_action(button, Scope::Camera);
+        let Some(action) = action else {
+            return EventResult::Ignored;
+        };
+        let cell = self.viewport.borrow().screen_to_cell((x, y));
+        // DrawInverse执行相反的操作
+        let alive = action == Action::Draw && self.tool != EditTool::Eraser;
+        let drag = match (action, self.tool) {
+            (Action::DragPan, _) | (Action::Draw | Action::DrawInverse, EditTool::Pan) => Drag::Pan,
+            (Action::Draw | Action::DrawInverse, tool) if tool.is_freehand() => {
+                // 一笔之内的修改作为一条撤销记录
+                self.view_grid.universe.borrow_mut().begin_group();
+                self.paint(&[cell], alive);
+                Drag::Stroke { alive, last: cell }
+            },
+            (Action::Draw | Action::DrawInverse, tool) if tool.is_shape() => {
+                self.dirty = true;
+                Drag::Shape { alive, start: cell, end: cell }
+            },
+            (Action::Draw | Action::DrawInverse, EditTool::Toggle) => {
+                self.view_grid.universe.borrow_mut().toggle_cell(&cell);
+                return EventResult::Handled;
+            },
+            (Action::Draw | Action::DrawInverse, EditTool::Fill) => {
+                self.flood_fill(cell);
+                return EventResult::Handled;
+            },
+            (Action::Draw, EditTool::Select) => {
+                if let Some(floating) = self.floating.take() {
+                    self.view_grid.universe.borrow_mut().stamp(&floating, cell);
+                    self.selection = Some(Selection::with_size(cell, floating.width(), floating.height()));
+                    return EventResult::Handled;
+                }
+                self.selection = Some(Selection::from_corners(cell, cell));
+                self.dirty = true;
+                Drag::Select { start: cell }
+            },
+            (Action::DrawInverse, EditTool::Select) => {
+                self.cancel();
+                return EventResult::Handled;
+            },
+            _ => return EventResult::Ignored,
+        };
+        self.drag = Some(drag);
+        self.drag_button = button;
+        EventResult::Capture
+    }
+
+    fn handle_mouse_motion(&mut self, x: i32, y: i32, xrel: i32, yrel: i32) -> EventResult {
+        let cell = self.viewport.borrow().screen_to_cell((x, y));
+        if self.hover_cell != Some(cell) {
+            self.hover_cell = Some(cell);
+            self.dirty |= self.floating.is_some();
+        }
+        match self.drag {
+            Some(Drag::Pan) => self.pan(-xrel as f64, -yrel as f64),
+            Some(Drag::Stroke { alive, last }) => {
+                if cell != last {
+                    // 鼠标采样间隔较大时用直线连接两次采样
+                    self.paint(&shape::line(last, cell), alive);
+                    self.drag = Some(Drag::Stroke { alive, last: cell });
+                }
+            },
+            Some(Drag::Shape { alive, start, end }) => {
+                if cell != end {
+                    self.drag = Some(Drag::Shape { alive, start, end: cell });
+                    self.dirty = true;
+                }
+            },
+            Some(Drag::Select { start }) => {
+                let selection = Selection::from_corners(start, cell);
+                if self.selection != Some(selection) {
+                    self.selection = Some(selection);
+                    self.dirty = true;
+                }
+            },
+            None => return EventResult::Ignored,
+        }
+        EventResult::Handled
+    }
+
+    fn handle_mouse_up(&mut self, button: MouseButton) -> EventResult {
+        if self.drag.is_some() && button != self.drag_button {
+            return EventResult::Handled;
+        }
+        match self.drag.take() {
+            Some(Drag::Shape { alive, start, end }) => {
+                let cells = self.tool.shape_cells(start, end);
+                self.paint(&cells, alive);
+                self.dirty = true;
+            },
+            Some(Drag::Stroke { .. }) => self.view_grid.universe.borrow_mut().end_group(),
+            Some(_) => {},
+            None => return EventResult::Ignored,
+        }
+        EventResult::Handled
+    }
+
+    fn handle_key(&mut self, keycode: Keycode, keymod: Mod) -> EventResult {
+        let action = self.widget_base.keymap.borrow().key_action(keycode, keymod, Scope::Camera);
+        let Some(action) = action else {
+            return EventResult::Ignored;
+        };
+        if let Some(tool) = EditTool::from_action(action) {
+            self.set_tool(tool);
+            return EventResult::Handled;
+        }
+
+        match action {
+            Action::Copy => self.copy_selection(),
+            Action::Cut => self.cut_selection(),
+            Action::Paste => self.paste(),
+            Action::Cancel => self.cancel(),
+            Action::ClearSelection => self.clear_selection(true),
+            Action::ClearOutside => self.clear_selection(false),
+            Action::Rotate180 => self.transform(Transform::Rotate180),
+            Action::RotateCw => self.transform(Transform::RotateCw),
+            Action::RotateCcw => self.transform(Transform::RotateCcw),
+            Action::FlipHorizontal => self.transform(Transform::FlipHorizontal),
+            Action::FlipVertical => self.transform(Transform::FlipVertical),
+            Action::Transpose => self.transform(Transform::Transpose),
+            Action::NudgeLeft => self.nudge(-1, 0),
+            Action::NudgeRight => self.nudge(1, 0),
+            Action::NudgeUp => self.nudge(0, -1),
+            Action::NudgeDown => self.nudge(0, 1),
+            Action::CycleColorMode => self.set_color_mode(self.color_mode.next()),
+            Action::ZoomToFit => self.zoom_to_fit(),
+            Action::Follow => self.toggle_follow(),
+            _ => return self.handle_pan_key(action),
+        }
+        self.dirty = true;
+        EventResult::Handled
+    }
+
+    fn handle_pan_key(&mut self, action: Action) -> EventResult {
+        let (dx, dy) = match action {
+            Action::PanLeft => (-PAN_STEP, 0.),
+            Action::PanRight => (PAN_STEP, 0.),
+            Action::PanUp => (0., -PAN_STEP),
+            Action::PanDown => (0., PAN_STEP),
+            _ => return EventResult::Ignored,
+        };
+        self.pan(dx, dy);
+        EventResult::Handled
     }
 }
 
@@ -124,17 +952,82 @@ impl widget_base::Widget for Camera {
         &self.widget_base
     }
 
-    fn rendering_on_texture(&mut self, width: u32, height: u32) 
+    fn rendering_on_texture(&mut self, width: u32, height: u32)
         -> Result<& render::Texture, error::DynError>
     {
-        if let Some(ref value) = self.texture_cache {
-            return Ok(value)
+        widget_base::drop_stale_cache(&mut self.texture_cache, width, height);
+        let requested = self.paste_request.borrow_mut().take();
+        if let Some(pattern) = requested {
+            self.begin_paste(pattern);
+        }
+        self.update_follow();
+        let viewport = {
+            let mut viewport = self.viewport.borrow_mut();
+            viewport.set_size(width, height);
+            viewport.advance(Instant::now());
+            *viewport
+        };
+        if self.drawn_viewport != Some(viewport) {
+            self.drawn_viewport = Some(viewport);
+            self.dirty = true;
+        }
+        let revision = {
+            let universe = self.view_grid.universe.borrow();
+            (universe.grid().revision(), universe.generation(), universe.annotation_revision())
+        };
+        if self.drawn_revision != Some(revision) {
+            self.drawn_revision = Some(revision);
+            self.dirty = true;
         }
 
-        let mut texture = self.create_new_texture(width, height)?;
-        self.fill_base_element(&mut texture)?;
+        let mut texture = match self.texture_cache.take() {
+            Some(texture) => texture,
+            None => {
+                self.dirty = true;
+                self.create_new_texture(width, height)?
+            },
+        };
+        if self.dirty {
+            self.fill_base_element(&mut texture)?;
+            self.dirty = false;
+        }
 
-        Ok(self.texture_cache.insert(texture))   
+        Ok(self.texture_cache.insert(texture))
+    }
+
+    fn invalidate(&mut self) {
+        widget_base::destroy_cache(&mut self.texture_cache);
+    }
+
+    fn handle_event(&mut self, event: &WidgetEvent) -> EventResult {
+        if let WidgetEvent::MouseButtonDown { .. } | WidgetEvent::KeyDown { .. } = event {
+            // 撤销/重做作用于最近操作的相机所显示的宇宙
+            self.universes.borrow_mut().set_active(&self.view_grid.universe);
+        }
+        match *event {
+            WidgetEvent::MouseButtonDown { x, y, button, .. } => self.handle_mouse_down(x, y, button),
+            WidgetEvent::MouseButtonUp { button, .. } => self.handle_mouse_up(button),
+            WidgetEvent::MouseMotion { x, y, xrel, yrel, .. } => self.handle_mouse_motion(x, y, xrel, yrel),
+            WidgetEvent::MouseWheel { x, y, scroll_y, .. } => {
+                self.zoom_at(ZOOM_STEP.powf(scroll_y as f64), (x as f64, y as f64));
+                EventResult::Handled
+            },
+            WidgetEvent::KeyDown { keycode, keymod, .. } => self.handle_key(keycode, keymod),
+            WidgetEvent::MouseLeave => {
+                self.hover_cell = None;
+                self.dirty |= self.floating.is_some();
+                EventResult::Handled
+            },
+            _ => EventResult::Ignored,
+        }
     }
-}
 
+    fn tooltip(&self, x: i32, y: i32) -> Option<String> {
+        // 拖拽或放置粘贴内容时提示会遮挡操作
+        if self.drag.is_some() || self.floating.is_some() {
+            return None;
+        }
+        let cell = self.viewport.borrow().screen_to_cell((x, y));
+        Some(self.inspect_cell(cell))
+    }
+}
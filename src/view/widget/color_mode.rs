@@ -0,0 +1,55 @@
+use crate::model::activity::TRAIL_LENGTH;
+
+/// 色阶被量化的级数, 同一级的cell一次绘制
+pub static RAMP_STEPS: usize = 16;
+/// 存活达到该代数后颜色不再变化
+static AGE_SATURATION: u32 = 256;
+
+/// 相机中活细胞的着色方式, 色阶与颜色来自主题
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// 所有活细胞同一颜色
+    Plain,
+    /// 按存活的代数着色
+    Age,
+    /// 突出这一代出生与死亡的cell
+    Changes,
+    /// 近期死亡的位置留下逐渐消失的尾迹
+    Trails,
+}
+
+impl ColorMode {
+    pub fn next(&self) -> Self {
+        match self {
+            ColorMode::Plain => ColorMode::Age,
+            ColorMode::Age => ColorMode::Changes,
+            ColorMode::Changes => ColorMode::Trails,
+            ColorMode::Trails => ColorMode::Plain,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorMode::Plain => "plain",
+            ColorMode::Age => "age",
+            ColorMode::Changes => "changes",
+            ColorMode::Trails => "trails",
+        }
+    }
+}
+
+/// 色阶级数对应的位置, 用于从色阶中取色
+pub fn step_position(step: usize) -> f64 {
+    step as f64 / (RAMP_STEPS - 1) as f64
+}
+
+/// 按对数分布, 使年轻的cell之间更容易区分
+pub fn age_step(age: u32) -> usize {
+    let t = ((age.min(AGE_SATURATION) + 1) as f64).ln() / ((AGE_SATURATION + 1) as f64).ln();
+    (t * (RAMP_STEPS - 1) as f64).round() as usize
+}
+
+pub fn trail_step(trail: u32) -> usize {
+    let t = trail.min(TRAIL_LENGTH) as f64 / TRAIL_LENGTH as f64;
+    (t * (RAMP_STEPS - 1) as f64).round() as usize
+}
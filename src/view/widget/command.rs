@@ -0,0 +1,461 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    path::PathBuf,
+};
+use crate::{
+    utils::error,
+    model::{
+        annotation::Annotation,
+        pattern_io::{
+            self,
+            PatternError,
+        },
+        rules::{
+            LifeRule,
+            RuleError,
+        },
+        universe::RcUniverse,
+        universe_set::RcUniverseSet,
+    },
+};
+use super::{
+    super::{
+        theme::RcThemeManager,
+        locale::{
+            LocaleManager,
+            RcLocaleManager,
+        },
+    },
+    viewport::RcViewport,
+};
+
+static MAX_STEPS: u64 = 100_000;
+static MAX_RANDOM_CELLS: i64 = 1 << 20;
+
+pub static COMMAND_NAMES: [&str; 14] = ["bookmark", "clear", "fit", "goto", "help", "language", "load",
+    "note", "randomize", "rule", "save", "step", "sync", "theme"];
+
+/// 控制台命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// 不带参数时显示当前规则
+    Rule(Option<LifeRule>),
+    Step(u64),
+    Goto(i32, i32),
+    /// 不带参数时列出所有书签, 否则把当前视口保存为书签
+    Bookmark(Option<String>),
+    GotoBookmark(String),
+    /// 缩放到显示所有活细胞
+    Fit,
+    /// 不带参数时列出当前宇宙的所有标注, 否则添加一个标注
+    Note(Option<Annotation>),
+    /// 删除覆盖某个cell的标注, 不带参数时删除所有标注
+    EraseNotes(Option<(i32, i32)>),
+    Load(PathBuf),
+    Save(PathBuf),
+    Randomize(f64),
+    /// 把当前宇宙的cell复制到其他宇宙
+    Sync,
+    /// 不带参数时列出可用的主题
+    Theme(Option<String>),
+    /// 不带参数时列出可用的语言
+    Language(Option<String>),
+    Clear,
+    Help,
+}
+
+impl Command {
+    /// 错误信息使用当前语言
+    pub fn parse(line: &str, locale: &LocaleManager) -> error::Result<Self> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err(locale.text("console.empty").into());
+        };
+        let args: Vec<_> = words.collect();
+        let command = match (name, args.as_slice()) {
+            ("rule", []) => Command::Rule(None),
+            ("rule", [rule]) => Command::Rule(Some(rule
+                .parse()
+                .map_err(|e| rule_error_message(&e, locale))?)),
+            ("step", []) => Command::Step(1),
+            ("step", [count]) => {
+                let count: u64 = count
+                    .parse()
+                    .map_err(|_| locale.format("console.invalid_count", &[count]))?;
+                if count > MAX_STEPS {
+                    return Err(locale.format("console.too_many_steps", &[&MAX_STEPS]).into());
+                }
+                Command::Step(count)
+            },
+            ("goto", [x, y]) => Command::Goto(parse_coordinate(x, locale)?, parse_coordinate(y, locale)?),
+            ("goto", [name]) => Command::GotoBookmark(name.to_string()),
+            ("bookmark", []) => Command::Bookmark(None),
+            ("bookmark", [name]) => Command::Bookmark(Some(name.to_string())),
+            ("fit", []) => Command::Fit,
+            ("note", []) => Command::Note(None),
+            ("note", ["clear"]) => Command::EraseNotes(None),
+            ("note", ["erase", x, y]) => {
+                Command::EraseNotes(Some((parse_coordinate(x, locale)?, parse_coordinate(y, locale)?)))
+            },
+            ("note", [_, ..]) => {
+                // 标注的文字可以包含空格
+                let annotation = line.trim_start()[name.len()..]
+                    .parse()
+                    .map_err(|e| locale.format("console.invalid_note", &[&e]))?;
+                Command::Note(Some(annotation))
+            },
+            ("load" | "save", [_, ..]) => {
+                // 文件名可以包含空格
+                let path = PathBuf::from(line.trim_start()[name.len()..].trim());
+                if name == "load" { Command::Load(path) } else { Command::Save(path) }
+            },
+            ("randomize", [density]) => {
+                let density: f64 = density
+                    .parse()
+                    .ok()
+                    .filter(|density| (0. ..=1.).contains(density))
+                    .ok_or_else(|| locale.format("console.invalid_density", &[density]))?;
+                Command::Randomize(density)
+            },
+            ("theme", []) => Command::Theme(None),
+            ("theme", [name]) => Command::Theme(Some(name.to_string())),
+            ("language", []) => Command::Language(None),
+            ("language", [id]) => Command::Language(Some(id.to_string())),
+            ("sync", []) => Command::Sync,
+            ("clear", []) => Command::Clear,
+            ("help", []) => Command::Help,
+            (name, _) if COMMAND_NAMES.contains(&name) => {
+                return Err(locale.format("console.invalid_arguments", &[&name]).into());
+            },
+            (name, _) => return Err(locale.format("console.unknown_command", &[&name]).into()),
+        };
+        Ok(command)
+    }
+}
+
+fn parse_coordinate(text: &str, locale: &LocaleManager) -> error::Result<i32> {
+    text.parse()
+        .map_err(|_| locale.format("console.invalid_coordinate", &[&text]).into())
+}
+
+/// 用当前语言描述规则错误, 错误本身的Display只用于配置文件
+pub fn rule_error_message(e: &RuleError, locale: &LocaleManager) -> String {
+    match e {
+        RuleError::Syntax(text) => locale.format("errors.rule_syntax", &[text]),
+        RuleError::DuplicatedSection(text) => locale.format("errors.rule_duplicated", &[text]),
+        RuleError::InvalidCount(c, text) => locale.format("errors.rule_count", &[c, text]),
+        RuleError::MissingSection(text) => locale.format("errors.rule_missing", &[text]),
+        RuleError::BirthOnZero => locale.text("errors.rule_b0").to_string(),
+    }
+}
+
+/// 用当前语言描述图案文件的错误
+pub fn pattern_error_message(e: &PatternError, locale: &LocaleManager) -> String {
+    match e {
+        PatternError::Io(e) => locale.format("errors.pattern_io", &[e]),
+        PatternError::Unsupported(path) => locale.format("errors.pattern_unsupported", &[&path.display()]),
+        PatternError::RunLengthOverflow => locale.text("errors.pattern_overflow").to_string(),
+        PatternError::InvalidRleChar(c) => locale.format("errors.pattern_rle_char", &[c]),
+        PatternError::InvalidHeader(line) => locale.format("errors.pattern_header", &[line]),
+        PatternError::InvalidPlaintextChar(c) => locale.format("errors.pattern_plaintext_char", &[c]),
+    }
+}
+
+/// 书签: 名称, 视口中心与缩放
+type Bookmark = (String, (f64, f64), f64);
+
+/// 命令作用的对象, 返回的字符串显示在控制台中
+/// 命令作用于关联相机所显示的宇宙, 演化命令作用于所有宇宙
+/// 控制台之外产生的消息, 例如快捷键操作失败的原因, 每个控制台都会显示
+pub type RcNotices = Rc<RefCell<Vec<String>>>;
+
+pub struct CommandContext {
+    universe: RcUniverse,
+    universes: RcUniverseSet,
+    viewport: RcViewport,
+    theme_manager: RcThemeManager,
+    locale: RcLocaleManager,
+    notices: RcNotices,
+    bookmarks: RefCell<Vec<Bookmark>>,
+}
+
+impl CommandContext {
+    pub fn new(universe: RcUniverse, universes: RcUniverseSet, viewport: RcViewport,
+        theme_manager: RcThemeManager, locale: RcLocaleManager, notices: RcNotices) -> Self
+    {
+        CommandContext {
+            universe,
+            universes,
+            viewport,
+            theme_manager,
+            locale,
+            notices,
+            bookmarks: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// 第`seen`条之后的消息
+    pub fn notices_since(&self, seen: usize) -> Vec<String> {
+        self.notices.borrow().iter().skip(seen).cloned().collect()
+    }
+
+    pub fn parse(&self, line: &str) -> error::Result<Command> {
+        Command::parse(line, &self.locale.borrow())
+    }
+
+    pub fn execute(&self, command: Command) -> error::Result<String> {
+        self.universes.borrow_mut().set_active(&self.universe);
+        let locale = self.locale.borrow();
+        let message = match command {
+            Command::Rule(None) => locale.format("console.rule_is", &[&self.universe.borrow().rule()]),
+            Command::Rule(Some(rule)) => {
+                self.universe.borrow_mut().set_rule(rule);
+                locale.format("console.rule_set", &[&rule])
+            },
+            Command::Step(count) => {
+                // 所有宇宙一起推进, 分散到之后的帧中执行
+                let mut universes = self.universes.borrow_mut();
+                universes.queue_steps(count);
+                locale.format("console.stepping",
+                    &[&universes.pending_steps(), &self.universe.borrow().generation()])
+            },
+            Command::Goto(x, y) => {
+                let scale = self.viewport.borrow().target().1;
+                self.animate_to((x as f64 + 0.5, y as f64 + 0.5), scale);
+                locale.format("console.centered", &[&x, &y])
+            },
+            Command::Bookmark(None) => {
+                let bookmarks = self.bookmarks.borrow();
+                if bookmarks.is_empty() {
+                    locale.text("console.no_bookmarks").to_string()
+                } else {
+                    let names: Vec<_> = bookmarks.iter().map(|(name, _, _)| name.as_str()).collect();
+                    locale.format("console.bookmarks", &[&names.join(", ")])
+                }
+            },
+            Command::Bookmark(Some(name)) => {
+                let (center, scale) = self.viewport.borrow().target();
+                let mut bookmarks = self.bookmarks.borrow_mut();
+                bookmarks.retain(|(known, _, _)| *known != name);
+                bookmarks.push((name.clone(), center, scale));
+                locale.format("console.bookmark_saved", &[&name, &center.0.floor(), &center.1.floor()])
+            },
+            Command::GotoBookmark(name) => {
+                let (center, scale) = self.bookmarks
+                    .borrow()
+                    .iter()
+                    .find(|(known, _, _)| *known == name)
+                    .map(|(_, center, scale)| (*center, *scale))
+                    .ok_or_else(|| locale.format("console.unknown_bookmark", &[&name]))?;
+                self.animate_to(center, scale);
+                locale.format("console.moving_to", &[&name])
+            },
+            Command::Fit => {
+                let bounds = self.universe.borrow().grid().bounding_box();
+                let duration = self.theme_manager.borrow().camera_transition();
+                self.viewport.borrow_mut().zoom_to_fit(bounds, duration);
+                match bounds {
+                    Some(_) => locale.format("console.fit",
+                        &[&self.universe.borrow().grid().alive_cells().len()]),
+                    None => locale.text("console.fit_empty").to_string(),
+                }
+            },
+            Command::Note(None) => {
+                let universe = self.universe.borrow();
+                let annotations = universe.annotations();
+                if annotations.is_empty() {
+                    locale.text("console.no_notes").to_string()
+                } else {
+                    let list: Vec<_> = annotations.iter().map(|annotation| annotation.to_string()).collect();
+                    locale.format("console.notes", &[&annotations.len(), &list.join("; ")])
+                }
+            },
+            Command::Note(Some(annotation)) => {
+                let message = locale.format("console.note_added", &[&annotation]);
+                self.universe.borrow_mut().add_annotation(annotation);
+                message
+            },
+            Command::EraseNotes(cell) => {
+                let mut universe = self.universe.borrow_mut();
+                let erased = match cell {
+                    Some((x, y)) => universe.remove_annotations((x, y, x, y)),
+                    None => universe.remove_annotations((i32::MIN, i32::MIN, i32::MAX, i32::MAX)),
+                };
+                locale.format("console.notes_erased", &[&erased])
+            },
+            Command::Load(path) => self.load(path, &locale)?,
+            Command::Save(path) => self.save(path, &locale)?,
+            Command::Randomize(density) => {
+                let bounds = self.visible_bounds(&locale)?;
+                let population = self.universe.borrow_mut().randomize(bounds, density);
+                locale.format("console.randomized", &[&population])
+            },
+            Command::Sync => {
+                let universes = self.universes.borrow();
+                if universes.count() < 2 {
+                    return Err(locale.text("console.single_universe").into());
+                }
+                let synced = universes.sync_from(&self.universe)?;
+                locale.format("console.synced", &[&self.universe.borrow().grid().alive_cells().len(), &synced])
+            },
+            Command::Theme(None) => {
+                let theme_manager = self.theme_manager.borrow();
+                locale.format("console.theme_is",
+                    &[&theme_manager.name(), &theme_manager.available_names().join(", ")])
+            },
+            Command::Theme(Some(name)) => {
+                self.theme_manager.borrow_mut().select(&name)?;
+                locale.format("console.theme_set", &[&name])
+            },
+            Command::Language(None) => locale.format("console.language_is",
+                &[&locale.id(), &locale.available_names().join(", ")]),
+            Command::Language(Some(id)) => {
+                // 切换后的提示使用新的语言
+                drop(locale);
+                let mut locale = self.locale.borrow_mut();
+                locale.select(&id)?;
+                return Ok(locale.format("console.language_set", &[&id]));
+            },
+            Command::Clear => {
+                let cleared = self.universe.borrow_mut().clear();
+                locale.format("console.cleared", &[&cleared])
+            },
+            Command::Help => locale.text("console.help").to_string(),
+        };
+        Ok(message)
+    }
+
+    /// 按主题中的过渡时间平滑地移动相机
+    fn animate_to(&self, center: (f64, f64), scale: f64) {
+        let duration = self.theme_manager.borrow().camera_transition();
+        self.viewport.borrow_mut().animate_to(center, scale, duration);
+    }
+
+    /// 替换当前的所有cell, 图案居中放在原点, 文件指定的规则同时生效
+    /// 标注不在撤销记录中, 文件中的标注追加到已有的标注之后, 撤销时不会丢失原来的标注
+    fn load(&self, path: PathBuf, locale: &LocaleManager) -> error::Result<String> {
+        let file = pattern_io::load(&path).map_err(|e| pattern_error_message(&e, locale))?;
+        let pattern = &file.pattern;
+        let origin = (-pattern.width() / 2, -pattern.height() / 2);
+        let mut universe = self.universe.borrow_mut();
+        universe.grouped(|universe| {
+            universe.clear();
+            universe.stamp(pattern, origin);
+        });
+        file.annotations
+            .iter()
+            .for_each(|annotation| universe.add_annotation(annotation.translated(origin)));
+        if let Some(rule) = file.rule {
+            universe.set_rule(rule);
+        }
+        self.viewport.borrow_mut().set_center((0., 0.));
+        let name = file.name.as_deref().unwrap_or_else(|| locale.text("console.pattern"));
+        Ok(locale.format("console.loaded", &[&name, &pattern.width(), &pattern.height(),
+            &pattern.population(), &universe.rule()]))
+    }
+
+    /// 保存所有活细胞的包围盒、当前规则以及所有标注, 文件名作为图案名
+    fn save(&self, path: PathBuf, locale: &LocaleManager) -> error::Result<String> {
+        let universe = self.universe.borrow();
+        let bounds = universe
+            .grid()
+            .bounding_box()
+            .ok_or_else(|| locale.text("console.nothing_to_save"))?;
+        let file = pattern_io::PatternFile {
+            name: path.file_stem().map(|stem| stem.to_string_lossy().into_owned()),
+            rule: Some(universe.rule()),
+            pattern: universe.extract(bounds),
+            annotations: universe
+                .annotations()
+                .iter()
+                .map(|annotation| annotation.translated((-bounds.0, -bounds.1)))
+                .collect(),
+        };
+        pattern_io::save(&path, &file).map_err(|e| pattern_error_message(&e, locale))?;
+        Ok(locale.format("console.saved", &[&file.pattern.population(), &path.display()]))
+    }
+
+    /// 相机可见范围与网格有效范围的交集
+    fn visible_bounds(&self, locale: &LocaleManager) -> error::Result<(i32, i32, i32, i32)> {
+        let (min_x, min_y, max_x, max_y) = self.viewport.borrow().visible_cells();
+        let bound = self.universe.borrow().grid().upper_scale() - 1;
+        let (min_x, min_y) = (min_x.max(-bound), min_y.max(-bound));
+        let (max_x, max_y) = (max_x.min(bound), max_y.min(bound));
+        if min_x > max_x || min_y > max_y {
+            return Err(locale.text("console.outside_grid").into());
+        }
+        let area = (max_x - min_x + 1) as i64 * (max_y - min_y + 1) as i64;
+        if area > MAX_RANDOM_CELLS {
+            return Err(locale.format("console.area_too_large", &[&area]).into());
+        }
+        Ok((min_x, min_y, max_x, max_y))
+    }
+}
+
+/// 补全第一个词为命令名, `load`与`save`之后补全文件路径
+/// 返回补全后的输入, 以及有多个候选时的候选列表
+pub fn complete(input: &str) -> (String, Vec<String>) {
+    match input.split_once(' ') {
+        None => {
+            let candidates: Vec<_> = COMMAND_NAMES
+                .iter()
+                .filter(|name| name.starts_with(input))
+                .map(|name| name.to_string())
+                .collect();
+            match candidates.as_slice() {
+                [single] => (format!("{} ", single), Vec::new()),
+                _ => (common_prefix(&candidates).unwrap_or_else(|| input.to_string()), candidates),
+            }
+        },
+        Some((name @ ("load" | "save"), path)) => {
+            let path = path.trim_start();
+            let candidates = complete_path(path);
+            let completed = match candidates.as_slice() {
+                [single] => single.clone(),
+                _ => common_prefix(&candidates).unwrap_or_else(|| path.to_string()),
+            };
+            (format!("{} {}", name, completed), if candidates.len() > 1 { candidates } else { Vec::new() })
+        },
+        Some(_) => (input.to_string(), Vec::new()),
+    }
+}
+
+fn complete_path(prefix: &str) -> Vec<String> {
+    let (dir, file_prefix) = match prefix.rfind(['/', '\\']) {
+        Some(idx) => (&prefix[..=idx], &prefix[idx + 1..]),
+        None => ("", prefix),
+    };
+    let Ok(entries) = std::fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(file_prefix) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+fn common_prefix(candidates: &[String]) -> Option<String> {
+    let first = candidates.first()?;
+    let len = candidates
+        .iter()
+        .map(|candidate| {
+            first.chars()
+                .zip(candidate.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a.len_utf8())
+                .sum::<usize>()
+        })
+        .min()
+        .unwrap_or(0);
+    Some(first[..len].to_string())
+}
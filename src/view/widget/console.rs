@@ -0,0 +1,277 @@
+use sdl2::{
+    render::{
+        self,
+        BlendMode,
+        Texture,
+    },
+    rect::Rect,
+    keyboard::Keycode,
+};
+use std::collections::VecDeque;
+use crate::utils::error;
+use super::{
+    widget_base,
+    command::{
+        self,
+        CommandContext,
+    },
+    event::{
+        WidgetEvent,
+        EventResult,
+    },
+};
+
+static MAX_OUTPUT_LINES: usize = 256;
+static MAX_HISTORY: usize = 128;
+static PROMPT: &str = "> ";
+static PADDING: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Echo,
+    Output,
+    Error,
+}
+
+/// 输入命令的控制台, 支持历史记录(上下方向键)与Tab补全, 命令的结果或错误显示在输入行上方
+pub struct Console {
+    widget_base: widget_base::WidgetBase,
+    context: CommandContext,
+    texture_cache: Option<Texture>,
+    input: String,
+    /// 光标在`input`中的字节位置
+    cursor: usize,
+    history: Vec<String>,
+    /// 正在浏览的历史记录, None表示正在编辑新的输入
+    history_index: Option<usize>,
+    output: VecDeque<(LineKind, String)>,
+    /// 已经显示过的外部消息数
+    seen_notices: usize,
+    dirty: bool,
+}
+
+impl Console {
+    pub fn new(widget_base: widget_base::WidgetBase, context: CommandContext) -> Self {
+        Console {
+            widget_base,
+            context,
+            texture_cache: None,
+            input: String::new(),
+            cursor: 0,
+            history: Vec::new(),
+            history_index: None,
+            output: VecDeque::new(),
+            seen_notices: 0,
+            dirty: true,
+        }
+    }
+
+    fn print(&mut self, kind: LineKind, text: String) {
+        self.output.extend(text.lines().map(|line| (kind, line.to_string())));
+        while self.output.len() > MAX_OUTPUT_LINES {
+            self.output.pop_front();
+        }
+    }
+
+    fn set_input(&mut self, input: String) {
+        self.input = input;
+        self.cursor = self.input.len();
+    }
+
+    fn submit(&mut self) {
+        let line = std::mem::take(&mut self.input);
+        self.cursor = 0;
+        self.history_index = None;
+        if line.trim().is_empty() {
+            return;
+        }
+        self.print(LineKind::Echo, format!("{}{}", PROMPT, line));
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        match self.context.parse(&line).and_then(|command| self.context.execute(command)) {
+            Ok(message) => self.print(LineKind::Output, message),
+            Err(e) => self.print(LineKind::Error, e.to_string()),
+        }
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        let index = match (self.history_index, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => return,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index + 1 < self.history.len() => Some(index + 1),
+            (Some(_), false) => None,
+        };
+        self.history_index = index;
+        let input = index
+            .and_then(|index| self.history.get(index).cloned())
+            .unwrap_or_default();
+        self.set_input(input);
+    }
+
+    fn complete(&mut self) {
+        let (completed, candidates) = command::complete(&self.input);
+        if !candidates.is_empty() {
+            self.print(LineKind::Output, candidates.join("  "));
+        }
+        self.set_input(completed);
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.input[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(idx, _)| idx)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.input[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    fn handle_key(&mut self, keycode: Keycode) -> EventResult {
+        match keycode {
+            Keycode::Return | Keycode::KpEnter => self.submit(),
+            Keycode::Tab => self.complete(),
+            Keycode::Up => self.browse_history(true),
+            Keycode::Down => self.browse_history(false),
+            Keycode::Left => self.cursor = self.prev_boundary(),
+            Keycode::Right => self.cursor = self.next_boundary(),
+            Keycode::Home => self.cursor = 0,
+            Keycode::End => self.cursor = self.input.len(),
+            Keycode::Backspace if self.cursor > 0 => {
+                let start = self.prev_boundary();
+                self.input.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            },
+            Keycode::Delete if self.cursor < self.input.len() => {
+                let end = self.next_boundary();
+                self.input.replace_range(self.cursor..end, "");
+            },
+            _ => return EventResult::Ignored,
+        }
+        self.dirty = true;
+        EventResult::Handled
+    }
+
+    fn fill_base_element(&self, texture: &mut Texture, width: u32, height: u32) -> error::Result<()> {
+        let (bg_color, text_color, echo_color, error_color) = {
+            let theme_manager = self.widget_base.theme_manager.borrow();
+            (theme_manager.console_bg_color(), theme_manager.text_color(),
+                theme_manager.console_echo_color(), theme_manager.console_error_color())
+        };
+        let text_renderer = self.widget_base.text_renderer.borrow();
+        let line_height = (text_renderer.line_height() as i32).max(1);
+        let input_y = height as i32 - PADDING - line_height;
+        let (cursor_x, _) = text_renderer.size_of(&format!("{}{}", PROMPT, &self.input[..self.cursor]));
+        // 过长的输出折成多行, 只处理能显示出来的部分
+        let max_width = (width as i32 - 2 * PADDING).max(1) as u32;
+        let visible = (input_y / line_height) as usize;
+        let mut rows = Vec::new();
+        for (kind, line) in self.output.iter().rev() {
+            if rows.len() >= visible {
+                break;
+            }
+            let wrapped = text_renderer.wrap(line, max_width);
+            rows.extend(wrapped.into_iter().rev().map(|row| (kind, row)));
+        }
+        drop(text_renderer);
+
+        let mut result = Ok(());
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_draw_color(bg_color);
+            canvas.clear();
+
+            let input = format!("{}{}", PROMPT, self.input);
+            if let Err(e) = self.widget_base.draw_text(canvas, &input, (PADDING, input_y), text_color) {
+                result = Err(e);
+                return;
+            }
+            canvas.set_draw_color(text_color);
+            let cursor = Rect::new(PADDING + cursor_x as i32, input_y, 1, line_height as u32);
+            if let Err(e) = canvas.fill_rect(cursor) {
+                result = Err(e.into());
+                return;
+            }
+
+            // 输出自下而上排列, 超出顶部的行不再绘制
+            let mut y = input_y - line_height;
+            for (kind, line) in rows.iter() {
+                if y + line_height <= 0 {
+                    break;
+                }
+                let color = match kind {
+                    LineKind::Echo => echo_color,
+                    LineKind::Output => text_color,
+                    LineKind::Error => error_color,
+                };
+                if let Err(e) = self.widget_base.draw_text(canvas, line, (PADDING, y), color) {
+                    result = Err(e);
+                    return;
+                }
+                y -= line_height;
+            }
+        })?;
+        result?;
+        Ok(())
+    }
+}
+
+impl widget_base::Widget for Console {
+    fn widget_base(&self) -> &widget_base::WidgetBase {
+        &self.widget_base
+    }
+
+    fn rendering_on_texture(&mut self, width: u32, height: u32)
+        -> error::Result<&render::Texture>
+    {
+        widget_base::drop_stale_cache(&mut self.texture_cache, width, height);
+        let mut texture = match self.texture_cache.take() {
+            Some(texture) => texture,
+            None => {
+                self.dirty = true;
+                let mut texture = self.create_new_texture(width, height)?;
+                // 半透明背景下仍能看到下方的网格
+                texture.set_blend_mode(BlendMode::Blend);
+                texture
+            },
+        };
+        let notices = self.context.notices_since(self.seen_notices);
+        if !notices.is_empty() {
+            self.seen_notices += notices.len();
+            notices
+                .into_iter()
+                .for_each(|notice| self.print(LineKind::Error, notice));
+            self.dirty = true;
+        }
+        if self.dirty {
+            self.fill_base_element(&mut texture, width, height)?;
+            self.dirty = false;
+        }
+
+        Ok(self.texture_cache.insert(texture))
+    }
+
+    fn invalidate(&mut self) {
+        widget_base::destroy_cache(&mut self.texture_cache);
+    }
+
+    fn handle_event(&mut self, event: &WidgetEvent) -> EventResult {
+        match event {
+            WidgetEvent::TextInput { text } => {
+                self.input.insert_str(self.cursor, text);
+                self.cursor += text.len();
+                self.dirty = true;
+                EventResult::Handled
+            },
+            WidgetEvent::KeyDown { keycode, .. } => self.handle_key(*keycode),
+            _ => EventResult::Ignored,
+        }
+    }
+}
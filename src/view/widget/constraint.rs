@@ -0,0 +1,252 @@
+/// 子项在布局方向上的尺寸
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    /// 固定的像素数
+    Pixels(u32),
+    /// 可用长度(扣除内边距与间隔后)的比例
+    Fraction(f32),
+    /// 按权重分配其他子项剩下的长度
+    Fill(f32),
+}
+
+/// 子项的尺寸及其上下限, 上下限为像素
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeHint {
+    size: Size,
+    min: u32,
+    max: Option<u32>,
+}
+
+impl SizeHint {
+    pub fn pixels(pixels: u32) -> Self {
+        SizeHint { size: Size::Pixels(pixels), min: 0, max: None }
+    }
+
+    pub fn fraction(fraction: f32) -> Self {
+        SizeHint { size: Size::Fraction(fraction.max(0.)), min: 0, max: None }
+    }
+
+    pub fn fill() -> Self {
+        SizeHint::weighted(1.)
+    }
+
+    pub fn weighted(weight: f32) -> Self {
+        SizeHint { size: Size::Fill(weight.max(0.)), min: 0, max: None }
+    }
+
+    pub fn min(mut self, min: u32) -> Self {
+        self.min = min;
+        self
+    }
+
+    pub fn max(mut self, max: u32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        let min = self.min as f64;
+        let max = self.max.map_or(f64::INFINITY, |max| (max as f64).max(min));
+        (min, max)
+    }
+}
+
+/// 兼容原来的写法: 比例, 或者None表示平分剩余的部分
+impl From<Option<f32>> for SizeHint {
+    fn from(fraction: Option<f32>) -> Self {
+        fraction.map_or_else(SizeHint::fill, SizeHint::fraction)
+    }
+}
+
+/// 计算各子项的长度, 结果之和恰好等于`length`
+/// 只有全部子项都是固定像素时, 末尾才会留下空白
+pub fn solve(hints: &[SizeHint], length: u32) -> Vec<u32> {
+    let total = length as f64;
+    let mut sizes: Vec<f64> = hints
+        .iter()
+        .map(|hint| {
+            let (min, max) = hint.bounds();
+            let size = match hint.size {
+                Size::Pixels(pixels) => pixels as f64,
+                Size::Fraction(fraction) => fraction as f64 * total,
+                Size::Fill(_) => 0.,
+            };
+            size.clamp(min, max)
+        })
+        .collect();
+
+    let remaining = total - sizes.iter().sum::<f64>();
+    if remaining > 0. {
+        // 优先分给Fill, 没有Fill时按比例分给Fraction
+        let fill: Vec<f64> = hints
+            .iter()
+            .map(|hint| match hint.size {
+                Size::Fill(weight) => weight as f64,
+                _ => 0.,
+            })
+            .collect();
+        let weights = if fill.iter().any(|weight| *weight > 0.) {
+            fill
+        } else {
+            hints
+                .iter()
+                .map(|hint| match hint.size {
+                    Size::Fraction(fraction) => fraction as f64,
+                    _ => 0.,
+                })
+                .collect()
+        };
+        let remaining = distribute(&mut sizes, remaining, &weights, hints, true);
+        // 所有上限都已达到时, 最后一个可伸缩的子项超出上限以铺满布局
+        if remaining > 0.
+            && let Some(last) = hints.iter().rposition(|hint| !matches!(hint.size, Size::Pixels(_))) {
+            sizes[last] += remaining;
+        }
+    } else if remaining < 0. {
+        // 先缩小可伸缩的子项, 再缩小固定像素的子项, 最后忽略下限按比例缩小
+        let flexible: Vec<f64> = hints
+            .iter()
+            .zip(sizes.iter())
+            .map(|(hint, size)| match hint.size {
+                Size::Pixels(_) => 0.,
+                _ => *size,
+            })
+            .collect();
+        let remaining = distribute(&mut sizes, remaining, &flexible, hints, true);
+        let weights = sizes.clone();
+        let remaining = distribute(&mut sizes, remaining, &weights, hints, true);
+        let weights = sizes.clone();
+        distribute(&mut sizes, remaining, &weights, hints, false);
+    }
+    round_exactly(&sizes, length)
+}
+
+/// 按权重把`delta`分给各子项, 超出上下限的子项固定在边界上后重新分配
+/// 返回未能分配的部分
+fn distribute(sizes: &mut [f64], mut delta: f64, weights: &[f64], hints: &[SizeHint],
+    respect_bounds: bool) -> f64
+{
+    let mut active: Vec<usize> = (0..sizes.len()).filter(|idx| weights[*idx] > 0.).collect();
+    while delta.abs() > f64::EPSILON && !active.is_empty() {
+        let total_weight: f64 = active.iter().map(|idx| weights[*idx]).sum();
+        let bounds = |idx: usize| if respect_bounds {
+            hints[idx].bounds()
+        } else {
+            (0., f64::INFINITY)
+        };
+        let violated: Vec<usize> = active
+            .iter()
+            .copied()
+            .filter(|idx| {
+                let (min, max) = bounds(*idx);
+                let target = sizes[*idx] + delta * weights[*idx] / total_weight;
+                target < min || target > max
+            })
+            .collect();
+        if violated.is_empty() {
+            for idx in active.iter() {
+                sizes[*idx] += delta * weights[*idx] / total_weight;
+            }
+            return 0.;
+        }
+        for idx in violated {
+            let (min, max) = bounds(idx);
+            let clamped = if delta > 0. { max } else { min };
+            delta -= clamped - sizes[idx];
+            sizes[idx] = clamped;
+            active.retain(|active| *active != idx);
+        }
+    }
+    delta
+}
+
+/// 取整时把舍去的像素按小数部分从大到小补回, 保证总和不变
+fn round_exactly(sizes: &[f64], length: u32) -> Vec<u32> {
+    let sizes: Vec<f64> = sizes.iter().map(|size| size.max(0.)).collect();
+    let target = (sizes.iter().sum::<f64>().round() as u32).min(length);
+    let mut rounded: Vec<u32> = sizes.iter().map(|size| size.floor() as u32).collect();
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| (sizes[*b] - sizes[*b].floor()).total_cmp(&(sizes[*a] - sizes[*a].floor())));
+    let missing = target.saturating_sub(rounded.iter().sum());
+    for idx in order.into_iter().cycle().take(missing as usize) {
+        rounded[idx] += 1;
+    }
+    rounded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_shares_the_rest_by_weight() {
+        let hints = [SizeHint::pixels(100), SizeHint::fill(), SizeHint::weighted(3.)];
+        assert_eq!(solve(&hints, 500), vec![100, 100, 300]);
+    }
+
+    #[test]
+    fn fractions_grow_when_there_is_no_fill() {
+        let hints = [SizeHint::fraction(0.25), SizeHint::fraction(0.25)];
+        assert_eq!(solve(&hints, 400), vec![200, 200]);
+    }
+
+    #[test]
+    fn rounding_keeps_the_total() {
+        let hints = [SizeHint::fill(), SizeHint::fill(), SizeHint::fill()];
+        let sizes = solve(&hints, 100);
+        assert_eq!(sizes.iter().sum::<u32>(), 100);
+        assert!(sizes.iter().all(|size| *size == 33 || *size == 34));
+    }
+
+    #[test]
+    fn max_passes_the_rest_to_other_children() {
+        let hints = [SizeHint::fill().max(100), SizeHint::fill()];
+        assert_eq!(solve(&hints, 500), vec![100, 400]);
+    }
+
+    #[test]
+    fn min_is_kept_before_sharing() {
+        let hints = [SizeHint::fill().min(300), SizeHint::fill()];
+        assert_eq!(solve(&hints, 400), vec![350, 50]);
+    }
+
+    #[test]
+    fn last_flexible_child_exceeds_max_to_fill() {
+        let hints = [SizeHint::fill().max(100), SizeHint::pixels(50)];
+        assert_eq!(solve(&hints, 300), vec![250, 50]);
+    }
+
+    #[test]
+    fn shrink_takes_flexible_space_before_pixels() {
+        let hints = [SizeHint::pixels(300), SizeHint::fill().min(50)];
+        assert_eq!(solve(&hints, 200), vec![150, 50]);
+    }
+
+    #[test]
+    fn shrink_ignores_min_when_nothing_else_fits() {
+        let hints = [SizeHint::fill().min(300), SizeHint::fill().min(100)];
+        assert_eq!(solve(&hints, 200), vec![150, 50]);
+    }
+
+    #[test]
+    fn all_pixels_leave_a_blank_tail() {
+        let hints = [SizeHint::pixels(100), SizeHint::pixels(50)];
+        assert_eq!(solve(&hints, 400), vec![100, 50]);
+    }
+
+    #[test]
+    fn all_pixels_shrink_proportionally() {
+        let hints = [SizeHint::pixels(300), SizeHint::pixels(100)];
+        assert_eq!(solve(&hints, 200), vec![150, 50]);
+    }
+
+    #[test]
+    fn zero_length_gives_zero_sizes() {
+        let hints = [SizeHint::fill(), SizeHint::pixels(10)];
+        assert_eq!(solve(&hints, 0), vec![0, 0]);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    model::shape,
+    view::keymap::Action,
+};
+
+/// 相机中的编辑工具, 均在世界坐标下工作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditTool {
+    Pan,
+    Pencil,
+    Eraser,
+    Toggle,
+    Line,
+    Rect,
+    FilledRect,
+    Fill,
+    Select,
+}
+
+impl EditTool {
+    pub fn from_action(action: Action) -> Option<Self> {
+        let tool = match action {
+            Action::ToolPan => EditTool::Pan,
+            Action::ToolPencil => EditTool::Pencil,
+            Action::ToolEraser => EditTool::Eraser,
+            Action::ToolToggle => EditTool::Toggle,
+            Action::ToolLine => EditTool::Line,
+            Action::ToolRect => EditTool::Rect,
+            Action::ToolFilledRect => EditTool::FilledRect,
+            Action::ToolFill => EditTool::Fill,
+            Action::ToolSelect => EditTool::Select,
+            _ => return None,
+        };
+        Some(tool)
+    }
+
+    /// 拖拽过程中随鼠标逐点绘制
+    pub fn is_freehand(&self) -> bool {
+        matches!(self, EditTool::Pencil | EditTool::Eraser)
+    }
+
+    /// 拖拽时只显示预览, 松开鼠标后一次性提交
+    pub fn is_shape(&self) -> bool {
+        matches!(self, EditTool::Line | EditTool::Rect | EditTool::FilledRect)
+    }
+
+    pub fn shape_cells(&self, start: (i32, i32), end: (i32, i32)) -> Vec<(i32, i32)> {
+        match self {
+            EditTool::Line => shape::line(start, end),
+            EditTool::Rect => shape::hollow_rect(start, end),
+            EditTool::FilledRect => shape::filled_rect(start, end),
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use sdl2::{
+    event::{
+        Event,
+        WindowEvent,
+    },
+    keyboard::{
+        Keycode,
+        Mod,
+    },
+    mouse::MouseButton,
+    rect,
+};
+
+/// 分发给widget的事件, 鼠标坐标在分发时会被转换为widget的局部坐标
+#[derive(Debug, Clone)]
+pub enum WidgetEvent {
+    MouseButtonDown { x: i32, y: i32, button: MouseButton, clicks: u8 },
+    MouseButtonUp { x: i32, y: i32, button: MouseButton },
+    MouseMotion { x: i32, y: i32, xrel: i32, yrel: i32 },
+    MouseWheel { x: i32, y: i32, scroll_x: f32, scroll_y: f32 },
+    MouseLeave,
+    KeyDown { keycode: Keycode, keymod: Mod, repeat: bool },
+    KeyUp,
+    TextInput { text: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventResult {
+    Ignored,
+    Handled,
+    /// 已处理, 并在按下的键松开前捕获之后的鼠标事件(用于拖拽)
+    Capture,
+    /// 已处理, 作为覆盖层显示时请求关闭
+    Close,
+}
+
+impl EventResult {
+    pub fn is_handled(&self) -> bool {
+        *self != EventResult::Ignored
+    }
+}
+
+impl WidgetEvent {
+    pub fn from_sdl(event: &Event) -> Option<Self> {
+        let widget_event = match event {
+            Event::MouseButtonDown { x, y, mouse_btn, clicks, .. } => {
+                WidgetEvent::MouseButtonDown { x: *x, y: *y, button: *mouse_btn, clicks: *clicks }
+            },
+            Event::MouseButtonUp { x, y, mouse_btn, .. } => {
+                WidgetEvent::MouseButtonUp { x: *x, y: *y, button: *mouse_btn }
+            },
+            Event::MouseMotion { x, y, xrel, yrel, .. } => {
+                WidgetEvent::MouseMotion { x: *x, y: *y, xrel: *xrel, yrel: *yrel }
+            },
+            Event::MouseWheel { mouse_x, mouse_y, precise_x, precise_y, .. } => {
+                WidgetEvent::MouseWheel {
+                    x: *mouse_x,
+                    y: *mouse_y,
+                    scroll_x: *precise_x,
+                    scroll_y: *precise_y,
+                }
+            },
+            Event::Window { win_event: WindowEvent::Leave, .. } => WidgetEvent::MouseLeave,
+            Event::KeyDown { keycode: Some(keycode), keymod, repeat, .. } => {
+                WidgetEvent::KeyDown { keycode: *keycode, keymod: *keymod, repeat: *repeat }
+            },
+            Event::KeyUp { keycode: Some(_), .. } => WidgetEvent::KeyUp,
+            Event::TextInput { text, .. } => WidgetEvent::TextInput { text: text.clone() },
+            _ => return None,
+        };
+        Some(widget_event)
+    }
+
+    pub fn position(&self) -> Option<(i32, i32)> {
+        match self {
+            WidgetEvent::MouseButtonDown { x, y, .. }
+            | WidgetEvent::MouseButtonUp { x, y, .. }
+            | WidgetEvent::MouseMotion { x, y, .. }
+            | WidgetEvent::MouseWheel { x, y, .. } => Some((*x, *y)),
+            _ => None,
+        }
+    }
+
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self,
+            WidgetEvent::KeyDown { .. } | WidgetEvent::KeyUp | WidgetEvent::TextInput { .. })
+    }
+
+    /// 将窗口坐标换算为绘制区域的像素坐标, 高DPI下比例大于1
+    pub fn scaled(&self, (scale_x, scale_y): (f64, f64)) -> Self {
+        let mut event = self.clone();
+        let scale = |v: &mut i32, factor: f64| *v = (*v as f64 * factor).round() as i32;
+        match &mut event {
+            WidgetEvent::MouseMotion { x, y, xrel, yrel, .. } => {
+                scale(x, scale_x);
+                scale(y, scale_y);
+                scale(xrel, scale_x);
+                scale(yrel, scale_y);
+            },
+            WidgetEvent::MouseButtonDown { x, y, .. }
+            | WidgetEvent::MouseButtonUp { x, y, .. }
+            | WidgetEvent::MouseWheel { x, y, .. } => {
+                scale(x, scale_x);
+                scale(y, scale_y);
+            },
+            _ => {},
+        }
+        event
+    }
+
+    /// 将鼠标坐标转换为以`rect`左上角为原点的局部坐标
+    pub fn to_local(&self, rect: &rect::Rect) -> Self {
+        let mut event = self.clone();
+        match &mut event {
+            WidgetEvent::MouseButtonDown { x, y, .. }
+            | WidgetEvent::MouseButtonUp { x, y, .. }
+            | WidgetEvent::MouseMotion { x, y, .. }
+            | WidgetEvent::MouseWheel { x, y, .. } => {
+                *x -= rect.x();
+                *y -= rect.y();
+            },
+            _ => {},
+        }
+        event
+    }
+}
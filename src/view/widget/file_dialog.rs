@@ -0,0 +1,464 @@
+use sdl2::{
+    render::{
+        self,
+        Texture,
+    },
+    rect::Rect,
+    keyboard::Keycode,
+    mouse::MouseButton,
+};
+use std::{
+    fs,
+    path::PathBuf,
+};
+use crate::{
+    utils::error,
+    model::pattern_io::{
+        self,
+        PatternFile,
+    },
+};
+use super::{
+    widget_base,
+    pattern_browser::thumbnail_rects,
+    command::{
+        self,
+        Command,
+        CommandContext,
+    },
+    event::{
+        WidgetEvent,
+        EventResult,
+    },
+};
+
+static PADDING: i32 = 6;
+static LIST_WIDTH: f64 = 0.5;
+static PAGE_ROWS: usize = 10;
+static DEFAULT_EXTENSION: &str = "rle";
+static PARENT_DIR: &str = "..";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogMode {
+    Open,
+    Save,
+}
+
+struct Entry {
+    name: String,
+    is_dir: bool,
+}
+
+/// 各区域的位置, 随纹理尺寸变化
+struct Geometry {
+    list: Rect,
+    preview: Rect,
+    row_height: i32,
+    info_y: i32,
+    input_y: i32,
+    status_y: i32,
+}
+
+/// 打开/保存图案的对话框, 只列出目录与支持的图案文件, 右侧预览选中的图案
+/// 打开时替换当前的所有cell, 保存时写入所有活细胞
+pub struct FileDialog {
+    widget_base: widget_base::WidgetBase,
+    context: CommandContext,
+    mode: DialogMode,
+    texture_cache: Option<Texture>,
+    size: (u32, u32),
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: Option<usize>,
+    /// 列表中第一个可见的行
+    scroll: usize,
+    file_name: String,
+    preview: Option<Result<PatternFile, String>>,
+    /// (消息, 是否为错误)
+    status: Option<(String, bool)>,
+    /// 已提示过会被覆盖的文件, 再次确认同一个文件时才写入
+    overwrite: Option<PathBuf>,
+    dirty: bool,
+}
+
+impl FileDialog {
+    pub fn new(widget_base: widget_base::WidgetBase, context: CommandContext, mode: DialogMode)
+        -> Self
+    {
+        let mut dialog = FileDialog {
+            widget_base,
+            context,
+            mode,
+            texture_cache: None,
+            size: (1, 1),
+            dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            entries: Vec::new(),
+            selected: None,
+            scroll: 0,
+            file_name: String::new(),
+            preview: None,
+            status: None,
+            overwrite: None,
+            dirty: true,
+        };
+        dialog.refresh();
+        dialog
+    }
+
+    /// 重新读取当前目录, 目录在前, 隐藏文件不显示
+    fn refresh(&mut self) {
+        self.entries.clear();
+        self.selected = None;
+        self.scroll = 0;
+        self.preview = None;
+        self.dirty = true;
+        if self.dir.parent().is_some() {
+            self.entries.push(Entry { name: PARENT_DIR.to_string(), is_dir: true });
+        }
+        let read = match fs::read_dir(&self.dir) {
+            Ok(read) => read,
+            Err(e) => {
+                self.status = Some((format!("{}: {}", self.dir.display(), e), true));
+                return;
+            },
+        };
+        let mut entries: Vec<_> = read
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let is_dir = entry.path().is_dir();
+                let listed = !name.starts_with('.')
+                    && (is_dir || pattern_io::is_pattern_file(&entry.path()));
+                listed.then_some(Entry { name, is_dir })
+            })
+            .collect();
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        self.entries.extend(entries);
+    }
+
+    fn geometry(&self) -> Geometry {
+        let (width, height) = (self.size.0 as i32, self.size.1 as i32);
+        let line_height = (self.widget_base.text_renderer.borrow().line_height() as i32).max(1);
+        let row_height = line_height + 2;
+        let status_y = height - PADDING - line_height;
+        let input_y = status_y - PADDING - line_height;
+        let top = 2 * PADDING + line_height;
+        let bottom = if self.mode == DialogMode::Save { input_y } else { status_y } - PADDING;
+        let list_width = ((width - 3 * PADDING) as f64 * LIST_WIDTH) as i32;
+        let list = Rect::new(PADDING, top, list_width.max(1) as u32, (bottom - top).max(1) as u32);
+        // 预览下方留出三行信息
+        let info_height = 3 * line_height + PADDING;
+        let preview_x = list.right() + PADDING;
+        let preview = Rect::new(preview_x, top, (width - PADDING - preview_x).max(1) as u32,
+            (bottom - top - info_height).max(1) as u32);
+        Geometry { list, preview, row_height, info_y: preview.bottom() + PADDING, input_y, status_y }
+    }
+
+    fn visible_rows(&self) -> usize {
+        let geometry = self.geometry();
+        (geometry.list.height() as i32 / geometry.row_height).max(1) as usize
+    }
+
+    fn select(&mut self, idx: usize) {
+        let Some(entry) = self.entries.get(idx) else {
+            return;
+        };
+        self.selected = Some(idx);
+        self.preview = (!entry.is_dir).then(|| pattern_io::load(&self.dir.join(&entry.name))
+            .map_err(|e| command::pattern_error_message(&e, &self.widget_base.locale.borrow())));
+        if self.mode == DialogMode::Save && !entry.is_dir {
+            self.file_name = entry.name.clone();
+        }
+        let rows = self.visible_rows();
+        if idx < self.scroll {
+            self.scroll = idx;
+        } else if idx >= self.scroll + rows {
+            self.scroll = idx + 1 - rows;
+        }
+        self.dirty = true;
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let current = self.selected.map_or(-1, |idx| idx as isize);
+        let idx = (current + delta).clamp(0, self.entries.len() as isize - 1);
+        self.select(idx as usize);
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        let max_scroll = self.entries.len().saturating_sub(self.visible_rows());
+        self.scroll = (self.scroll as isize + delta).clamp(0, max_scroll as isize) as usize;
+        self.dirty = true;
+    }
+
+    fn enter_dir(&mut self, name: &str) {
+        let dir = if name == PARENT_DIR {
+            match self.dir.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => return,
+            }
+        } else {
+            self.dir.join(name)
+        };
+        self.dir = dir;
+        self.status = None;
+        self.refresh();
+    }
+
+    /// 进入选中的目录, 或者打开/保存文件, 成功时返回Close
+    fn confirm(&mut self) -> EventResult {
+        if let Some(entry) = self.selected.and_then(|idx| self.entries.get(idx))
+            && entry.is_dir {
+            let name = entry.name.clone();
+            self.enter_dir(&name);
+            return EventResult::Handled;
+        }
+        let command = match self.mode {
+            DialogMode::Open => {
+                let Some(entry) = self.selected.and_then(|idx| self.entries.get(idx)) else {
+                    let message = self.widget_base.locale.borrow().text("file_dialog.select_file").to_string();
+                    self.set_status(&message, true);
+                    return EventResult::Handled;
+                };
+                Command::Load(self.dir.join(&entry.name))
+            },
+            DialogMode::Save => match self.save_path() {
+                Ok(path) if path.exists() && self.overwrite.as_ref() != Some(&path) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                    let message = self.widget_base.locale.borrow().format("file_dialog.confirm_overwrite", &[&name]);
+                    self.set_status(&message, false);
+                    self.overwrite = Some(path);
+                    return EventResult::Handled;
+                },
+                Ok(path) => Command::Save(path),
+                Err(e) => {
+                    self.set_status(&e.to_string(), true);
+                    return EventResult::Handled;
+                },
+            },
+        };
+        self.overwrite = None;
+        match self.context.execute(command) {
+            Ok(message) => {
+                self.set_status(&message, false);
+                self.refresh();
+                EventResult::Close
+            },
+            Err(e) => {
+                self.set_status(&e.to_string(), true);
+                EventResult::Handled
+            },
+        }
+    }
+
+    /// 没有扩展名时使用RLE
+    fn save_path(&self) -> error::Result<PathBuf> {
+        let locale = self.widget_base.locale.borrow();
+        let name = self.file_name.trim();
+        if name.is_empty() {
+            return Err(locale.text("file_dialog.enter_name").into());
+        }
+        let mut path = self.dir.join(name);
+        if path.extension().is_none() {
+            path.set_extension(DEFAULT_EXTENSION);
+        }
+        if !pattern_io::is_pattern_file(&path) {
+            return Err(locale.text("file_dialog.unsupported").into());
+        }
+        Ok(path)
+    }
+
+    fn set_status(&mut self, message: &str, is_error: bool) {
+        self.status = Some((message.to_string(), is_error));
+        self.dirty = true;
+    }
+
+    fn handle_key(&mut self, keycode: Keycode) -> EventResult {
+        match keycode {
+            Keycode::Return | Keycode::KpEnter => return self.confirm(),
+            Keycode::Up => self.move_selection(-1),
+            Keycode::Down => self.move_selection(1),
+            Keycode::PageUp => self.move_selection(-(PAGE_ROWS as isize)),
+            Keycode::PageDown => self.move_selection(PAGE_ROWS as isize),
+            Keycode::Backspace if self.mode == DialogMode::Save => {
+                self.file_name.pop();
+            },
+            Keycode::Backspace => self.enter_dir(PARENT_DIR),
+            _ => return EventResult::Ignored,
+        }
+        self.dirty = true;
+        EventResult::Handled
+    }
+
+    fn handle_click(&mut self, x: i32, y: i32, clicks: u8) -> EventResult {
+        let geometry = self.geometry();
+        if !geometry.list.contains_point((x, y)) {
+            return EventResult::Handled;
+        }
+        let idx = self.scroll + ((y - geometry.list.y()) / geometry.row_height) as usize;
+        if idx >= self.entries.len() {
+            return EventResult::Handled;
+        }
+        self.select(idx);
+        if clicks >= 2 {
+            return self.confirm();
+        }
+        EventResult::Handled
+    }
+
+    fn fill_base_element(&self, texture: &mut Texture) -> error::Result<()> {
+        let (bg_color, highlight_color, text_color, echo_color, error_color, cell_color, dead_color) = {
+            let theme_manager = self.widget_base.theme_manager.borrow();
+            (theme_manager.panel_bg_color(), theme_manager.panel_highlight_color(),
+                theme_manager.text_color(), theme_manager.console_echo_color(),
+                theme_manager.console_error_color(), theme_manager.cell_alive_color(),
+                theme_manager.cell_dead_color())
+        };
+        let geometry = self.geometry();
+        let line_height = geometry.row_height - 2;
+        let locale = self.widget_base.locale.borrow();
+        let title = match self.mode {
+            DialogMode::Open => locale.text("file_dialog.open"),
+            DialogMode::Save => locale.text("file_dialog.save"),
+        };
+        let title = locale.format("file_dialog.title", &[&title, &self.dir.display()]);
+        let rows: Vec<_> = self.entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.visible_rows())
+            .collect();
+        let mut lines = vec![(title, (PADDING, PADDING), text_color)];
+        let info_x = geometry.preview.x();
+        match &self.preview {
+            Some(Ok(file)) => {
+                let pattern = &file.pattern;
+                let rule = file.rule
+                    .map_or_else(|| locale.text("file_dialog.unspecified").to_string(), |rule| rule.to_string());
+                lines.push((file.name.clone().unwrap_or_default(), (info_x, geometry.info_y), text_color));
+                lines.push((locale.format("file_dialog.size",
+                    &[&pattern.width(), &pattern.height(), &pattern.population()]),
+                    (info_x, geometry.info_y + line_height), echo_color));
+                lines.push((locale.format("file_dialog.rule", &[&rule]),
+                    (info_x, geometry.info_y + 2 * line_height), echo_color));
+            },
+            Some(Err(e)) => lines.push((e.clone(), (info_x, geometry.info_y), error_color)),
+            None => {},
+        }
+        if self.mode == DialogMode::Save {
+            lines.push((locale.format("file_dialog.file_name", &[&self.file_name]), (PADDING, geometry.input_y),
+                text_color));
+        }
+        let (status, status_color) = match &self.status {
+            Some((message, true)) => (message.clone(), error_color),
+            Some((message, false)) => (message.clone(), echo_color),
+            None => (locale.text("file_dialog.hint").to_string(), echo_color),
+        };
+        lines.push((status, (PADDING, geometry.status_y), status_color));
+        drop(locale);
+
+        let mut result = Ok(());
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_draw_color(bg_color);
+            canvas.clear();
+            canvas.set_draw_color(dead_color);
+            if let Err(e) = canvas.fill_rect(geometry.preview) {
+                result = Err(e.into());
+                return;
+            }
+            if let Some(Ok(file)) = &self.preview {
+                canvas.set_draw_color(cell_color);
+                let bounds = Rect::new(geometry.preview.x() + PADDING, geometry.preview.y() + PADDING,
+                    (geometry.preview.width() as i32 - 2 * PADDING).max(1) as u32,
+                    (geometry.preview.height() as i32 - 2 * PADDING).max(1) as u32);
+                if let Err(e) = canvas.fill_rects(&thumbnail_rects(&file.pattern, bounds)) {
+                    result = Err(e.into());
+                    return;
+                }
+            }
+            for (row, (idx, entry)) in rows.iter().enumerate() {
+                let y = geometry.list.y() + row as i32 * geometry.row_height;
+                if self.selected == Some(*idx) {
+                    canvas.set_draw_color(highlight_color);
+                    let rect = Rect::new(geometry.list.x(), y, geometry.list.width(), geometry.row_height as u32);
+                    if let Err(e) = canvas.fill_rect(rect) {
+                        result = Err(e.into());
+                        return;
+                    }
+                }
+                let name = if entry.is_dir { format!("{}/", entry.name) } else { entry.name.clone() };
+                let color = if entry.is_dir { echo_color } else { text_color };
+                if let Err(e) = self.widget_base.draw_text(canvas, &name, (geometry.list.x() + PADDING, y + 1), color) {
+                    result = Err(e);
+                    return;
+                }
+            }
+            for (text, pos, color) in lines.iter() {
+                if let Err(e) = self.widget_base.draw_text(canvas, text, *pos, *color) {
+                    result = Err(e);
+                    return;
+                }
+            }
+        })?;
+        result?;
+        Ok(())
+    }
+}
+
+impl widget_base::Widget for FileDialog {
+    fn widget_base(&self) -> &widget_base::WidgetBase {
+        &self.widget_base
+    }
+
+    fn rendering_on_texture(&mut self, width: u32, height: u32)
+        -> error::Result<&render::Texture>
+    {
+        widget_base::drop_stale_cache(&mut self.texture_cache, width, height);
+        self.size = (width, height);
+        let mut texture = match self.texture_cache.take() {
+            Some(texture) => texture,
+            None => {
+                self.dirty = true;
+                self.create_new_texture(width, height)?
+            },
+        };
+        if self.dirty {
+            self.fill_base_element(&mut texture)?;
+            self.dirty = false;
+        }
+
+        Ok(self.texture_cache.insert(texture))
+    }
+
+    fn invalidate(&mut self) {
+        widget_base::destroy_cache(&mut self.texture_cache);
+    }
+
+    /// 每次显示时重新读取目录, 保留上次所在的目录
+    fn on_show(&mut self) {
+        self.status = None;
+        self.overwrite = None;
+        self.refresh();
+    }
+
+    fn handle_event(&mut self, event: &WidgetEvent) -> EventResult {
+        match event {
+            WidgetEvent::TextInput { text } if self.mode == DialogMode::Save => {
+                self.file_name.push_str(text);
+                self.dirty = true;
+                EventResult::Handled
+            },
+            WidgetEvent::KeyDown { keycode, .. } => self.handle_key(*keycode),
+            WidgetEvent::MouseButtonDown { x, y, button: MouseButton::Left, clicks } => {
+                self.handle_click(*x, *y, *clicks)
+            },
+            WidgetEvent::MouseWheel { scroll_y, .. } => {
+                self.scroll_by(-scroll_y.round() as isize);
+                EventResult::Handled
+            },
+            _ => EventResult::Ignored,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::model::{
+    grid,
+    shape,
+};
+
+/// 同一物体的细胞之间的最大间距
+static CLUSTER_GAP: i32 = 2;
+static MAX_CLUSTER_CELLS: usize = 1 << 12;
+/// 一次推进很多代时, 搜索范围最多按这么多代扩大
+static MAX_SEARCH_GENERATIONS: u64 = 64;
+
+/// 相机跟随的物体(例如飞船), 记录其质心与大小
+/// 每代在上一代质心附近重新找到这一组细胞, 物体每代最多移动一格(光速)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Follow {
+    centroid: (f64, f64),
+    /// 细胞到质心的最大距离(切比雪夫距离)
+    radius: i32,
+    population: usize,
+    generation: u64,
+}
+
+impl Follow {
+    /// 从`seeds`中的活细胞开始跟随, `seeds`中只有一个细胞时跟随与它相连的一组细胞
+    /// 没有活细胞时返回None
+    pub fn start(cell_grid: &grid::CellGrid, seeds: &[(i32, i32)], generation: u64) -> Option<Self> {
+        let cells = match seeds {
+            [cell] => {
+                let everywhere = (i32::MIN, i32::MIN, i32::MAX, i32::MAX);
+                shape::cluster(cell_grid, *cell, CLUSTER_GAP, everywhere, MAX_CLUSTER_CELLS)
+            },
+            _ => seeds
+                .iter()
+                .filter_map(|cell| cell_grid.normalize(cell))
+                .filter(|addr| cell_grid.is_alive(addr))
+                .collect(),
+        };
+        let (centroid, radius) = centroid(&cells)?;
+        Some(Follow { centroid, radius, population: cells.len(), generation })
+    }
+
+    pub fn centroid(&self) -> (f64, f64) {
+        self.centroid
+    }
+
+    pub fn population(&self) -> usize {
+        self.population
+    }
+
+    /// 代数改变后重新定位, 物体消失时返回false
+    pub fn update(&mut self, cell_grid: &grid::CellGrid, generation: u64) -> bool {
+        if generation == self.generation {
+            return true;
+        }
+        let elapsed = generation.abs_diff(self.generation).min(MAX_SEARCH_GENERATIONS) as i32;
+        self.generation = generation;
+        let reach = self.radius + elapsed + CLUSTER_GAP;
+        let (cx, cy) = (self.centroid.0.floor() as i32, self.centroid.1.floor() as i32);
+        let bounds = (cx - reach, cy - reach, cx + reach, cy + reach);
+        // 从离原质心最近的活细胞出发, 附近的其他物体只有与它相连时才会被包括进来
+        let distance = |&(x, y): &(i32, i32)| (x - cx).pow(2) + (y - cy).pow(2);
+        let nearest = cell_grid
+            .alive_cells()
+            .iter()
+            .filter(|(x, y)| bounds.0 <= *x && *x <= bounds.2 && bounds.1 <= *y && *y <= bounds.3)
+            .min_by_key(|cell| distance(cell));
+        let Some(&nearest) = nearest else {
+            return false;
+        };
+        let cells = shape::cluster(cell_grid, nearest, CLUSTER_GAP, bounds, MAX_CLUSTER_CELLS);
+        let Some((centroid, radius)) = centroid(&cells) else {
+            return false;
+        };
+        (self.centroid, self.radius, self.population) = (centroid, radius, cells.len());
+        true
+    }
+}
+
+/// cell中心的平均位置, 以及cell到它的最大距离
+fn centroid(cells: &[(i32, i32)]) -> Option<((f64, f64), i32)> {
+    if cells.is_empty() {
+        return None;
+    }
+    let count = cells.len() as f64;
+    let sum = cells
+        .iter()
+        .fold((0., 0.), |(sx, sy), (x, y)| (sx + *x as f64 + 0.5, sy + *y as f64 + 0.5));
+    let centroid = (sum.0 / count, sum.1 / count);
+    let radius = cells
+        .iter()
+        .map(|(x, y)| (*x as f64 + 0.5 - centroid.0).abs().max((*y as f64 + 0.5 - centroid.1).abs()))
+        .fold(0., f64::max);
+    Some((centroid, radius.ceil() as i32))
+}
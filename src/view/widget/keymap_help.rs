@@ -0,0 +1,138 @@
+use sdl2::{
+    render::{
+        self,
+        BlendMode,
+        Texture,
+    },
+    pixels::Color,
+};
+use crate::utils::error;
+use super::super::keymap::{
+    Action,
+    Scope,
+};
+use super::widget_base;
+
+static PADDING: i32 = 8;
+static COLUMN_GAP: i32 = 24;
+
+/// 列出当前生效的绑定以及配置中的问题, 内容过多时分为多列
+pub struct KeymapHelp {
+    widget_base: widget_base::WidgetBase,
+    texture_cache: Option<Texture>,
+}
+
+impl KeymapHelp {
+    pub fn new(widget_base: widget_base::WidgetBase) -> Self {
+        KeymapHelp { widget_base, texture_cache: None }
+    }
+
+    /// (绑定, 说明, 颜色), 绑定为空的行是标题或提示
+    fn lines(&self) -> Vec<(String, String, Color)> {
+        let (text_color, echo_color, error_color) = {
+            let theme_manager = self.widget_base.theme_manager.borrow();
+            (theme_manager.text_color(), theme_manager.console_echo_color(),
+                theme_manager.console_error_color())
+        };
+        let keymap = self.widget_base.keymap.borrow();
+        let locale = self.widget_base.locale.borrow();
+        let mut lines: Vec<_> = keymap
+            .problems()
+            .iter()
+            .map(|problem| (String::new(), problem.clone(), error_color))
+            .collect();
+        for scope in Scope::all() {
+            lines.push((String::new(), locale.text(&format!("keymap.{}", scope.name())).to_string(), echo_color));
+            for action in Action::all().filter(|action| action.scope() == scope) {
+                let triggers: Vec<_> = keymap
+                    .triggers(action)
+                    .iter()
+                    .map(|trigger| trigger.to_string())
+                    .collect();
+                let triggers = if triggers.is_empty() { String::from("-") } else { triggers.join(", ") };
+                let description = format!("{} ({})",
+                    locale.text(&format!("action.{}", action.name())), action.name());
+                lines.push((triggers, description, text_color));
+            }
+        }
+        lines
+    }
+
+    fn fill_base_element(&self, texture: &mut Texture, height: u32) -> error::Result<()> {
+        let bg_color = self.widget_base.theme_manager.borrow().console_bg_color();
+        let lines = self.lines();
+        let text_renderer = self.widget_base.text_renderer.borrow();
+        let line_height = (text_renderer.line_height() as i32).max(1);
+        let rows = ((height as i32 - 2 * PADDING) / line_height).max(1) as usize;
+        // 每列的绑定宽度与总宽度
+        let columns: Vec<_> = lines
+            .chunks(rows)
+            .map(|column| {
+                let width = |text: &str| text_renderer.size_of(text).0 as i32;
+                let key_width = column.iter().map(|(keys, _, _)| width(keys)).max().unwrap_or(0);
+                let description_width = column
+                    .iter()
+                    .map(|(keys, description, _)| if keys.is_empty() {
+                        width(description) - key_width - COLUMN_GAP / 2
+                    } else {
+                        width(description)
+                    })
+                    .max()
+                    .unwrap_or(0);
+                (key_width, key_width + COLUMN_GAP / 2 + description_width.max(0))
+            })
+            .collect();
+        drop(text_renderer);
+
+        let mut result = Ok(());
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_draw_color(bg_color);
+            canvas.clear();
+            let mut x = PADDING;
+            for (column, (key_width, column_width)) in lines.chunks(rows).zip(columns) {
+                for (row, (keys, description, color)) in column.iter().enumerate() {
+                    let y = PADDING + row as i32 * line_height;
+                    let description_x = if keys.is_empty() { x } else { x + key_width + COLUMN_GAP / 2 };
+                    let drawn = self.widget_base
+                        .draw_text(canvas, keys, (x, y), *color)
+                        .and_then(|_| self.widget_base.draw_text(canvas, description, (description_x, y), *color));
+                    if let Err(e) = drawn {
+                        result = Err(e);
+                        return;
+                    }
+                }
+                x += column_width + COLUMN_GAP;
+            }
+        })?;
+        result?;
+        Ok(())
+    }
+}
+
+impl widget_base::Widget for KeymapHelp {
+    fn widget_base(&self) -> &widget_base::WidgetBase {
+        &self.widget_base
+    }
+
+    /// 内容只在创建纹理时绘制, 绑定在运行期间不会改变
+    fn rendering_on_texture(&mut self, width: u32, height: u32)
+        -> error::Result<&render::Texture>
+    {
+        widget_base::drop_stale_cache(&mut self.texture_cache, width, height);
+        let texture = match self.texture_cache.take() {
+            Some(texture) => texture,
+            None => {
+                let mut texture = self.create_new_texture(width, height)?;
+                texture.set_blend_mode(BlendMode::Blend);
+                self.fill_base_element(&mut texture, height)?;
+                texture
+            },
+        };
+
+        Ok(self.texture_cache.insert(texture))
+    }
+
+    fn invalidate(&mut self) {
+        widget_base::destroy_cache(&mut self.texture_cache);
+    }
+}
@@ -1,29 +1,80 @@
 use sdl2::{
     render,
     rect,
+    mouse::MouseButton,
 };
 
 use crate::{
     utils::error,
-    view::theme,
+    view::{
+        theme,
+        text,
+        locale,
+        keymap::{
+            self,
+            Action,
+            Scope,
+        },
+    },
+    model::{
+        universe::RcUniverse,
+        universe_set::RcUniverseSet,
+        library::RcPatternLibrary,
+    },
 };
 use super::{
     widget_base,
+    constraint::{
+        self,
+        SizeHint,
+    },
     camera,
+    minimap,
+    population_graph,
+    pattern_browser,
+    console,
+    command,
+    keymap_help,
+    file_dialog::{
+        self,
+        DialogMode,
+    },
+    layout_spec::{
+        self,
+        LayoutSpec,
+        NodeSpec,
+        WidgetKind,
+        WidgetSpec,
+    },
+    camera::RcPasteRequest,
+    viewport::RcViewport,
+    event::{
+        WidgetEvent,
+        EventResult,
+    },
 };
 
 use std::{
     rc::Rc,
     cell::RefCell,
+    collections::VecDeque,
 };
 
 pub struct Layout {
     direction: Direction,
     children: Vec<LayoutItem>,
-    left_percentage: f32,
+    padding: u32,
+    gap: u32,
+    /// 分不到空间的子项为None
+    child_rects: Vec<Option<rect::Rect>>,
+    hovered: Option<usize>,
+    focused: Option<usize>,
+    /// 捕获鼠标的子项以及开始捕获的按键
+    captured: Option<(usize, MouseButton)>,
+    overlays: Vec<Overlay>,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     Horizontal,
     Vertical,
@@ -31,13 +82,13 @@ pub enum Direction {
 
 struct LayoutItem {
     content: LayoutContent,
-    percentage: Option<f32>,
+    hint: SizeHint,
 }
 
 impl LayoutItem {
-    fn new(content: LayoutContent, percentage: Option<f32>) -> Self
+    fn new(content: LayoutContent, hint: SizeHint) -> Self
     {
-        LayoutItem { content, percentage }
+        LayoutItem { content, hint }
     }
 
     pub fn rendering(&mut self, canvas: Rc<RefCell<render::WindowCanvas>>,
@@ -55,6 +106,27 @@ impl LayoutItem {
         }
         Ok(())
     }
+
+    fn invalidate(&mut self) {
+        match &mut self.content {
+            LayoutContent::Layout(layout) => layout.invalidate(),
+            LayoutContent::Widget(widget) => widget.invalidate(),
+        }
+    }
+
+    fn tooltip(&self, (x, y): (i32, i32), rect: &rect::Rect) -> Option<String> {
+        match &self.content {
+            LayoutContent::Layout(layout) => layout.tooltip((x, y)),
+            LayoutContent::Widget(widget) => widget.tooltip(x - rect.x(), y - rect.y()),
+        }
+    }
+
+    fn dispatch_event(&mut self, event: &WidgetEvent, rect: &rect::Rect) -> EventResult {
+        match &mut self.content {
+            LayoutContent::Layout(layout) => layout.dispatch_event(event),
+            LayoutContent::Widget(widget) => widget.handle_event(&event.to_local(rect)),
+        }
+    }
 }
 
 pub enum LayoutContent {
@@ -75,99 +147,162 @@ impl From<Layout> for LayoutContent {
     }
 }
 
+/// 显示在布局底部、覆盖其他子项的widget, 由`toggle`动作切换显示
+/// 显示时接收所有键盘事件以及落在其范围内的鼠标事件
+pub struct Overlay {
+    widget: Box<dyn widget_base::Widget>,
+    height_percentage: f32,
+    toggle: Action,
+    visible: bool,
+    rect: rect::Rect,
+    /// 切换键产生的TextInput不应该进入widget
+    swallow_text: bool,
+}
+
+impl Overlay {
+    pub fn new(widget: Box<dyn widget_base::Widget>, height_percentage: f32, toggle: Action)
+        -> Self
+    {
+        Overlay {
+            widget,
+            height_percentage,
+            toggle,
+            visible: false,
+            rect: rect::Rect::new(0, 0, 1, 1),
+            swallow_text: false,
+        }
+    }
+
+    fn rendering(&mut self, canvas: Rc<RefCell<render::WindowCanvas>>, rect: rect::Rect)
+        -> error::Result<()>
+    {
+        if !self.visible {
+            return Ok(());
+        }
+        let height = ((rect.height() as f32 * self.height_percentage) as u32).max(1);
+        self.rect = rect::Rect::new(rect.x(), rect.bottom() - height as i32, rect.width(), height);
+        let texture = self.widget.rendering_on_texture(self.rect.width(), self.rect.height())?;
+        canvas.borrow_mut().copy(texture, None, self.rect)?;
+        Ok(())
+    }
+
+    fn dispatch_event(&mut self, event: &WidgetEvent) -> EventResult {
+        let (action, close) = {
+            let keymap = self.widget.widget_base().keymap.borrow();
+            (keymap.event_action(event, Scope::Global), keymap.event_action(event, Scope::Overlay))
+        };
+        if action == Some(self.toggle) {
+            self.visible = !self.visible;
+            self.swallow_text = true;
+            if self.visible {
+                self.widget.on_show();
+            }
+            return EventResult::Handled;
+        }
+        match event {
+            _ if !self.visible => return EventResult::Ignored,
+            _ if close == Some(Action::CloseOverlay) => {
+                self.visible = false;
+                return EventResult::Handled;
+            },
+            WidgetEvent::TextInput { .. } if self.swallow_text => {
+                self.swallow_text = false;
+                return EventResult::Handled;
+            },
+            WidgetEvent::KeyDown { .. } => self.swallow_text = false,
+            _ => {},
+        }
+
+        let result = if event.is_keyboard() {
+            self.widget.handle_event(event)
+        } else {
+            match event.position() {
+                Some(pos) if self.rect.contains_point(pos) => {
+                    self.widget.handle_event(&event.to_local(&self.rect))
+                },
+                _ => return EventResult::Ignored,
+            }
+        };
+        if result == EventResult::Close {
+            self.visible = false;
+        }
+        EventResult::Handled
+    }
+}
+
 impl Layout {
     pub fn new(direction: Direction) -> Self {
         Layout {
             direction,
             children: Vec::new(),
-            left_percentage: 1.,
+            padding: 0,
+            gap: 0,
+            child_rects: Vec::new(),
+            hovered: None,
+            focused: None,
+            captured: None,
+            overlays: Vec::new(),
         }
     }
 
-    pub fn add_child<T: Into<LayoutContent>>(&mut self,
-        content: T, hint_pct: Option<f32>)
-    -> &mut Self
-    {
-        let percentage = match hint_pct {
-            None => None,
-            Some(pct) if pct > self.left_percentage => None,
-            Some(pct) => {
-                self.left_percentage -= pct;
-                Some(pct)
-            }
-        };
+    pub fn add_overlay(&mut self, overlay: Overlay) -> &mut Self {
+        self.overlays.push(overlay);
+        self
+    }
 
-        let layout_item = LayoutItem::new(content.into(), percentage);
-        self.children.push(layout_item);
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
         self
     }
 
-    fn compute_child_percentage(&self) -> Vec<f32>{
-        let none_cnt = self.children
-            .iter()
-            .filter(|item| item.percentage.is_none())
-            .count();
-        
-        let none_pec = if none_cnt > 0 {
-            self.left_percentage / none_cnt as f32
-        } else {
-            0.
-        };
+    pub fn with_gap(mut self, gap: u32) -> Self {
+        self.gap = gap;
+        self
+    }
 
-        self.children
-            .iter()
-            .map(|item| item.percentage.unwrap_or(none_pec))
-            .collect()
+    /// 子项的总长度超出布局时按约束缩小, 不足时分给可伸缩的子项
+    pub fn add_child<T: Into<LayoutContent>, H: Into<SizeHint>>(&mut self,
+        content: T, hint: H)
+    -> &mut Self
+    {
+        let layout_item = LayoutItem::new(content.into(), hint.into());
+        self.children.push(layout_item);
+        self
     }
 
-    fn compute_child_rect(&self, rect: & rect::Rect) -> Vec<rect::Rect> {
-        let percentages = self.compute_child_percentage();
+    /// 子项沿布局方向依次排列, 之间留出`gap`, 四周留出`padding`
+    /// 子项与间隔恰好铺满内部区域, 长度为0的子项没有矩形, 不绘制也不接收事件
+    fn compute_child_rect(&self, rect: &rect::Rect) -> Vec<Option<rect::Rect>> {
         let is_horizontal = self.direction == Direction::Horizontal;
-        let mut cur_pos = if is_horizontal { rect.y } else { rect.x };
+        let inner = {
+            let padding = self.padding.min(rect.width() / 2).min(rect.height() / 2);
+            rect::Rect::new(rect.x() + padding as i32, rect.y() + padding as i32,
+                (rect.width() - 2 * padding).max(1), (rect.height() - 2 * padding).max(1))
+        };
+        let (main, cross) = if is_horizontal {
+            (inner.height(), inner.width())
+        } else {
+            (inner.width(), inner.height())
+        };
+        // 放不下所有间隔时缩小间隔
+        let gap_count = self.children.len().saturating_sub(1) as u32;
+        let gap = main.checked_div(gap_count).map_or(self.gap, |max_gap| self.gap.min(max_gap));
+        let hints: Vec<_> = self.children.iter().map(|item| item.hint).collect();
+        let sizes = constraint::solve(&hints, main - gap * gap_count);
 
-        percentages
+        let mut cur_pos = if is_horizontal { inner.y() } else { inner.x() };
+        sizes
             .iter()
-            .map(|pct| {
-                if is_horizontal {
-                    let height = (rect.height() as f32 * pct) as u32;
-                    let width = rect.width();
-                    let rect = rect::Rect::new(rect.x, cur_pos, width, height);
-                    cur_pos += height as i32;
-                    return rect;
+            .map(|size| {
+                let pos = cur_pos;
+                cur_pos += (size + gap) as i32;
+                (*size > 0).then(|| if is_horizontal {
+                    rect::Rect::new(inner.x(), pos, cross, *size)
                 } else {
-                    let width = (rect.width() as f32 * pct) as u32;
-                    let height = rect.height();
-                    let rect = rect::Rect::new(cur_pos, rect.y, width, height);
-                    cur_pos += width as i32;
-                    return rect;
-                }
+                    rect::Rect::new(pos, inner.y(), *size, cross)
+                })
             })
             .collect()
-     
-        //let mut rect_vec = Vec::<rect::Rect>::with_capacity(each_pec.len());
-        //if self.direction == Direction::Horizontal {
-        //    let mut next_pos = rect.y;
-        //    for pec in each_pec.iter() {
-        //        let x = rect.x;
-        //        let y = next_pos;
-        //        let width = rect.width();
-        //        let height = (rect.height() as f32 * pec) as u32;
-        //        next_pos += height;
-        //        rect_vec.push(rect::Rect::new(x, y, width, height));
-        //    }
-        //} else {
-        //    let mut next_pos = rect.x;
-        //    for pec in each_pec.iter() {
-        //        let x = next_pos;
-        //        let y = rect.y;
-        //        let width = (rect.width() as f32 * pec) as u32;
-        //        let height = rect.height();
-        //        next_pos += width;
-        //        rect_vec.push(rect::Rect::new(x, y, width, height));
-        //    }
-        //}
-        //
-        //rect_vec
     }
 
     pub fn rendering(&mut self, canvas: Rc<RefCell<render::WindowCanvas>>,
@@ -177,45 +312,299 @@ impl Layout {
         child_rects
             .iter()
             .enumerate()
+            .filter_map(|(idx, rect)| rect.map(|rect| (idx, rect)))
             .try_for_each(|(idx, rect)| -> error::Result<()>  {
-                self.children[idx].rendering(canvas.clone(), rect.clone())?;
+                self.children[idx].rendering(canvas.clone(), rect)?;
                 Ok(())
             })?;
-        
+        self.child_rects = child_rects;
+        self.overlays
+            .iter_mut()
+            .try_for_each(|overlay| overlay.rendering(canvas.clone(), rect))?;
+
         Ok(())
     }
+
+    pub fn invalidate(&mut self) {
+        self.children
+            .iter_mut()
+            .for_each(|child| child.invalidate());
+        self.overlays
+            .iter_mut()
+            .for_each(|overlay| overlay.widget.invalidate());
+    }
+
+    /// 窗口坐标`pos`处的提示, 可见的覆盖层遮住其下的子项
+    pub fn tooltip(&self, pos: (i32, i32)) -> Option<String> {
+        if let Some(overlay) = self.overlays
+            .iter()
+            .rev()
+            .find(|overlay| overlay.visible && overlay.rect.contains_point(pos)) {
+            return overlay.widget.tooltip(pos.0 - overlay.rect.x(), pos.1 - overlay.rect.y());
+        }
+        let idx = self.hit_test(pos)?;
+        let rect = self.child_rects.get(idx).copied().flatten()?;
+        self.children[idx].tooltip(pos, &rect)
+    }
+
+    fn hit_test(&self, (x, y): (i32, i32)) -> Option<usize> {
+        self.child_rects
+            .iter()
+            .position(|rect| rect.is_some_and(|rect| rect.contains_point((x, y))))
+    }
+
+    fn dispatch_to_child(&mut self, idx: usize, event: &WidgetEvent) -> EventResult {
+        match (self.children.get_mut(idx), self.child_rects.get(idx)) {
+            (Some(child), Some(Some(rect))) => child.dispatch_event(event, rect),
+            _ => EventResult::Ignored,
+        }
+    }
+
+    fn update_hovered(&mut self, hovered: Option<usize>) {
+        if self.hovered == hovered {
+            return;
+        }
+        if let Some(idx) = self.hovered {
+            self.dispatch_to_child(idx, &WidgetEvent::MouseLeave);
+        }
+        self.hovered = hovered;
+    }
+
+    /// 事件坐标为窗口坐标(与rendering时使用的rect一致)
+    /// 鼠标事件交给捕获者或光标下的子项, 键盘事件交给焦点子项,
+    /// 未处理的事件返回`Ignored`, 冒泡回调用者
+    pub fn dispatch_event(&mut self, event: &WidgetEvent) -> EventResult {
+        for overlay in self.overlays.iter_mut() {
+            let result = overlay.dispatch_event(event);
+            if result.is_handled() {
+                return result;
+            }
+        }
+
+        if let WidgetEvent::MouseLeave = event {
+            if self.captured.is_none() {
+                self.update_hovered(None);
+            }
+            return EventResult::Handled;
+        }
+
+        if event.is_keyboard() {
+            return match self.focused.or(self.hovered) {
+                Some(idx) => self.dispatch_to_child(idx, event),
+                None => EventResult::Ignored,
+            };
+        }
+
+        let Some(pos) = event.position() else {
+            return EventResult::Ignored;
+        };
+
+        let target = match self.captured {
+            Some((idx, _)) => Some(idx),
+            None => {
+                let hit = self.hit_test(pos);
+                self.update_hovered(hit);
+                hit
+            },
+        };
+        let Some(idx) = target else {
+            return EventResult::Ignored;
+        };
+
+        if let WidgetEvent::MouseButtonDown { .. } = event {
+            self.focused = Some(idx);
+        }
+
+        let result = self.dispatch_to_child(idx, event);
+        match event {
+            WidgetEvent::MouseButtonDown { button, .. } if result == EventResult::Capture => {
+                self.captured = Some((idx, *button));
+            },
+            // 只有开始捕获的按键松开时才结束捕获
+            WidgetEvent::MouseButtonUp { button, .. }
+                if self.captured.is_some_and(|(_, captured)| captured == *button) => {
+                self.captured = None;
+                let hit = self.hit_test(pos);
+                self.update_hovered(hit);
+            },
+            _ => {},
+        }
+        result
+    }
 }
 
 
 pub struct LayoutManager {
     theme_manager: theme::RcThemeManager,
+    locale: locale::RcLocaleManager,
     widget_base_builder: widget_base::WidgetBaseBuilder,
+    universes: RcUniverseSet,
+    library: RcPatternLibrary,
+    notices: command::RcNotices,
 }
 
 impl LayoutManager {
-    pub fn new(theme_manager: theme::RcThemeManager,
-        canvas: Rc<RefCell<render::WindowCanvas>>) 
-        -> Self
+    pub fn new(theme_manager: theme::RcThemeManager, text_renderer: text::RcTextRenderer,
+        locale: locale::RcLocaleManager, keymap: keymap::RcKeymap,
+        canvas: Rc<RefCell<render::WindowCanvas>>, universes: RcUniverseSet,
+        library: RcPatternLibrary) -> Self
     {
-        let widget_base_builder = widget_base::WidgetBaseBuilder::new(theme_manager.clone(), canvas);
-        LayoutManager { theme_manager: theme_manager, widget_base_builder }
+        let widget_base_builder = widget_base::WidgetBaseBuilder::new(theme_manager.clone(),
+            canvas, text_renderer, locale.clone(), keymap);
+        LayoutManager { theme_manager, locale, widget_base_builder, universes, library,
+            notices: Rc::new(RefCell::new(Vec::new())) }
+    }
+
+    /// 在所有控制台中显示
+    pub fn notify(&self, message: String) {
+        self.notices.borrow_mut().push(message);
     }
 
     fn get_blank(&self) -> widget_base::BlankWidget {
         widget_base::BlankWidget::new(self.widget_base_builder.build(), true)
     }
 
-    fn get_camera(&self) -> error::Result<camera::Camera> {
-        camera::Camera::new(self.widget_base_builder.build(), self.theme_manager.clone())
+    fn get_camera(&self, universe: RcUniverse) -> error::Result<camera::Camera> {
+        camera::Camera::new(self.widget_base_builder.build(), self.theme_manager.clone(),
+            universe, self.universes.clone())
+    }
+
+    fn get_population_graph(&self, universe: RcUniverse) -> population_graph::PopulationGraph {
+        population_graph::PopulationGraph::new(self.widget_base_builder.build(), universe)
+    }
+
+    fn get_pattern_browser(&self, paste_request: RcPasteRequest)
+        -> pattern_browser::PatternBrowser
+    {
+        pattern_browser::PatternBrowser::new(self.widget_base_builder.build(),
+            self.library.clone(), paste_request)
+    }
+
+    fn get_command_context(&self, links: &CameraLinks) -> command::CommandContext {
+        command::CommandContext::new(links.universe.clone(), self.universes.clone(),
+            links.viewport.clone(), self.theme_manager.clone(), self.locale.clone(), self.notices.clone())
+    }
+
+    fn get_console(&self, links: &CameraLinks) -> console::Console {
+        console::Console::new(self.widget_base_builder.build(), self.get_command_context(links))
+    }
+
+    fn get_file_dialog(&self, links: &CameraLinks, mode: DialogMode) -> file_dialog::FileDialog {
+        file_dialog::FileDialog::new(self.widget_base_builder.build(), self.get_command_context(links),
+            mode)
+    }
+
+    fn get_keymap_help(&self) -> keymap_help::KeymapHelp {
+        keymap_help::KeymapHelp::new(self.widget_base_builder.build())
+    }
+
+    fn get_minimap(&self, links: &CameraLinks) -> error::Result<minimap::Minimap> {
+        minimap::Minimap::new(self.widget_base_builder.build(), self.theme_manager.clone(),
+            links.universe.clone(), links.viewport.clone())
     }
 
     pub fn get_default_layout(&self) -> error::Result<Layout> {
-        let mut root_layout = Layout::new(Direction::Horizontal);
-        let camera = self.get_camera()?;
-        root_layout
-            .add_child(self.get_blank(), None)
-            .add_child(camera, Some(0.8))
-            .add_child(self.get_blank(), None);
-        Ok(root_layout)
+        let spec = LayoutSpec::from_toml(layout_spec::DEFAULT_LAYOUT)?;
+        self.build_layout(&spec)
+    }
+
+    /// 相机在构建前创建, 树中位于相机之前的widget也能与它共享状态
+    pub fn build_layout(&self, spec: &LayoutSpec) -> error::Result<Layout> {
+        for universe in spec.universes.iter() {
+            self.universes.borrow_mut().get_or_create(&universe.name, universe.rule);
+        }
+        let mut cameras = Cameras::default();
+        for widget in spec.widgets().into_iter().filter(|widget| widget.kind == WidgetKind::Camera) {
+            let universe = self.universes
+                .borrow()
+                .get(widget.universe())
+                .ok_or_else(|| format!("unknown universe `{}`", widget.universe()))?;
+            let camera = self.get_camera(universe.clone())?;
+            cameras.links.push((widget.id.clone(), CameraLinks {
+                viewport: camera.viewport(),
+                paste_request: camera.paste_request(),
+                universe,
+            }));
+            cameras.pending.push_back(camera);
+        }
+        self.build_node(spec, &mut cameras)
+    }
+
+    fn build_node(&self, spec: &LayoutSpec, cameras: &mut Cameras) -> error::Result<Layout> {
+        let mut layout = Layout::new(spec.direction)
+            .with_padding(spec.padding)
+            .with_gap(spec.gap);
+        for child in spec.children.iter() {
+            let content = match &child.node {
+                NodeSpec::Layout(spec) => LayoutContent::Layout(self.build_node(spec, cameras)?),
+                NodeSpec::Widget(widget) => LayoutContent::Widget(self.build_widget(widget, cameras)?),
+            };
+            layout.add_child(content, child.hint);
+        }
+        for overlay in spec.overlays.iter() {
+            let widget = self.build_widget(&overlay.widget, cameras)?;
+            layout.add_overlay(Overlay::new(widget, overlay.height, overlay.toggle));
+        }
+        Ok(layout)
+    }
+
+    fn build_widget(&self, spec: &WidgetSpec, cameras: &mut Cameras)
+        -> error::Result<Box<dyn widget_base::Widget>>
+    {
+        let kind = spec.kind;
+        if kind == WidgetKind::Camera {
+            let camera = cameras.pending.pop_front().ok_or("cameras should be created before building")?;
+            return Ok(Box::new(camera));
+        }
+        let linked = || cameras
+            .link(spec.camera.as_deref())
+            .ok_or_else(|| format!("`{}` requires a camera in the layout", kind.name()));
+        let widget: Box<dyn widget_base::Widget> = match kind {
+            WidgetKind::Blank => Box::new(self.get_blank()),
+            WidgetKind::Camera => unreachable!("the camera is taken above"),
+            WidgetKind::Minimap => Box::new(self.get_minimap(linked()?)?),
+            WidgetKind::PopulationGraph => {
+                let universe = self.universes
+                    .borrow()
+                    .get(spec.universe())
+                    .ok_or_else(|| format!("unknown universe `{}`", spec.universe()))?;
+                Box::new(self.get_population_graph(universe))
+            },
+            WidgetKind::PatternBrowser => {
+                Box::new(self.get_pattern_browser(linked()?.paste_request.clone()))
+            },
+            WidgetKind::Console => Box::new(self.get_console(linked()?)),
+            WidgetKind::KeymapHelp => Box::new(self.get_keymap_help()),
+            WidgetKind::OpenDialog => Box::new(self.get_file_dialog(linked()?, DialogMode::Open)),
+            WidgetKind::SaveDialog => Box::new(self.get_file_dialog(linked()?, DialogMode::Save)),
+        };
+        Ok(widget)
+    }
+}
+
+/// 与相机共享的状态
+struct CameraLinks {
+    viewport: RcViewport,
+    paste_request: RcPasteRequest,
+    universe: RcUniverse,
+}
+
+/// 构建前创建的相机, 构建时按树中的顺序取出
+#[derive(Default)]
+struct Cameras {
+    pending: VecDeque<camera::Camera>,
+    links: Vec<(Option<String>, CameraLinks)>,
+}
+
+impl Cameras {
+    /// 未指定`id`时为第一个相机
+    fn link(&self, id: Option<&str>) -> Option<&CameraLinks> {
+        match id {
+            Some(id) => self.links
+                .iter()
+                .find(|(known, _)| known.as_deref() == Some(id))
+                .map(|(_, links)| links),
+            None => self.links.first().map(|(_, links)| links),
+        }
     }
 }
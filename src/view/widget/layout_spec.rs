@@ -0,0 +1,509 @@
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+};
+use crate::{
+    utils::error,
+    model::{
+        rules::LifeRule,
+        universe_set::MAIN_UNIVERSE,
+    },
+    view::keymap::{
+        Action,
+        Scope,
+    },
+};
+use super::{
+    layout::Direction,
+    constraint::{
+        Size,
+        SizeHint,
+    },
+};
+
+/// 未提供布局文件时使用的布局: 相机与侧边栏位于上下两条空白之间, 控制台与帮助为覆盖层
+pub static DEFAULT_LAYOUT: &str = r#"
+[root]
+direction = "horizontal"
+
+[[root.children]]
+widget = "blank"
+
+[[root.children]]
+direction = "vertical"
+size = 0.8
+
+[[root.children.children]]
+widget = "camera"
+size = 0.8
+
+[[root.children.children]]
+direction = "horizontal"
+children = [
+    { widget = "minimap", size = 0.3 },
+    { widget = "population_graph", size = 0.25 },
+    { widget = "pattern_browser" },
+]
+
+[[root.children]]
+widget = "blank"
+
+[[root.overlays]]
+widget = "console"
+height = 0.4
+toggle = "toggle_console"
+
+[[root.overlays]]
+widget = "keymap_help"
+height = 1.0
+toggle = "toggle_help"
+
+[[root.overlays]]
+widget = "open_dialog"
+height = 0.6
+toggle = "open_file"
+
+[[root.overlays]]
+widget = "save_dialog"
+height = 0.6
+toggle = "save_file"
+"#;
+
+static LAYOUT_KEYS: [&str; 5] = ["direction", "children", "overlays", "padding", "gap"];
+/// 子项在父布局中的尺寸约束
+static CHILD_KEYS: [&str; 5] = ["size", "pixels", "weight", "min", "max"];
+/// widget自身的选项
+static WIDGET_KEYS: [&str; 4] = ["widget", "id", "camera", "universe"];
+static OVERLAY_KEYS: [&str; 2] = ["height", "toggle"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidgetKind {
+    Blank,
+    Camera,
+    Minimap,
+    PopulationGraph,
+    PatternBrowser,
+    Console,
+    KeymapHelp,
+    OpenDialog,
+    SaveDialog,
+}
+
+static WIDGET_KINDS: [(WidgetKind, &str); 9] = [
+    (WidgetKind::Blank, "blank"),
+    (WidgetKind::Camera, "camera"),
+    (WidgetKind::Minimap, "minimap"),
+    (WidgetKind::PopulationGraph, "population_graph"),
+    (WidgetKind::PatternBrowser, "pattern_browser"),
+    (WidgetKind::Console, "console"),
+    (WidgetKind::KeymapHelp, "keymap_help"),
+    (WidgetKind::OpenDialog, "open_dialog"),
+    (WidgetKind::SaveDialog, "save_dialog"),
+];
+
+impl WidgetKind {
+    pub fn name(&self) -> &'static str {
+        WIDGET_KINDS
+            .iter()
+            .find(|(kind, _)| kind == self)
+            .map(|(_, name)| *name)
+            .expect("every widget kind should be listed in WIDGET_KINDS")
+    }
+
+    /// 需要与相机共享视口或粘贴请求
+    pub fn needs_camera(&self) -> bool {
+        matches!(self, WidgetKind::Minimap | WidgetKind::PatternBrowser | WidgetKind::Console
+            | WidgetKind::OpenDialog | WidgetKind::SaveDialog)
+    }
+
+    /// 可以用`universe`指定显示的宇宙
+    pub fn shows_universe(&self) -> bool {
+        matches!(self, WidgetKind::Camera | WidgetKind::PopulationGraph)
+    }
+}
+
+impl FromStr for WidgetKind {
+    type Err = error::DynError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        WIDGET_KINDS
+            .iter()
+            .find(|(_, known)| *known == name)
+            .map(|(kind, _)| *kind)
+            .ok_or_else(|| {
+                let names: Vec<_> = WIDGET_KINDS.iter().map(|(_, name)| *name).collect();
+                format!("unknown widget `{}`, expected one of {}", name, names.join(", ")).into()
+            })
+    }
+}
+
+pub enum NodeSpec {
+    Layout(LayoutSpec),
+    Widget(WidgetSpec),
+}
+
+pub struct WidgetSpec {
+    pub kind: WidgetKind,
+    /// 相机的名称, 供其他widget的`camera`引用
+    pub id: Option<String>,
+    /// 关联的相机, 未指定时为树中的第一个相机
+    pub camera: Option<String>,
+    /// 显示的宇宙, 未指定时为main
+    pub universe: Option<String>,
+}
+
+impl WidgetSpec {
+    pub fn universe(&self) -> &str {
+        self.universe.as_deref().unwrap_or(MAIN_UNIVERSE)
+    }
+}
+
+/// `[universes.<name>]`定义的宇宙, 与main同步演化
+pub struct UniverseSpec {
+    pub name: String,
+    pub rule: LifeRule,
+}
+
+pub struct ChildSpec {
+    pub node: NodeSpec,
+    pub hint: SizeHint,
+}
+
+pub struct OverlaySpec {
+    pub widget: WidgetSpec,
+    /// 占所在布局高度的比例
+    pub height: f32,
+    pub toggle: Action,
+}
+
+/// 布局文件描述的布局树, 构建前已经过检查
+pub struct LayoutSpec {
+    pub direction: Direction,
+    pub padding: u32,
+    pub gap: u32,
+    pub children: Vec<ChildSpec>,
+    pub overlays: Vec<OverlaySpec>,
+    /// 只在根布局中出现
+    pub universes: Vec<UniverseSpec>,
+}
+
+impl LayoutSpec {
+    pub fn load(path: &Path) -> error::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        LayoutSpec::from_toml(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// 格式为`[root]`表, 子项是`widget`或者嵌套的布局, 错误信息中标明出错条目的路径:
+    /// ```toml
+    /// [root]
+    /// direction = "vertical"      # horizontal: 子项自上而下排列, vertical: 自左向右排列
+    /// padding = 4                 # 四周与子项之间的像素
+    /// gap = 2
+    /// children = [
+    ///     { widget = "camera", size = 0.75, min = 320 },
+    ///     { direction = "horizontal", max = 400, children = [
+    ///         { widget = "minimap", pixels = 200 },
+    ///         { widget = "blank", weight = 2 },
+    ///     ] },
+    /// ]
+    /// overlays = [{ widget = "console", height = 0.4, toggle = "toggle_console" }]
+    /// ```
+    /// 子项的`size`为比例, `pixels`为固定像素, 两者都没有时按`weight`(默认为1)分配剩余的长度
+    ///
+    /// 可以有多个相机, 以`id`命名后由其他widget的`camera`引用;
+    /// 相机与人口图可以用`universe`显示`[universes]`中定义的、以其他规则同步演化的宇宙:
+    /// ```toml
+    /// [universes.highlife]
+    /// rule = "B36/S23"
+    ///
+    /// [root]
+    /// direction = "vertical"
+    /// children = [
+    ///     { widget = "camera", id = "life" },
+    ///     { widget = "camera", id = "highlife", universe = "highlife" },
+    ///     { widget = "minimap", camera = "highlife", pixels = 200 },
+    /// ]
+    /// ```
+    pub fn from_toml(text: &str) -> error::Result<Self> {
+        let table: toml::Table = text.parse()?;
+        if let Some(key) = table.keys().find(|key| *key != "root" && *key != "universes") {
+            return Err(format!("unknown key `{}`, the layout belongs in [root]", key).into());
+        }
+        let root = match table.get("root") {
+            Some(toml::Value::Table(root)) => root,
+            Some(_) => return Err(String::from("root: expected a table").into()),
+            None => return Err(String::from("missing [root] table").into()),
+        };
+        if let Some(key) = CHILD_KEYS.iter().find(|key| root.contains_key(**key)) {
+            return Err(format!("root.{}: the root layout always fills the window", key).into());
+        }
+        let mut spec = LayoutSpec::parse_layout(root, "root")?;
+        spec.universes = match table.get("universes") {
+            Some(toml::Value::Table(universes)) => universes
+                .iter()
+                .map(|(name, value)| parse_universe(name, value))
+                .collect::<error::Result<_>>()?,
+            Some(_) => return Err(String::from("universes: expected a table").into()),
+            None => Vec::new(),
+        };
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    fn parse_layout(table: &toml::Table, path: &str) -> error::Result<Self> {
+        if let Some(key) = table.keys().find(|key| !LAYOUT_KEYS.contains(&key.as_str())
+            && !CHILD_KEYS.contains(&key.as_str())) {
+            return Err(format!("{}.{}: unknown key", path, key).into());
+        }
+        let direction = match table.get("direction") {
+            Some(value) => match value.as_str() {
+                Some("horizontal") => Direction::Horizontal,
+                Some("vertical") => Direction::Vertical,
+                _ => return Err(format!("{}.direction: expected \"horizontal\" or \"vertical\"",
+                    path).into()),
+            },
+            None => return Err(format!("{}: missing `direction`", path).into()),
+        };
+        let children = array_items(table, "children", path)?
+            .into_iter()
+            .map(|(value, path)| LayoutSpec::parse_child(value, &path))
+            .collect::<error::Result<Vec<_>>>()?;
+        if children.is_empty() {
+            return Err(format!("{}: a layout needs at least one child", path).into());
+        }
+        let total: f32 = children
+            .iter()
+            .filter_map(|child| match child.hint.size() {
+                Size::Fraction(fraction) => Some(fraction),
+                _ => None,
+            })
+            .sum();
+        if total > 1. + f32::EPSILON {
+            return Err(format!("{}: sizes of the children add up to {}, more than 1", path, total).into());
+        }
+        let padding = optional_pixels(table, "padding", path)?.unwrap_or(0);
+        let gap = optional_pixels(table, "gap", path)?.unwrap_or(0);
+        let overlays = array_items(table, "overlays", path)?
+            .into_iter()
+            .map(|(value, path)| LayoutSpec::parse_overlay(value, &path))
+            .collect::<error::Result<Vec<_>>>()?;
+        Ok(LayoutSpec { direction, padding, gap, children, overlays, universes: Vec::new() })
+    }
+
+    fn parse_child(value: &toml::Value, path: &str) -> error::Result<ChildSpec> {
+        let toml::Value::Table(table) = value else {
+            return Err(format!("{}: expected a table", path).into());
+        };
+        let hint = LayoutSpec::parse_hint(table, path)?;
+        let node = match table.get("widget") {
+            Some(_) if table.contains_key("direction") => {
+                return Err(format!("{}: `widget` and `direction` cannot be used together", path).into());
+            },
+            Some(_) => {
+                if let Some(key) = table.keys().find(|key| !WIDGET_KEYS.contains(&key.as_str())
+                    && !CHILD_KEYS.contains(&key.as_str())) {
+                    return Err(format!("{}.{}: unknown key", path, key).into());
+                }
+                NodeSpec::Widget(parse_widget(table, path)?)
+            },
+            None => NodeSpec::Layout(LayoutSpec::parse_layout(table, path)?),
+        };
+        Ok(ChildSpec { node, hint })
+    }
+
+    fn parse_hint(table: &toml::Table, path: &str) -> error::Result<SizeHint> {
+        let weight = match table.get("weight") {
+            Some(value) => Some(value
+                .as_float()
+                .or_else(|| value.as_integer().map(|n| n as f64))
+                .filter(|weight| *weight > 0.)
+                .ok_or_else(|| format!("{}.weight: expected a positive number", path))? as f32),
+            None => None,
+        };
+        let pixels = optional_pixels(table, "pixels", path)?;
+        let hint = match (table.get("size"), pixels, weight) {
+            (Some(_), Some(_), _) => {
+                return Err(format!("{}: `size` and `pixels` cannot be used together", path).into());
+            },
+            (Some(_), _, Some(_)) | (_, Some(_), Some(_)) => {
+                return Err(format!("{}.weight: only applies to children without `size` or `pixels`",
+                    path).into());
+            },
+            (Some(size), None, None) => SizeHint::fraction(fraction(size, &format!("{}.size", path))?),
+            (None, Some(pixels), None) => SizeHint::pixels(pixels),
+            (None, None, weight) => SizeHint::weighted(weight.unwrap_or(1.)),
+        };
+        let min = optional_pixels(table, "min", path)?;
+        let max = optional_pixels(table, "max", path)?;
+        if let (Some(min), Some(max)) = (min, max)
+            && min > max {
+            return Err(format!("{}: `min` ({}) is larger than `max` ({})", path, min, max).into());
+        }
+        let hint = min.map_or(hint, |min| hint.min(min));
+        Ok(max.map_or(hint, |max| hint.max(max)))
+    }
+
+    fn parse_overlay(value: &toml::Value, path: &str) -> error::Result<OverlaySpec> {
+        let toml::Value::Table(table) = value else {
+            return Err(format!("{}: expected a table", path).into());
+        };
+        if let Some(key) = table.keys().find(|key| !WIDGET_KEYS.contains(&key.as_str())
+            && !OVERLAY_KEYS.contains(&key.as_str())) {
+            return Err(format!("{}.{}: unknown key", path, key).into());
+        }
+        let widget = parse_widget(table, path)?;
+        if widget.kind == WidgetKind::Camera {
+            return Err(format!("{}.widget: the camera cannot be an overlay", path).into());
+        }
+        let height = match table.get("height") {
+            Some(value) => fraction(value, &format!("{}.height", path))?,
+            None => 1.,
+        };
+        let toggle = table
+            .get("toggle")
+            .ok_or_else(|| format!("{}: missing `toggle`", path))?;
+        let toggle: Action = toggle
+            .as_str()
+            .ok_or_else(|| format!("{}.toggle: expected an action name", path))?
+            .parse()
+            .map_err(|e| format!("{}.toggle: {}", path, e))?;
+        if toggle.scope() != Scope::Global {
+            return Err(format!("{}.toggle: `{}` is not a global action", path, toggle.name()).into());
+        }
+        Ok(OverlaySpec { widget, height, toggle })
+    }
+
+    /// 树中(包括覆盖层)所有的widget, 按构建顺序排列
+    pub fn widgets(&self) -> Vec<&WidgetSpec> {
+        let mut widgets = Vec::new();
+        for child in self.children.iter() {
+            match &child.node {
+                NodeSpec::Layout(layout) => widgets.extend(layout.widgets()),
+                NodeSpec::Widget(widget) => widgets.push(widget),
+            }
+        }
+        widgets.extend(self.overlays.iter().map(|overlay| &overlay.widget));
+        widgets
+    }
+
+    fn validate(&self) -> error::Result<()> {
+        let widgets = self.widgets();
+        let cameras: Vec<_> = widgets
+            .iter()
+            .filter(|widget| widget.kind == WidgetKind::Camera)
+            .collect();
+        if cameras.is_empty()
+            && let Some(widget) = widgets.iter().find(|widget| widget.kind.needs_camera()) {
+            return Err(format!("`{}` requires a camera in the layout", widget.kind.name()).into());
+        }
+        for (idx, camera) in cameras.iter().enumerate() {
+            if let Some(id) = &camera.id
+                && cameras[..idx].iter().any(|other| other.id.as_ref() == Some(id)) {
+                return Err(format!("duplicated camera id `{}`", id).into());
+            }
+        }
+        for widget in widgets.iter() {
+            if let Some(camera) = &widget.camera
+                && !cameras.iter().any(|other| other.id.as_ref() == Some(camera)) {
+                return Err(format!("`{}` refers to unknown camera `{}`", widget.kind.name(), camera).into());
+            }
+            let universe = widget.universe();
+            if universe != MAIN_UNIVERSE && !self.universes.iter().any(|known| known.name == universe) {
+                return Err(format!("`{}` refers to unknown universe `{}`, define it in [universes.{}]",
+                    widget.kind.name(), universe, universe).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 可选的像素值
+fn optional_pixels(table: &toml::Table, key: &str, path: &str) -> error::Result<Option<u32>> {
+    table
+        .get(key)
+        .map(|value| value
+            .as_integer()
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(|| format!("{}.{}: expected a non-negative number of pixels", path, key).into()))
+        .transpose()
+}
+
+/// 可选的数组条目及其路径
+fn array_items<'a>(table: &'a toml::Table, key: &str, path: &str)
+    -> error::Result<Vec<(&'a toml::Value, String)>>
+{
+    match table.get(key) {
+        None => Ok(Vec::new()),
+        Some(toml::Value::Array(values)) => Ok(values
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| (value, format!("{}.{}[{}]", path, key, idx)))
+            .collect()),
+        Some(_) => Err(format!("{}.{}: expected an array", path, key).into()),
+    }
+}
+
+/// `widget`及其选项, 选项只能用于对应种类的widget
+fn parse_widget(table: &toml::Table, path: &str) -> error::Result<WidgetSpec> {
+    let kind = table
+        .get("widget")
+        .ok_or_else(|| format!("{}: missing `widget`", path))?;
+    let kind = widget_kind(kind, &format!("{}.widget", path))?;
+    let name = |key: &str, allowed: bool, usage: &str| -> error::Result<Option<String>> {
+        let Some(value) = table.get(key) else {
+            return Ok(None);
+        };
+        if !allowed {
+            return Err(format!("{}.{}: only applies to {}", path, key, usage).into());
+        }
+        value
+            .as_str()
+            .filter(|name| !name.is_empty())
+            .map(|name| Some(name.to_string()))
+            .ok_or_else(|| format!("{}.{}: expected a name", path, key).into())
+    };
+    let id = name("id", kind == WidgetKind::Camera, "cameras")?;
+    let camera = name("camera", kind.needs_camera(), "widgets linked to a camera")?;
+    let universe = name("universe", kind.shows_universe(), "cameras and population graphs")?;
+    Ok(WidgetSpec { kind, id, camera, universe })
+}
+
+fn parse_universe(name: &str, value: &toml::Value) -> error::Result<UniverseSpec> {
+    let path = format!("universes.{}", name);
+    if name == MAIN_UNIVERSE {
+        return Err(format!("{}: `{}` is built in, change its rule from the console", path, name).into());
+    }
+    let toml::Value::Table(table) = value else {
+        return Err(format!("{}: expected a table", path).into());
+    };
+    if let Some(key) = table.keys().find(|key| *key != "rule") {
+        return Err(format!("{}.{}: unknown key", path, key).into());
+    }
+    let rule = table
+        .get("rule")
+        .ok_or_else(|| format!("{}: missing `rule`", path))?
+        .as_str()
+        .ok_or_else(|| format!("{}.rule: expected a rule like \"B36/S23\"", path))?
+        .parse()
+        .map_err(|e| format!("{}.rule: {}", path, e))?;
+    Ok(UniverseSpec { name: name.to_string(), rule })
+}
+
+fn widget_kind(value: &toml::Value, path: &str) -> error::Result<WidgetKind> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("{}: expected a widget name", path))?
+        .parse()
+        .map_err(|e| format!("{}: {}", path, e).into())
+}
+
+/// (0, 1]之间的比例, 整数也可以接受
+fn fraction(value: &toml::Value, path: &str) -> error::Result<f32> {
+    value
+        .as_float()
+        .or_else(|| value.as_integer().map(|n| n as f64))
+        .filter(|value| 0. < *value && *value <= 1.)
+        .map(|value| value as f32)
+        .ok_or_else(|| format!("{}: expected a number within (0, 1]", path).into())
+}
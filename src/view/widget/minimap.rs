@@ -0,0 +1,178 @@
+use sdl2::{
+    render::{
+        self,
+        Texture,
+    },
+    rect::Rect,
+    mouse::MouseButton,
+};
+use crate::{
+    utils::error,
+    model::universe::RcUniverse,
+};
+use super::super::theme;
+use super::{
+    widget_base,
+    camera::ViewGrid,
+    color_mode::ColorMode,
+    viewport::{
+        Viewport,
+        RcViewport,
+    },
+    event::{
+        WidgetEvent,
+        EventResult,
+    },
+};
+
+static MARGIN_RATIO: f64 = 0.1;
+
+/// 显示活细胞包围盒的缩略图及相机视口的位置, 点击或拖拽可移动相机
+pub struct Minimap {
+    widget_base: widget_base::WidgetBase,
+    view_grid: ViewGrid,
+    universe: RcUniverse,
+    camera_viewport: RcViewport,
+    viewport: Viewport,
+    texture_cache: Option<Texture>,
+    bounding_box: Option<(i32, i32, i32, i32)>,
+    drawn_state: Option<(u64, Viewport)>,
+    dragging: bool,
+}
+
+impl Minimap {
+    pub fn new(widget_base: widget_base::WidgetBase, theme_manager: theme::RcThemeManager,
+        universe: RcUniverse, camera_viewport: RcViewport) -> error::Result<Self>
+    {
+        let view_grid = ViewGrid::new(universe.clone(), theme_manager);
+        let viewport = Viewport::new((0., 0.), 1.)?;
+        Ok(Minimap {
+            widget_base,
+            view_grid,
+            universe,
+            camera_viewport,
+            viewport,
+            texture_cache: None,
+            bounding_box: None,
+            drawn_state: None,
+            dragging: false,
+        })
+    }
+
+    /// 显示范围为加上边距的包围盒, 网格为空时显示相机当前的可见范围
+    fn update_viewport(&mut self, width: u32, height: u32, revision_changed: bool) {
+        if revision_changed {
+            self.bounding_box = self.universe.borrow().grid().bounding_box();
+        }
+        let region = match self.bounding_box {
+            Some((min_x, min_y, max_x, max_y)) => {
+                let (min_x, min_y) = (min_x as f64, min_y as f64);
+                let (max_x, max_y) = (max_x as f64 + 1., max_y as f64 + 1.);
+                let margin = (max_x - min_x).max(max_y - min_y) * MARGIN_RATIO;
+                (min_x - margin, min_y - margin, max_x + margin, max_y + margin)
+            },
+            None => {
+                let (min_x, min_y, max_x, max_y) = self.camera_viewport.borrow().visible_cells();
+                (min_x as f64, min_y as f64, max_x as f64, max_y as f64)
+            },
+        };
+        self.viewport.set_size(width, height);
+        self.viewport.fit(region);
+    }
+
+    fn indicator_rect(&self) -> Rect {
+        let camera_viewport = self.camera_viewport.borrow();
+        let (width, height) = camera_viewport.size();
+        let top_left = camera_viewport.screen_to_world((0., 0.));
+        let bottom_right = camera_viewport.screen_to_world((width as f64, height as f64));
+        let (x0, y0) = self.viewport.world_to_screen(top_left);
+        let (x1, y1) = self.viewport.world_to_screen(bottom_right);
+        Rect::new(x0.floor() as i32, y0.floor() as i32,
+            ((x1 - x0).round() as u32).max(1), ((y1 - y0).round() as u32).max(1))
+    }
+
+    fn fill_base_element(&self, texture: &mut Texture) -> error::Result<()> {
+        let (bg_color, indicator_color) = {
+            let theme_manager = self.widget_base.theme_manager.borrow();
+            (theme_manager.minimap_bg_color(), theme_manager.viewport_indicator_color())
+        };
+
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_draw_color(bg_color);
+            canvas.clear();
+        })?;
+        self.view_grid.fill_population(self.widget_base.canvas.clone(), texture, &self.viewport,
+            &self.widget_base.texture_creator, ColorMode::Plain)?;
+
+        let indicator = self.indicator_rect();
+        let mut result = Ok(());
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_draw_color(indicator_color);
+            result = canvas.draw_rect(indicator);
+        })?;
+        result?;
+        Ok(())
+    }
+
+    fn move_camera(&mut self, x: i32, y: i32) {
+        let center = self.viewport.screen_to_world((x as f64, y as f64));
+        self.camera_viewport.borrow_mut().set_center(center);
+    }
+}
+
+impl widget_base::Widget for Minimap {
+    fn widget_base(&self) -> &widget_base::WidgetBase {
+        &self.widget_base
+    }
+
+    fn rendering_on_texture(&mut self, width: u32, height: u32)
+        -> error::Result<&render::Texture>
+    {
+        widget_base::drop_stale_cache(&mut self.texture_cache, width, height);
+        let revision = self.universe.borrow().grid().revision();
+        let camera_viewport = *self.camera_viewport.borrow();
+        let state = Some((revision, camera_viewport));
+        let revision_changed = self.drawn_state.is_none_or(|(drawn, _)| drawn != revision);
+        // 拖拽时保持缩放不变, 避免显示范围跟着指示框跳动
+        if !self.dragging || self.viewport.size() != (width, height) {
+            self.update_viewport(width, height, revision_changed);
+        }
+
+        let mut texture = match self.texture_cache.take() {
+            Some(texture) => texture,
+            None => {
+                self.drawn_state = None;
+                self.create_new_texture(width, height)?
+            },
+        };
+        if self.drawn_state != state {
+            self.fill_base_element(&mut texture)?;
+            self.drawn_state = state;
+        }
+
+        Ok(self.texture_cache.insert(texture))
+    }
+
+    fn invalidate(&mut self) {
+        widget_base::destroy_cache(&mut self.texture_cache);
+    }
+
+    fn handle_event(&mut self, event: &WidgetEvent) -> EventResult {
+        match *event {
+            WidgetEvent::MouseButtonDown { x, y, button: MouseButton::Left, .. } => {
+                self.dragging = true;
+                self.move_camera(x, y);
+                EventResult::Capture
+            },
+            WidgetEvent::MouseMotion { x, y, .. } if self.dragging => {
+                self.move_camera(x, y);
+                EventResult::Handled
+            },
+            WidgetEvent::MouseButtonUp { button: MouseButton::Left, .. } if self.dragging => {
+                self.dragging = false;
+                EventResult::Handled
+            },
+            _ => EventResult::Ignored,
+        }
+    }
+}
@@ -1,3 +1,19 @@
 pub mod camera;
 pub mod widget_base;
 pub mod layout;
+pub mod layout_spec;
+pub mod constraint;
+pub mod event;
+pub mod viewport;
+pub mod edit_tool;
+pub mod color_mode;
+pub mod selection;
+pub mod follow;
+pub mod minimap;
+pub mod population_graph;
+pub mod pattern_browser;
+pub mod command;
+pub mod console;
+pub mod keymap_help;
+pub mod file_dialog;
+pub mod tooltip;
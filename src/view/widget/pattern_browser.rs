@@ -0,0 +1,324 @@
+use sdl2::{
+    render::{
+        self,
+        Texture,
+    },
+    rect::Rect,
+    mouse::MouseButton,
+};
+use std::collections::HashSet;
+use crate::{
+    utils::error,
+    model::{
+        pattern::Pattern,
+        library::RcPatternLibrary,
+    },
+};
+use super::{
+    widget_base,
+    camera::RcPasteRequest,
+    event::{
+        WidgetEvent,
+        EventResult,
+    },
+};
+
+static THUMBNAIL_SIZE: u32 = 40;
+static MAX_THUMBNAIL_SCALE: f64 = 8.;
+static PADDING: i32 = 4;
+static SCROLL_ROWS: f64 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Category(usize),
+    Entry(usize, usize),
+}
+
+/// 按分类列出图案库中的图案及缩略图, 点击分类折叠/展开, 点击图案将其交给相机放置
+pub struct PatternBrowser {
+    widget_base: widget_base::WidgetBase,
+    library: RcPatternLibrary,
+    paste_request: RcPasteRequest,
+    texture_cache: Option<Texture>,
+    collapsed: HashSet<String>,
+    scroll: i32,
+    hovered: Option<Row>,
+    selected: Option<Row>,
+    dirty: bool,
+    drawn_revision: Option<u64>,
+}
+
+impl PatternBrowser {
+    pub fn new(widget_base: widget_base::WidgetBase, library: RcPatternLibrary,
+        paste_request: RcPasteRequest) -> Self
+    {
+        PatternBrowser {
+            widget_base,
+            library,
+            paste_request,
+            texture_cache: None,
+            collapsed: HashSet::new(),
+            scroll: 0,
+            hovered: None,
+            selected: None,
+            dirty: true,
+            drawn_revision: None,
+        }
+    }
+
+    fn header_height(&self) -> i32 {
+        self.widget_base.text_renderer.borrow().line_height() as i32 + 2 * PADDING
+    }
+
+    fn entry_height(&self) -> i32 {
+        THUMBNAIL_SIZE as i32 + 2 * PADDING
+    }
+
+    /// 展开状态下可见的所有行及其顶端的y坐标(未计滚动)
+    fn rows(&self) -> Vec<(Row, i32)> {
+        let library = self.library.borrow();
+        let (header_height, entry_height) = (self.header_height(), self.entry_height());
+        let mut rows = Vec::new();
+        let mut y = 0;
+        for (i, category) in library.categories().iter().enumerate() {
+            rows.push((Row::Category(i), y));
+            y += header_height;
+            if self.collapsed.contains(category.name()) {
+                continue;
+            }
+            for j in 0..category.entries().len() {
+                rows.push((Row::Entry(i, j), y));
+                y += entry_height;
+            }
+        }
+        rows
+    }
+
+    fn row_height(&self, row: Row) -> i32 {
+        match row {
+            Row::Category(_) => self.header_height(),
+            Row::Entry(..) => self.entry_height(),
+        }
+    }
+
+    fn content_height(&self) -> i32 {
+        self.rows()
+            .last()
+            .map_or(0, |(row, y)| y + self.row_height(*row))
+    }
+
+    fn row_at(&self, y: i32) -> Option<Row> {
+        let y = y + self.scroll;
+        self.rows()
+            .into_iter()
+            .find(|(row, top)| *top <= y && y < top + self.row_height(*row))
+            .map(|(row, _)| row)
+    }
+
+    fn scroll_by(&mut self, delta: i32) {
+        let height = self.texture_cache
+            .as_ref()
+            .map_or(0, |texture| texture.query().height as i32);
+        let max_scroll = (self.content_height() - height).max(0);
+        self.scroll = (self.scroll + delta).clamp(0, max_scroll);
+        self.dirty = true;
+    }
+
+    fn activate(&mut self, row: Row) {
+        match row {
+            Row::Category(i) => {
+                let Some(name) = self.library
+                    .borrow()
+                    .categories()
+                    .get(i)
+                    .map(|category| category.name().to_string()) else {
+                    return;
+                };
+                if !self.collapsed.remove(&name) {
+                    self.collapsed.insert(name);
+                }
+                self.scroll_by(0);
+            },
+            Row::Entry(i, j) => {
+                let pattern = self.library
+                    .borrow()
+                    .categories()
+                    .get(i)
+                    .and_then(|category| category.entries().get(j))
+                    .map(|entry| entry.pattern().clone());
+                *self.paste_request.borrow_mut() = pattern;
+                self.selected = Some(row);
+            },
+        }
+        self.dirty = true;
+    }
+
+    fn fill_base_element(&self, texture: &mut Texture, width: u32, height: u32)
+        -> error::Result<()>
+    {
+        let (bg_color, highlight_color, text_color, cell_color) = {
+            let theme_manager = self.widget_base.theme_manager.borrow();
+            (theme_manager.panel_bg_color(), theme_manager.panel_highlight_color(),
+                theme_manager.text_color(), theme_manager.cell_alive_color())
+        };
+        let rows: Vec<_> = self.rows()
+            .into_iter()
+            .map(|(row, y)| (row, y - self.scroll))
+            .filter(|(row, y)| y + self.row_height(*row) > 0 && *y < height as i32)
+            .collect();
+        let library = self.library.borrow();
+        let categories = library.categories();
+        let locale = self.widget_base.locale.borrow();
+
+        let mut result = Ok(());
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_draw_color(bg_color);
+            canvas.clear();
+            for (row, y) in rows {
+                if Some(row) == self.hovered || Some(row) == self.selected {
+                    canvas.set_draw_color(highlight_color);
+                    let rect = Rect::new(0, y, width, self.row_height(row) as u32);
+                    if let Err(e) = canvas.fill_rect(rect) {
+                        result = Err(e.into());
+                        return;
+                    }
+                }
+                let drawn = match row {
+                    Row::Category(i) => {
+                        let category = &categories[i];
+                        let marker = if self.collapsed.contains(category.name()) { "+" } else { "-" };
+                        let name = if category.is_builtin() {
+                            locale.text(&format!("library.{}", category.name())).to_string()
+                        } else {
+                            category.name().to_string()
+                        };
+                        let text = format!("{} {} ({})", marker, name, category.entries().len());
+                        self.widget_base
+                            .draw_text(canvas, &text, (PADDING, y + PADDING), text_color)
+                            .map(|_| ())
+                    },
+                    Row::Entry(i, j) => {
+                        let entry = &categories[i].entries()[j];
+                        let pattern = entry.pattern();
+                        let thumbnail = Rect::new(2 * PADDING, y + PADDING, THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+                        canvas.set_draw_color(cell_color);
+                        let text = format!("{} ({}x{})", entry.name(), pattern.width(), pattern.height());
+                        let line_height = self.widget_base.text_renderer.borrow().line_height() as i32;
+                        let text_pos = (thumbnail.right() + 2 * PADDING,
+                            thumbnail.center().y() - line_height / 2);
+                        canvas
+                            .fill_rects(&thumbnail_rects(pattern, thumbnail))
+                            .map_err(|e| e.into())
+                            .and_then(|_| self.widget_base.draw_text(canvas, &text, text_pos, text_color))
+                            .map(|_| ())
+                    },
+                };
+                if drawn.is_err() {
+                    result = drawn;
+                    return;
+                }
+            }
+        })?;
+        result?;
+        Ok(())
+    }
+}
+
+/// 将图案缩放后居中放入`bounds`, 每个cell至少占一个像素
+pub fn thumbnail_rects(pattern: &Pattern, bounds: Rect) -> Vec<Rect> {
+    let (width, height) = (pattern.width().max(1) as f64, pattern.height().max(1) as f64);
+    let scale = (bounds.width() as f64 / width)
+        .min(bounds.height() as f64 / height)
+        .min(MAX_THUMBNAIL_SCALE);
+    let x0 = bounds.x() as f64 + (bounds.width() as f64 - width * scale) / 2.;
+    let y0 = bounds.y() as f64 + (bounds.height() as f64 - height * scale) / 2.;
+    let size = (scale.ceil() as u32).max(1);
+    pattern.cells()
+        .iter()
+        .map(|(x, y)| {
+            let x = (x0 + *x as f64 * scale).floor() as i32;
+            let y = (y0 + *y as f64 * scale).floor() as i32;
+            Rect::new(x, y, size, size)
+        })
+        .collect()
+}
+
+impl widget_base::Widget for PatternBrowser {
+    fn widget_base(&self) -> &widget_base::WidgetBase {
+        &self.widget_base
+    }
+
+    fn rendering_on_texture(&mut self, width: u32, height: u32)
+        -> error::Result<&render::Texture>
+    {
+        widget_base::drop_stale_cache(&mut self.texture_cache, width, height);
+        let revision = self.library.borrow().revision();
+        if self.drawn_revision != Some(revision) {
+            // 图案库变化后行号失效
+            self.drawn_revision = Some(revision);
+            self.hovered = None;
+            self.selected = None;
+            self.dirty = true;
+        }
+
+        let mut texture = match self.texture_cache.take() {
+            Some(texture) => texture,
+            None => {
+                self.dirty = true;
+                self.create_new_texture(width, height)?
+            },
+        };
+        if self.dirty {
+            self.fill_base_element(&mut texture, width, height)?;
+            self.dirty = false;
+        }
+
+        Ok(self.texture_cache.insert(texture))
+    }
+
+    fn invalidate(&mut self) {
+        widget_base::destroy_cache(&mut self.texture_cache);
+    }
+
+    fn handle_event(&mut self, event: &WidgetEvent) -> EventResult {
+        match *event {
+            WidgetEvent::MouseMotion { y, .. } => {
+                let hovered = self.row_at(y);
+                self.dirty |= self.hovered != hovered;
+                self.hovered = hovered;
+                EventResult::Handled
+            },
+            WidgetEvent::MouseLeave => {
+                self.dirty |= self.hovered.is_some();
+                self.hovered = None;
+                EventResult::Handled
+            },
+            WidgetEvent::MouseWheel { scroll_y, .. } => {
+                let delta = -scroll_y as f64 * SCROLL_ROWS * self.entry_height() as f64;
+                self.scroll_by(delta.round() as i32);
+                EventResult::Handled
+            },
+            WidgetEvent::MouseButtonDown { y, button: MouseButton::Left, .. } => {
+                if let Some(row) = self.row_at(y) {
+                    self.activate(row);
+                }
+                EventResult::Handled
+            },
+            _ => EventResult::Ignored,
+        }
+    }
+
+    fn tooltip(&self, _x: i32, y: i32) -> Option<String> {
+        let library = self.library.borrow();
+        let locale = self.widget_base.locale.borrow();
+        match self.row_at(y)? {
+            Row::Category(_) => Some(locale.text("pattern_browser.category").to_string()),
+            Row::Entry(i, j) => {
+                let entry = library.categories().get(i)?.entries().get(j)?;
+                let pattern = entry.pattern();
+                Some(locale.format("pattern_browser.entry", &[&entry.name(),
+                    &pattern.width(), &pattern.height(), &pattern.population()]))
+            },
+        }
+    }
+}
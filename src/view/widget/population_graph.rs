@@ -0,0 +1,333 @@
+use sdl2::{
+    render::{
+        self,
+        Texture,
+    },
+    rect::{
+        Point,
+        Rect,
+    },
+    pixels::Color,
+    keyboard::{
+        Keycode,
+        Mod,
+    },
+};
+use crate::{
+    utils::error,
+    model::{
+        universe::RcUniverse,
+        statistics::GenerationStats,
+    },
+};
+use super::{
+    super::keymap::{
+        Action,
+        Scope,
+    },
+    widget_base,
+    event::{
+        WidgetEvent,
+        EventResult,
+    },
+};
+
+static DEFAULT_SPAN: u64 = 200;
+static MIN_SPAN: u64 = 16;
+static MAX_SPAN: u64 = 1 << 14;
+static SPAN_STEP: f64 = 1.25;
+static SCROLL_STEP: f64 = 0.1;
+static PADDING: i32 = 4;
+
+type Series = (Color, fn(&GenerationStats) -> usize);
+
+/// 绘制最近若干代的种群数量曲线, 可选显示出生数和死亡数
+/// 滚轮缩放显示的代数范围, 横向滚动或左右方向键向前翻看, 悬停时显示该代的数值
+pub struct PopulationGraph {
+    widget_base: widget_base::WidgetBase,
+    universe: RcUniverse,
+    texture_cache: Option<Texture>,
+    span: u64,
+    /// 显示窗口的右端距最新一代的代数, 为0时跟随最新一代
+    offset: u64,
+    show_births: bool,
+    show_deaths: bool,
+    hover_x: Option<i32>,
+    dirty: bool,
+    drawn_state: Option<(u64, u64)>,
+}
+
+impl PopulationGraph {
+    pub fn new(widget_base: widget_base::WidgetBase, universe: RcUniverse) -> Self {
+        PopulationGraph {
+            widget_base,
+            universe,
+            texture_cache: None,
+            span: DEFAULT_SPAN,
+            offset: 0,
+            show_births: true,
+            show_deaths: true,
+            hover_x: None,
+            dirty: true,
+            drawn_state: None,
+        }
+    }
+
+    /// 当前显示的代数区间 [start, end]
+    fn window(&self, latest: u64) -> (u64, u64) {
+        let end = latest.saturating_sub(self.offset).max(self.span.min(latest));
+        (end.saturating_sub(self.span), end)
+    }
+
+    fn plot_rect(&self, width: u32, height: u32) -> Rect {
+        let line_height = self.widget_base.text_renderer.borrow().line_height() as i32;
+        let top = PADDING + line_height;
+        let bottom = height as i32 - PADDING - line_height;
+        Rect::new(PADDING, top, (width as i32 - 2 * PADDING).max(1) as u32,
+            (bottom - top).max(1) as u32)
+    }
+
+    fn visible_samples(&self, start: u64, end: u64) -> Vec<GenerationStats> {
+        self.universe
+            .borrow()
+            .statistics()
+            .samples()
+            .iter()
+            .filter(|stats| start <= stats.generation && stats.generation <= end)
+            .copied()
+            .collect()
+    }
+
+    fn fill_base_element(&self, texture: &mut Texture, width: u32, height: u32)
+        -> error::Result<()>
+    {
+        let (bg_color, axis_color, text_color, series_colors) = {
+            let theme_manager = self.widget_base.theme_manager.borrow();
+            (theme_manager.graph_bg_color(), theme_manager.graph_axis_color(),
+                theme_manager.text_color(), [theme_manager.population_color(),
+                theme_manager.births_color(), theme_manager.deaths_color()])
+        };
+        let latest = self.universe.borrow().generation();
+        let (start, end) = self.window(latest);
+        let samples = self.visible_samples(start, end);
+        let plot = self.plot_rect(width, height);
+
+        let mut series: Vec<Series> =
+            vec![(series_colors[0], |stats| stats.population)];
+        if self.show_births {
+            series.push((series_colors[1], |stats| stats.births));
+        }
+        if self.show_deaths {
+            series.push((series_colors[2], |stats| stats.deaths));
+        }
+        let max_value = samples
+            .iter()
+            .flat_map(|stats| series.iter().map(move |(_, value)| value(stats)))
+            .max()
+            .unwrap_or(0);
+        let y_max = nice_ceil(max_value);
+
+        let span = (end - start).max(1) as f64;
+        let to_point = |generation: u64, value: usize| {
+            let x = plot.left() as f64 + (generation - start) as f64 / span * (plot.width() - 1) as f64;
+            let y = plot.bottom() as f64 - 1. - value as f64 / y_max as f64 * (plot.height() - 1) as f64;
+            Point::new(x.round() as i32, y.round() as i32)
+        };
+        let hovered = self.hover_x
+            .and_then(|x| nearest_sample(&samples, start, span, plot, x));
+
+        let mut result = Ok(());
+        self.widget_base.canvas.borrow_mut().with_texture_canvas(texture, |canvas| {
+            canvas.set_draw_color(bg_color);
+            canvas.clear();
+            canvas.set_draw_color(axis_color);
+            result = canvas
+                .draw_line(plot.bottom_left().offset(0, -1), plot.bottom_right().offset(-1, -1))
+                .and_then(|_| canvas.draw_line(plot.top_left(), plot.bottom_left().offset(0, -1)));
+            if result.is_err() {
+                return;
+            }
+
+            for (color, value) in series.iter().rev() {
+                let points: Vec<_> = samples
+                    .iter()
+                    .map(|stats| to_point(stats.generation, value(stats)))
+                    .collect();
+                canvas.set_draw_color(*color);
+                result = match points.len() {
+                    0 => Ok(()),
+                    1 => canvas.draw_point(points[0]),
+                    _ => canvas.draw_lines(points.as_slice()),
+                };
+                if result.is_err() {
+                    return;
+                }
+            }
+
+            if let Some(stats) = hovered {
+                let x = to_point(stats.generation, 0).x();
+                canvas.set_draw_color(axis_color);
+                result = canvas.draw_line((x, plot.top()), (x, plot.bottom() - 1));
+                if result.is_err() {
+                    return;
+                }
+            }
+
+            let labels = [
+                (y_max.to_string(), (PADDING, 0)),
+                (start.to_string(), (PADDING, plot.bottom())),
+            ];
+            for (text, pos) in labels {
+                if let Err(e) = self.widget_base.draw_text(canvas, &text, pos, text_color) {
+                    result = Err(e.to_string());
+                    return;
+                }
+            }
+            let end_label = end.to_string();
+            let (label_width, _) = self.widget_base.text_renderer.borrow().size_of(&end_label);
+            let pos = (plot.right() - label_width as i32, plot.bottom());
+            if let Err(e) = self.widget_base.draw_text(canvas, &end_label, pos, text_color) {
+                result = Err(e.to_string());
+                return;
+            }
+
+            if let Some(stats) = hovered {
+                let text = self.widget_base.locale.borrow().format("population_graph.hovered",
+                    &[&stats.generation, &stats.population, &stats.births, &stats.deaths]);
+                let (text_width, _) = self.widget_base.text_renderer.borrow().size_of(&text);
+                let x = (plot.right() - text_width as i32).max(PADDING);
+                if let Err(e) = self.widget_base.draw_text(canvas, &text, (x, 0), text_color) {
+                    result = Err(e.to_string());
+                }
+            }
+        })?;
+        result?;
+        Ok(())
+    }
+
+    fn set_span(&mut self, span: u64) {
+        self.span = span.clamp(MIN_SPAN, MAX_SPAN);
+        self.dirty = true;
+    }
+
+    /// `delta`为正时向更早的代数翻看
+    fn scroll(&mut self, delta: i64) {
+        let latest = self.universe.borrow().generation();
+        let max_offset = latest.saturating_sub(self.span);
+        self.offset = self.offset
+            .saturating_add_signed(delta)
+            .min(max_offset);
+        self.dirty = true;
+    }
+
+    fn handle_key(&mut self, keycode: Keycode, keymod: Mod) -> EventResult {
+        let step = ((self.span as f64 * SCROLL_STEP) as i64).max(1);
+        let action = self.widget_base.keymap.borrow().key_action(keycode, keymod, Scope::Graph);
+        match action {
+            Some(Action::GraphToggleBirths) => {
+                self.show_births = !self.show_births;
+                self.dirty = true;
+            },
+            Some(Action::GraphToggleDeaths) => {
+                self.show_deaths = !self.show_deaths;
+                self.dirty = true;
+            },
+            Some(Action::GraphScrollBack) => self.scroll(step),
+            Some(Action::GraphScrollForward) => self.scroll(-step),
+            Some(Action::GraphLatest) => self.scroll(i64::MIN),
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Handled
+    }
+}
+
+/// 向上取整到1、2、5乘以10的幂
+fn nice_ceil(value: usize) -> usize {
+    let mut magnitude = 1;
+    loop {
+        for factor in [1, 2, 5] {
+            if value <= factor * magnitude {
+                return factor * magnitude;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+fn nearest_sample(samples: &[GenerationStats], start: u64, span: f64, plot: Rect, x: i32)
+    -> Option<GenerationStats>
+{
+    if x < plot.left() || x >= plot.right() {
+        return None;
+    }
+    let ratio = (x - plot.left()) as f64 / (plot.width() - 1).max(1) as f64;
+    let generation = start + (ratio * span).round() as u64;
+    let index = samples.partition_point(|stats| stats.generation < generation);
+    [index.checked_sub(1), Some(index)]
+        .into_iter()
+        .flatten()
+        .filter_map(|index| samples.get(index))
+        .min_by_key(|stats| stats.generation.abs_diff(generation))
+        .copied()
+}
+
+impl widget_base::Widget for PopulationGraph {
+    fn widget_base(&self) -> &widget_base::WidgetBase {
+        &self.widget_base
+    }
+
+    fn rendering_on_texture(&mut self, width: u32, height: u32)
+        -> error::Result<&render::Texture>
+    {
+        widget_base::drop_stale_cache(&mut self.texture_cache, width, height);
+        let state = {
+            let universe = self.universe.borrow();
+            Some((universe.grid().revision(), universe.generation()))
+        };
+
+        let mut texture = match self.texture_cache.take() {
+            Some(texture) => texture,
+            None => {
+                self.dirty = true;
+                self.create_new_texture(width, height)?
+            },
+        };
+        if self.dirty || self.drawn_state != state {
+            self.fill_base_element(&mut texture, width, height)?;
+            self.drawn_state = state;
+            self.dirty = false;
+        }
+
+        Ok(self.texture_cache.insert(texture))
+    }
+
+    fn invalidate(&mut self) {
+        widget_base::destroy_cache(&mut self.texture_cache);
+    }
+
+    fn handle_event(&mut self, event: &WidgetEvent) -> EventResult {
+        match *event {
+            WidgetEvent::MouseMotion { x, .. } => {
+                self.dirty |= self.hover_x != Some(x);
+                self.hover_x = Some(x);
+                EventResult::Handled
+            },
+            WidgetEvent::MouseLeave => {
+                self.dirty |= self.hover_x.is_some();
+                self.hover_x = None;
+                EventResult::Handled
+            },
+            WidgetEvent::MouseWheel { scroll_x, scroll_y, .. } => {
+                if scroll_y != 0. {
+                    self.set_span((self.span as f64 / SPAN_STEP.powf(scroll_y as f64)) as u64);
+                }
+                if scroll_x != 0. {
+                    self.scroll((-scroll_x as f64 * self.span as f64 * SCROLL_STEP) as i64);
+                }
+                EventResult::Handled
+            },
+            WidgetEvent::KeyDown { keycode, keymod, .. } => self.handle_key(keycode, keymod),
+            _ => EventResult::Ignored,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+/// 世界坐标下的矩形选区, 闭区间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+impl Selection {
+    pub fn from_corners(a: (i32, i32), b: (i32, i32)) -> Self {
+        Selection {
+            min: (a.0.min(b.0), a.1.min(b.1)),
+            max: (a.0.max(b.0), a.1.max(b.1)),
+        }
+    }
+
+    /// 以`origin`为左上角, 大小为`width` x `height`的选区, 大小至少为1
+    pub fn with_size(origin: (i32, i32), width: i32, height: i32) -> Self {
+        let max = (origin.0 + width.max(1) - 1, origin.1 + height.max(1) - 1);
+        Selection { min: origin, max }
+    }
+
+    pub fn origin(&self) -> (i32, i32) {
+        self.min
+    }
+
+    /// (min_x, min_y, max_x, max_y)
+    pub fn bounds(&self) -> (i32, i32, i32, i32) {
+        (self.min.0, self.min.1, self.max.0, self.max.1)
+    }
+
+    pub fn translated(&self, dx: i32, dy: i32) -> Self {
+        Selection {
+            min: (self.min.0 + dx, self.min.1 + dy),
+            max: (self.max.0 + dx, self.max.1 + dy),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use sdl2::{
+    render::BlendMode,
+    rect::Rect,
+};
+use std::time::Instant;
+use crate::utils::error;
+use super::{
+    widget_base,
+    layout::Layout,
+    event::WidgetEvent,
+};
+
+static PADDING: i32 = 4;
+/// 提示框相对光标的偏移, 避免被光标遮挡
+static CURSOR_OFFSET: (i32, i32) = (16, 20);
+
+/// 光标在widget上静止一段时间(主题中的`tooltip_delay`)后, 在光标旁显示该widget提供的提示
+/// 不属于布局树, 在布局绘制完成后直接画在窗口上
+pub struct Tooltip {
+    widget_base: widget_base::WidgetBase,
+    pointer: Option<(i32, i32)>,
+    still_since: Instant,
+}
+
+impl Tooltip {
+    pub fn new(widget_base: widget_base::WidgetBase) -> Self {
+        Tooltip { widget_base, pointer: None, still_since: Instant::now() }
+    }
+
+    /// 接收所有窗口事件(包括被布局处理的), 光标移动或有其他输入时重新计时
+    pub fn track(&mut self, event: &WidgetEvent) {
+        match event {
+            WidgetEvent::MouseMotion { x, y, .. } => {
+                self.pointer = Some((*x, *y));
+                self.still_since = Instant::now();
+            },
+            WidgetEvent::MouseLeave => self.pointer = None,
+            WidgetEvent::MouseButtonDown { .. }
+            | WidgetEvent::MouseWheel { .. }
+            | WidgetEvent::KeyDown { .. } => self.still_since = Instant::now(),
+            _ => {},
+        }
+    }
+
+    /// 每帧重新向布局询问提示文字, 内容随模拟实时更新; 过长的行在半个窗口宽处折行
+    pub fn rendering(&self, layout: &Layout, window: Rect) -> error::Result<()> {
+        let Some(pointer) = self.pointer else {
+            return Ok(());
+        };
+        let (delay, bg_color, text_color) = {
+            let theme_manager = self.widget_base.theme_manager.borrow();
+            (theme_manager.tooltip_delay(), theme_manager.tooltip_bg_color(),
+                theme_manager.tooltip_text_color())
+        };
+        if self.still_since.elapsed() < delay {
+            return Ok(());
+        }
+        let Some(text) = layout.tooltip(pointer) else {
+            return Ok(());
+        };
+
+        let (lines, width, line_height) = {
+            let text_renderer = self.widget_base.text_renderer.borrow();
+            let lines = text_renderer.wrap(&text, window.width() / 2);
+            let width = lines
+                .iter()
+                .map(|line| text_renderer.size_of(line).0)
+                .max()
+                .unwrap_or(0);
+            (lines, width as i32, text_renderer.line_height() as i32)
+        };
+        let size = (width + 2 * PADDING, lines.len() as i32 * line_height + 2 * PADDING);
+        // 靠近窗口右侧或底部时翻到光标的另一侧
+        let x = match pointer.0 + CURSOR_OFFSET.0 {
+            x if x + size.0 > window.right() => (pointer.0 - size.0).max(window.x()),
+            x => x,
+        };
+        let y = match pointer.1 + CURSOR_OFFSET.1 {
+            y if y + size.1 > window.bottom() => (pointer.1 - size.1).max(window.y()),
+            y => y,
+        };
+
+        let mut canvas = self.widget_base.canvas.borrow_mut();
+        let blend_mode = canvas.blend_mode();
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(bg_color);
+        let filled = canvas.fill_rect(Rect::new(x, y, size.0 as u32, size.1 as u32));
+        canvas.set_blend_mode(blend_mode);
+        filled?;
+        for (i, line) in lines.iter().enumerate() {
+            let pos = (x + PADDING, y + PADDING + i as i32 * line_height);
+            self.widget_base.draw_text(&mut canvas, line, pos, text_color)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use sdl2::rect::Rect;
+use crate::utils::error;
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+pub type RcViewport = Rc<RefCell<Viewport>>;
+
+pub static CELL_PIXEL_UPPER: usize = 128;
+static CELL_PIXEL_LOWER: f64 = 1. / 256.;
+/// 缩放到适合时四周留出的cell数
+static FIT_MARGIN: f64 = 2.;
+
+/// 平移与缩放的过渡, 中心线性插值, 缩放按对数插值使放大与缩小的速度一致
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transition {
+    from: ((f64, f64), f64),
+    to: ((f64, f64), f64),
+    start: Instant,
+    duration: Duration,
+}
+
+impl Transition {
+    /// 先加速后减速
+    fn progress(&self, now: Instant) -> f64 {
+        let t = (now.saturating_duration_since(self.start).as_secs_f64() / self.duration.as_secs_f64()).min(1.);
+        if t < 0.5 { 4. * t * t * t } else { 1. - (2. - 2. * t).powi(3) / 2. }
+    }
+}
+
+/// 相机的视口, 负责世界坐标(单位: cell)与屏幕坐标(单位: 像素, 相对于widget左上角)的换算
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    center: (f64, f64),
+    scale: f64,     // 一个cell对应几个像素
+    width: u32,
+    height: u32,
+    transition: Option<Transition>,
+}
+
+impl Viewport {
+    pub fn new(center: (f64, f64), scale: f64) -> error::Result<Self> {
+        if !(CELL_PIXEL_LOWER..=CELL_PIXEL_UPPER as f64).contains(&scale) {
+            return Err(String::from("Invalid Scale").into());
+        }
+        Ok(Viewport { center, scale, width: 0, height: 0, transition: None })
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// 直接修改视口时中止正在进行的过渡
+    pub fn set_center(&mut self, center: (f64, f64)) {
+        self.transition = None;
+        self.center = center;
+    }
+
+    /// 过渡结束后的中心与缩放, 没有过渡时为当前值
+    pub fn target(&self) -> ((f64, f64), f64) {
+        self.transition.map_or((self.center, self.scale), |transition| transition.to)
+    }
+
+    /// 在`duration`内平滑地移动到`center`并缩放到`scale`, `duration`为0时立即完成
+    pub fn animate_to(&mut self, center: (f64, f64), scale: f64, duration: Duration) {
+        let scale = scale.clamp(CELL_PIXEL_LOWER, CELL_PIXEL_UPPER as f64);
+        if duration.is_zero() {
+            self.transition = None;
+            (self.center, self.scale) = (center, scale);
+            return;
+        }
+        self.transition = Some(Transition {
+            from: (self.center, self.scale),
+            to: (center, scale),
+            start: Instant::now(),
+            duration,
+        });
+    }
+
+    /// 推进过渡, 每帧绘制前调用
+    pub fn advance(&mut self, now: Instant) {
+        let Some(transition) = self.transition else {
+            return;
+        };
+        let t = transition.progress(now);
+        let ((from_center, from_scale), (to_center, to_scale)) = (transition.from, transition.to);
+        self.center = (
+            from_center.0 + (to_center.0 - from_center.0) * t,
+            from_center.1 + (to_center.1 - from_center.1) * t,
+        );
+        self.scale = from_scale * (to_scale / from_scale).powf(t);
+        if t >= 1. {
+            self.transition = None;
+        }
+    }
+
+    pub fn screen_to_world(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            self.center.0 + (x - self.width as f64 / 2.) / self.scale,
+            self.center.1 + (y - self.height as f64 / 2.) / self.scale,
+        )
+    }
+
+    pub fn world_to_screen(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            (x - self.center.0) * self.scale + self.width as f64 / 2.,
+            (y - self.center.1) * self.scale + self.height as f64 / 2.,
+        )
+    }
+
+    /// 屏幕上的点所在的cell
+    pub fn screen_to_cell(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (wx, wy) = self.screen_to_world((x as f64, y as f64));
+        (wx.floor() as i32, wy.floor() as i32)
+    }
+
+    /// cell在屏幕上占据的矩形, 相邻cell的矩形恰好拼接, 不重叠也不留缝
+    pub fn cell_to_screen_rect(&self, (x, y): (i32, i32)) -> Rect {
+        let (left, top) = self.world_to_screen((x as f64, y as f64));
+        let (right, bottom) = self.world_to_screen((x as f64 + 1., y as f64 + 1.));
+        let (left, top) = (left.floor() as i32, top.floor() as i32);
+        let (right, bottom) = (right.floor() as i32, bottom.floor() as i32);
+        Rect::new(left, top, (right - left).max(1) as u32, (bottom - top).max(1) as u32)
+    }
+
+    /// 视口内可见的cell范围 (min_x, min_y, max_x, max_y), 闭区间
+    pub fn visible_cells(&self) -> (i32, i32, i32, i32) {
+        let (min_x, min_y) = self.screen_to_world((0., 0.));
+        let (max_x, max_y) = self.screen_to_world((self.width as f64, self.height as f64));
+        (min_x.floor() as i32, min_y.floor() as i32, max_x.ceil() as i32, max_y.ceil() as i32)
+    }
+
+    /// 调整中心与缩放, 使世界坐标下的矩形 (min_x, min_y, max_x, max_y) 完整显示在视口内
+    pub fn fit(&mut self, region: (f64, f64, f64, f64)) {
+        let (center, scale) = self.fit_target(region);
+        self.animate_to(center, scale, Duration::ZERO);
+    }
+
+    fn fit_target(&self, (min_x, min_y, max_x, max_y): (f64, f64, f64, f64)) -> ((f64, f64), f64) {
+        let width = (max_x - min_x).max(1.);
+        let height = (max_y - min_y).max(1.);
+        let scale = (self.width as f64 / width).min(self.height as f64 / height);
+        (((min_x + max_x) / 2., (min_y + max_y) / 2.), scale)
+    }
+
+    /// 平滑地缩放到显示`cells` (min_x, min_y, max_x, max_y, 闭区间) 中的所有cell, 为None时回到原点
+    pub fn zoom_to_fit(&mut self, cells: Option<(i32, i32, i32, i32)>, duration: Duration) {
+        let (center, scale) = match cells {
+            Some((min_x, min_y, max_x, max_y)) => self.fit_target((
+                min_x as f64 - FIT_MARGIN, min_y as f64 - FIT_MARGIN,
+                max_x as f64 + 1. + FIT_MARGIN, max_y as f64 + 1. + FIT_MARGIN,
+            )),
+            None => ((0., 0.), self.target().1),
+        };
+        self.animate_to(center, scale, duration);
+    }
+
+    /// 按屏幕像素平移, 正方向与拖拽方向相反
+    pub fn pan_pixels(&mut self, dx: f64, dy: f64) {
+        self.transition = None;
+        self.center.0 += dx / self.scale;
+        self.center.1 += dy / self.scale;
+    }
+
+    /// 以屏幕上的`anchor`为锚点缩放, 锚点下的世界坐标保持不变
+    pub fn zoom_at(&mut self, factor: f64, anchor: (f64, f64)) {
+        self.transition = None;
+        let world_anchor = self.screen_to_world(anchor);
+        self.scale = (self.scale * factor).clamp(CELL_PIXEL_LOWER, CELL_PIXEL_UPPER as f64);
+        self.center = (
+            world_anchor.0 - (anchor.0 - self.width as f64 / 2.) / self.scale,
+            world_anchor.1 - (anchor.1 - self.height as f64 / 2.) / self.scale,
+        );
+    }
+}
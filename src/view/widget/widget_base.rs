@@ -1,8 +1,14 @@
-use crate::view::theme;
+use crate::view::{
+    theme,
+    text,
+    locale,
+    keymap,
+};
 use sdl2::{
     video,
     render,
     pixels,
+    rect,
 };
 
 use std::{
@@ -11,25 +17,44 @@ use std::{
 };
 
 use crate::utils::error;
+use super::event;
 type WindowCanvas = render::Canvas<video::Window>;
 
 pub struct WidgetBase {
     pub(super) theme_manager: Rc<RefCell<theme::ThemeManager>>,
     pub(super) canvas: Rc<RefCell<WindowCanvas>>,
     pub(super) texture_creator: render::TextureCreator<video::WindowContext>,
+    pub(super) text_renderer: text::RcTextRenderer,
+    pub(super) locale: locale::RcLocaleManager,
+    pub(super) keymap: keymap::RcKeymap,
+}
+
+impl WidgetBase {
+    /// 在`canvas`当前的渲染目标上绘制文字, 通常在`with_texture_canvas`中调用
+    pub fn draw_text(&self, canvas: &mut WindowCanvas, text: &str, pos: (i32, i32),
+        color: pixels::Color) -> error::Result<rect::Rect>
+    {
+        self.text_renderer
+            .borrow()
+            .draw_text(canvas, &self.texture_creator, text, pos, color)
+    }
 }
 
 pub struct WidgetBaseBuilder {
     theme_manager: Rc<RefCell<theme::ThemeManager>>,
     canvas: Rc<RefCell<WindowCanvas>>,
+    text_renderer: text::RcTextRenderer,
+    locale: locale::RcLocaleManager,
+    keymap: keymap::RcKeymap,
 }
 
 impl WidgetBaseBuilder {
     pub fn new(theme_manager: Rc<RefCell<theme::ThemeManager>>,
-           canvas: Rc<RefCell<WindowCanvas>>)
+           canvas: Rc<RefCell<WindowCanvas>>, text_renderer: text::RcTextRenderer,
+           locale: locale::RcLocaleManager, keymap: keymap::RcKeymap)
     -> Self
     {
-        WidgetBaseBuilder{ theme_manager, canvas }
+        WidgetBaseBuilder{ theme_manager, canvas, text_renderer, locale, keymap }
     }
     
     pub fn build(&self) -> WidgetBase {
@@ -41,7 +66,10 @@ impl WidgetBaseBuilder {
         WidgetBase {
             theme_manager: self.theme_manager.clone(),
             canvas: self.canvas.clone(),
-            texture_creator
+            texture_creator,
+            text_renderer: self.text_renderer.clone(),
+            locale: self.locale.clone(),
+            keymap: self.keymap.clone(),
         }
     }
 }
@@ -64,6 +92,41 @@ pub trait Widget {
         Ok(texture)
     }
 
+    fn handle_event(&mut self, _event: &event::WidgetEvent) -> event::EventResult {
+        event::EventResult::Ignored
+    }
+
+    /// 丢弃纹理缓存, 下一帧重新生成
+    fn invalidate(&mut self) {}
+
+    /// 作为覆盖层被切换为显示时调用
+    fn on_show(&mut self) {}
+
+    /// 光标停留在局部坐标`(x, y)`时显示的提示, 多行以换行分隔
+    fn tooltip(&self, _x: i32, _y: i32) -> Option<String> {
+        None
+    }
+}
+
+/// unsafe_textures下Texture在drop时不会被释放, 需要手动销毁
+pub fn destroy_cache(cache: &mut Option<render::Texture>) {
+    if let Some(texture) = cache.take() {
+        // widget持有canvas的Rc, 此时canvas必然存活
+        unsafe { texture.destroy(); }
+    }
+}
+
+/// 分配给widget的尺寸变化时销毁旧缓存
+pub fn drop_stale_cache(cache: &mut Option<render::Texture>, width: u32, height: u32) {
+    let stale = cache
+        .as_ref()
+        .is_some_and(|texture| {
+            let query = texture.query();
+            query.width != width || query.height != height
+        });
+    if stale {
+        destroy_cache(cache);
+    }
 }
 
 pub struct BlankWidget {
@@ -97,6 +160,7 @@ impl Widget for BlankWidget {
     fn rendering_on_texture(&mut self, width: u32, height: u32)
         -> Result<&render::Texture, error::DynError>
     {
+        drop_stale_cache(&mut self.texture_cache, width, height);
         if let Some(ref value) = self.texture_cache {
             return Ok(value)
         }
@@ -114,6 +178,10 @@ impl Widget for BlankWidget {
         
         Ok(self.texture_cache.insert(texture))
     }
+
+    fn invalidate(&mut self) {
+        destroy_cache(&mut self.texture_cache);
+    }
 }
 
 pub struct BlankWidgetBuilder {
@@ -0,0 +1,167 @@
+use sdl2::video::FullscreenType;
+use std::{
+    fs,
+    path::Path,
+};
+use crate::utils::error;
+
+static KEYS: [&str; 7] = ["x", "y", "width", "height", "fullscreen", "present_mode", "max_fps"];
+static MAX_WINDOW_SIZE: i64 = 1 << 14;
+static MAX_FPS: i64 = 1000;
+
+/// 画面的呈现方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentMode {
+    /// 等待垂直同步, 帧率不超过显示器的刷新率
+    VSync,
+    /// 立即呈现, 由`max_fps`限制帧率
+    Immediate,
+}
+
+impl PresentMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PresentMode::VSync => "vsync",
+            PresentMode::Immediate => "immediate",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "vsync" => Some(PresentMode::VSync),
+            "immediate" => Some(PresentMode::Immediate),
+            _ => None,
+        }
+    }
+}
+
+pub fn fullscreen_name(fullscreen: FullscreenType) -> &'static str {
+    match fullscreen {
+        FullscreenType::Off => "off",
+        FullscreenType::Desktop => "desktop",
+        FullscreenType::True => "exclusive",
+    }
+}
+
+fn fullscreen_from_name(name: &str) -> Option<FullscreenType> {
+    match name {
+        "off" => Some(FullscreenType::Off),
+        "desktop" => Some(FullscreenType::Desktop),
+        "exclusive" => Some(FullscreenType::True),
+        _ => None,
+    }
+}
+
+/// 窗口的显示设置以及上次退出时的位置与尺寸, 退出时写回文件
+/// 全屏时保存的是进入全屏前的窗口位置与尺寸
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowState {
+    /// 未指定时居中
+    pub position: Option<(i32, i32)>,
+    /// 指定时优先于主题中的`window_width`与`window_height`, 未指定时使用主题中的尺寸
+    pub size: Option<(u32, u32)>,
+    pub fullscreen: FullscreenType,
+    pub present_mode: PresentMode,
+    /// 0表示不限制, 只在`immediate`下生效
+    pub max_fps: u32,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        WindowState {
+            position: None,
+            size: None,
+            fullscreen: FullscreenType::Off,
+            present_mode: PresentMode::VSync,
+            max_fps: 0,
+        }
+    }
+}
+
+impl WindowState {
+    pub fn load(path: &Path) -> error::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        WindowState::from_toml(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+    }
+
+    /// 格式如下, 所有键都是可选的:
+    /// ```toml
+    /// x = 120
+    /// y = 80
+    /// width = 1280
+    /// height = 720
+    /// fullscreen = "off"          # "off", "desktop" 或 "exclusive"
+    /// present_mode = "vsync"      # "vsync" 或 "immediate"
+    /// max_fps = 144
+    /// ```
+    pub fn from_toml(text: &str) -> error::Result<Self> {
+        let table: toml::Table = text.parse()?;
+        if let Some(key) = table.keys().find(|key| !KEYS.contains(&key.as_str())) {
+            return Err(format!("unknown key `{}`, expected one of {}", key, KEYS.join(", ")).into());
+        }
+        let int = |key: &str, min: i64, max: i64| -> error::Result<Option<i64>> {
+            table
+                .get(key)
+                .map(|value| value
+                    .as_integer()
+                    .filter(|n| (min..=max).contains(n))
+                    .ok_or_else(|| format!("{}: expected an integer within [{}, {}]", key, min, max).into()))
+                .transpose()
+        };
+        let name = |key: &str| -> error::Result<Option<&str>> {
+            table
+                .get(key)
+                .map(|value| value.as_str().ok_or_else(|| format!("{}: expected a string", key).into()))
+                .transpose()
+        };
+
+        let mut state = WindowState::default();
+        let (x, y) = (int("x", i32::MIN as i64, i32::MAX as i64)?, int("y", i32::MIN as i64, i32::MAX as i64)?);
+        state.position = match (x, y) {
+            (Some(x), Some(y)) => Some((x as i32, y as i32)),
+            (None, None) => None,
+            _ => return Err(String::from("`x` and `y` should be given together").into()),
+        };
+        let (width, height) = (int("width", 1, MAX_WINDOW_SIZE)?, int("height", 1, MAX_WINDOW_SIZE)?);
+        state.size = match (width, height) {
+            (Some(width), Some(height)) => Some((width as u32, height as u32)),
+            (None, None) => None,
+            _ => return Err(String::from("`width` and `height` should be given together").into()),
+        };
+        if let Some(fullscreen) = name("fullscreen")? {
+            state.fullscreen = fullscreen_from_name(fullscreen)
+                .ok_or_else(|| format!("fullscreen: unknown mode `{}`, expected off, desktop or exclusive",
+                    fullscreen))?;
+        }
+        if let Some(present_mode) = name("present_mode")? {
+            state.present_mode = PresentMode::from_name(present_mode)
+                .ok_or_else(|| format!("present_mode: unknown mode `{}`, expected vsync or immediate",
+                    present_mode))?;
+        }
+        if let Some(max_fps) = int("max_fps", 0, MAX_FPS)? {
+            state.max_fps = max_fps as u32;
+        }
+        Ok(state)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut table = toml::Table::new();
+        if let Some((x, y)) = self.position {
+            table.insert(String::from("x"), toml::Value::from(x));
+            table.insert(String::from("y"), toml::Value::from(y));
+        }
+        if let Some((width, height)) = self.size {
+            table.insert(String::from("width"), toml::Value::from(width as i64));
+            table.insert(String::from("height"), toml::Value::from(height as i64));
+        }
+        table.insert(String::from("fullscreen"), toml::Value::from(fullscreen_name(self.fullscreen)));
+        table.insert(String::from("present_mode"), toml::Value::from(self.present_mode.name()));
+        table.insert(String::from("max_fps"), toml::Value::from(self.max_fps as i64));
+        table.to_string()
+    }
+
+    pub fn save(&self, path: &Path) -> error::Result<()> {
+        fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+}